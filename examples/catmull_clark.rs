@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use cgmath::{ElementWise, EuclideanSpace, Point3};
 use pep_mesh::{
     halfedge::{HalfEdgeMesh, VertexRef},
@@ -51,13 +49,13 @@ impl From<PropertyMap> for VData {
 
 impl ToPropertyMap for VData {
     fn to_proprety_map(&self) -> PropertyMap {
-        let mut map = HashMap::new();
+        let mut props = PropertyMap::default();
 
-        map.insert("x".into(), Property::F32(self.pos.x));
-        map.insert("y".into(), Property::F32(self.pos.y));
-        map.insert("z".into(), Property::F32(self.pos.z));
+        props.map.insert("x".into(), Property::F32(self.pos.x));
+        props.map.insert("y".into(), Property::F32(self.pos.y));
+        props.map.insert("z".into(), Property::F32(self.pos.z));
 
-        PropertyMap { map }
+        props
     }
 }
 
@@ -134,9 +132,7 @@ fn catmull_clark(mesh: &mut HalfEdgeMesh<VData, EData, FData>, iter_times: u32)
                     } else {
                         let pos_f1 = he.face(mesh).data(mesh).new_pos;
                         let pos_f2 = twin.face(mesh).data(mesh).new_pos;
-                        if pos_f1.is_some() && pos_f2.is_some() {
-                            let pos_f1 = pos_f1.unwrap();
-                            let pos_f2 = pos_f2.unwrap();
+                        if let (Some(pos_f1), Some(pos_f2)) = (pos_f1, pos_f2) {
                             he.data_mut(mesh).new_pos = Some(
                                 pos_v1
                                     .add_element_wise(pos_v2)
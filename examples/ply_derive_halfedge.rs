@@ -0,0 +1,30 @@
+use pep_mesh::{
+    halfedge::HalfEdgeMesh,
+    io::{
+        self,
+        ply::{FromPropertyMap, ToPropertyMap},
+    },
+};
+
+#[derive(Default, FromPropertyMap, ToPropertyMap)]
+struct VData {
+    #[ply(x, y, z)]
+    position: [f32; 3],
+    #[ply(red, green, blue, default = 255)]
+    color: [u8; 3],
+}
+
+fn main() {
+    let path = "examples/color_cube.ply";
+    let mesh: HalfEdgeMesh<VData, (), ()> =
+        io::ply::load_to_halfedge(path).expect("Failed to load ply mesh");
+
+    println!("# vertices: {}", mesh.num_vertices());
+    for vref in mesh.vertices() {
+        let data = mesh.vertex_data(&vref);
+        println!("{:?} {:?}", data.position, data.color);
+    }
+
+    let path = "color_cube.ply";
+    io::ply::save_halfedge(path, &mesh).expect("Failed to save ply mesh");
+}
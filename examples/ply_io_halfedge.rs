@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use cgmath::Point3;
 use pep_mesh::{
     halfedge::HalfEdgeMesh,
@@ -62,17 +60,21 @@ impl From<PropertyMap> for VData {
 
 impl ToPropertyMap for VData {
     fn to_proprety_map(&self) -> PropertyMap {
-        let mut map = HashMap::new();
+        let mut props = PropertyMap::default();
 
-        map.insert("x".into(), Property::F32(self.position.x));
-        map.insert("y".into(), Property::F32(self.position.y));
-        map.insert("z".into(), Property::F32(self.position.z));
+        props.map.insert("x".into(), Property::F32(self.position.x));
+        props.map.insert("y".into(), Property::F32(self.position.y));
+        props.map.insert("z".into(), Property::F32(self.position.z));
 
-        map.insert("red".into(), Property::F32(self.color[0]));
-        map.insert("green".into(), Property::F32(self.color[1]));
-        map.insert("blue".into(), Property::F32(self.color[2]));
+        props.map.insert("red".into(), Property::F32(self.color[0]));
+        props
+            .map
+            .insert("green".into(), Property::F32(self.color[1]));
+        props
+            .map
+            .insert("blue".into(), Property::F32(self.color[2]));
 
-        PropertyMap { map }
+        props
     }
 }
 
@@ -0,0 +1,251 @@
+//! derive macros of `FromPropertyMap` and `ToPropertyMap` for `pep-mesh`,
+//! enabled by the `derive` feature of `pep-mesh`
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Data, DeriveInput, Expr, Field, Fields, Ident, LitStr, Token,
+};
+
+/// implement `FromPropertyMap`
+///
+/// fields are read from the property with the same name, which can be changed by `#[ply(...)]`
+/// * `#[ply(rename = "name")]` or `#[ply(name)]` reads property `name`
+/// * `#[ply(x, y, z)]` reads a group of properties into a field which is `From<[T; 3]>`
+/// * `#[ply(default = value)]` is used when the property is missing,
+///   it is used for each missing property of a group, `Default::default()` is used if not given
+/// * `#[ply(skip)]` leaves the field as `Default::default()`
+///
+/// numeric properties are converted to the type of the field (or of the group element)
+/// by `PropertyValue`, a value that can't be kept (e.g. out of range) is an error of
+/// `try_from_proprety_map()`, which is used by loaders, and is treated as missing by
+/// `from_proprety_map()`
+#[proc_macro_derive(FromPropertyMap, attributes(ply))]
+pub fn derive_from_property_map(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand_from_property_map(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// implement `ToPropertyMap`, properties are written in the order of fields
+///
+/// it accepts the same `#[ply(...)]` attributes as `FromPropertyMap`,
+/// a field of group is indexed by `field[i]` and `skip` fields are not written
+#[proc_macro_derive(ToPropertyMap, attributes(ply))]
+pub fn derive_to_property_map(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand_to_property_map(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_from_property_map(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = named_fields(input)?
+        .into_iter()
+        .map(|field| {
+            let ident = &field.ident;
+            let attrs = FieldAttrs::from_field(field)?;
+            if attrs.skip {
+                let field = quote!(#ident: ::core::default::Default::default());
+                return Ok((field.clone(), field));
+            }
+
+            let default = match &attrs.default {
+                Some(expr) => quote!(#expr),
+                None => quote!(::core::default::Default::default()),
+            };
+            let lenient = attrs.names.iter().map(|name| {
+                quote! {
+                    props
+                        .map
+                        .get(#name)
+                        .and_then(::pep_mesh::io::ply::PropertyValue::from_property)
+                        .unwrap_or_else(|| #default)
+                }
+            });
+            let strict = attrs.names.iter().map(|name| {
+                quote! {
+                    match props.map.get(#name) {
+                        ::core::option::Option::Some(_) => props.get(#name)?,
+                        ::core::option::Option::None => #default,
+                    }
+                }
+            });
+            if attrs.names.len() == 1 {
+                Ok((quote!(#ident: #(#lenient)*), quote!(#ident: #(#strict)*)))
+            } else {
+                Ok((
+                    quote!(#ident: ::core::convert::From::from([#(#lenient),*])),
+                    quote!(#ident: ::core::convert::From::from([#(#strict),*])),
+                ))
+            }
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    let (lenient, strict): (Vec<_>, Vec<_>) = fields.into_iter().unzip();
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::pep_mesh::io::ply::FromPropertyMap for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn from_proprety_map(props: ::pep_mesh::io::ply::PropertyMap) -> Self {
+                Self {
+                    #(#lenient,)*
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn try_from_proprety_map(
+                props: ::pep_mesh::io::ply::PropertyMap,
+            ) -> ::core::result::Result<Self, ::pep_mesh::io::ply::PropertyError> {
+                ::core::result::Result::Ok(Self {
+                    #(#strict,)*
+                })
+            }
+        }
+    })
+}
+
+fn expand_to_property_map(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let inserts = named_fields(input)?
+        .into_iter()
+        .map(|field| {
+            let ident = &field.ident;
+            let attrs = FieldAttrs::from_field(field)?;
+            if attrs.skip {
+                return Ok(quote!());
+            }
+
+            let is_group = attrs.names.len() > 1;
+            let inserts = attrs.names.iter().enumerate().map(|(i, name)| {
+                let value = if is_group {
+                    quote!(&self.#ident[#i])
+                } else {
+                    quote!(&self.#ident)
+                };
+                quote! {
+                    props.map.insert(
+                        #name.into(),
+                        ::pep_mesh::io::ply::PropertyValue::to_property(#value),
+                    );
+                }
+            });
+            Ok(quote!(#(#inserts)*))
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::pep_mesh::io::ply::ToPropertyMap for #name #ty_generics #where_clause {
+            #[allow(unused_mut)]
+            fn to_proprety_map(&self) -> ::pep_mesh::io::ply::PropertyMap {
+                let mut props = ::pep_mesh::io::ply::PropertyMap::default();
+                #(#inserts)*
+                props
+            }
+        }
+    })
+}
+
+/// fields of a struct with named fields, a unit struct has no fields
+fn named_fields(input: &DeriveInput) -> syn::Result<Vec<&Field>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(fields.named.iter().collect()),
+            Fields::Unit => Ok(vec![]),
+            Fields::Unnamed(_) => Err(syn::Error::new_spanned(
+                &input.ident,
+                "tuple structs are not supported, use a struct with named fields",
+            )),
+        },
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            "only structs with named fields are supported",
+        )),
+    }
+}
+
+/// an argument of `#[ply(...)]`
+enum PlyArg {
+    Name(LitStr),
+    Default(Expr),
+    Skip,
+}
+
+impl Parse for PlyArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return Ok(PlyArg::Name(input.parse()?));
+        }
+
+        let ident = input.call(Ident::parse_any)?.unraw();
+        if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            match ident.to_string().as_str() {
+                "rename" => Ok(PlyArg::Name(input.parse()?)),
+                "default" => Ok(PlyArg::Default(input.parse()?)),
+                _ => Err(syn::Error::new(
+                    ident.span(),
+                    "unknown argument, expect `rename = \"...\"` or `default = ...`",
+                )),
+            }
+        } else if ident == "skip" {
+            Ok(PlyArg::Skip)
+        } else {
+            Ok(PlyArg::Name(LitStr::new(&ident.to_string(), ident.span())))
+        }
+    }
+}
+
+struct FieldAttrs {
+    /// names of properties, more than one for a group
+    names: Vec<LitStr>,
+    default: Option<Expr>,
+    skip: bool,
+}
+
+impl FieldAttrs {
+    fn from_field(field: &Field) -> syn::Result<Self> {
+        let mut attrs = Self {
+            names: vec![],
+            default: None,
+            skip: false,
+        };
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("ply"))
+        {
+            let args = attr.parse_args_with(Punctuated::<PlyArg, Token![,]>::parse_terminated)?;
+            for arg in args {
+                match arg {
+                    PlyArg::Name(name) => attrs.names.push(name),
+                    PlyArg::Default(expr) => attrs.default = Some(expr),
+                    PlyArg::Skip => attrs.skip = true,
+                }
+            }
+        }
+
+        if attrs.skip && (!attrs.names.is_empty() || attrs.default.is_some()) {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`skip` can't be used with other arguments",
+            ));
+        }
+        if attrs.names.is_empty() {
+            // fields of named struct always have an ident
+            let ident = field.ident.as_ref().unwrap().unraw();
+            attrs
+                .names
+                .push(LitStr::new(&ident.to_string(), ident.span()));
+        }
+        Ok(attrs)
+    }
+}
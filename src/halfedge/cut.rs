@@ -0,0 +1,269 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{Face, HalfEdge, HalfEdgeMesh, HalfEdgeRef, Vertex, VertexRef};
+
+impl<VData, EData, FData> HalfEdgeMesh<VData, EData, FData>
+where
+    VData: Clone,
+    EData: Clone,
+    FData: Default,
+{
+    /// cut the mesh along `edges`, making each of them two boundary edges
+    ///
+    /// a vertex whose surrounding faces are separated by the cut is duplicated, one copy for each
+    /// separated fan, vertex data is cloned to the new copies, and so is edge data to the new side
+    /// of each cut edge; edges that are already on boundary are ignored
+    ///
+    /// return `(original, duplicated)` pairs of all created vertices
+    ///
+    /// Notice:
+    /// * boundary faces are recreated and faces are renumbered, so all refs got before are invalidated
+    /// * ids of vertices and half-edges are kept, new ones are appended
+    pub fn cut_edges(&mut self, edges: &[HalfEdgeRef]) -> Vec<(VertexRef, VertexRef)> {
+        let mut cut = HashSet::new();
+        let mut affected = vec![];
+        for heref in edges {
+            assert!(self.is_halfedge_ref_valid(heref));
+            let he = heref.id;
+            let twin = self.halfedges[he].twin;
+            if self.is_boundary_halfedge(he)
+                || self.is_boundary_halfedge(twin)
+                || !cut.insert(self.halfedges[he].edge)
+            {
+                continue;
+            }
+
+            let new_edge = self.edges_data.len();
+            self.edges_data
+                .push(self.edges_data[self.halfedges[he].edge].clone());
+            let he_twin = HalfEdge {
+                id: self.halfedges.len(),
+                edge: self.halfedges[he].edge,
+                next: usize::MAX,
+                twin: he,
+                vertex: self.halfedges[twin].vertex,
+                face: usize::MAX,
+            };
+            let twin_twin = HalfEdge {
+                id: self.halfedges.len() + 1,
+                edge: new_edge,
+                next: usize::MAX,
+                twin,
+                vertex: self.halfedges[he].vertex,
+                face: usize::MAX,
+            };
+            self.halfedges[he].twin = he_twin.id;
+            self.halfedges[twin].twin = twin_twin.id;
+            self.halfedges[twin].edge = new_edge;
+            affected.push(he_twin.vertex);
+            affected.push(twin_twin.vertex);
+            self.halfedges.push(he_twin);
+            self.halfedges.push(twin_twin);
+        }
+
+        affected.sort_unstable();
+        affected.dedup();
+        let affected_set = affected.iter().copied().collect::<HashSet<_>>();
+        let mut outgoing = HashMap::<usize, Vec<usize>>::new();
+        for he in &self.halfedges {
+            if affected_set.contains(&he.vertex) && !self.is_boundary_halfedge(he.id) {
+                outgoing.entry(he.vertex).or_default().push(he.id);
+            }
+        }
+
+        // split vertices, one copy for each fan of interior faces
+        let mut duplicated = vec![];
+        let mut visited = HashSet::new();
+        for v in affected {
+            let mut first = true;
+            for &he in &outgoing[&v] {
+                if visited.contains(&he) {
+                    continue;
+                }
+                let start = self.fan_start(he);
+                let out_boundary = self.halfedges[self.prev_halfedge(start)].twin;
+
+                let target = if first {
+                    v
+                } else {
+                    let vertex = Vertex {
+                        id: self.vertices.len(),
+                        halfedge: usize::MAX,
+                    };
+                    self.vertices_data.push(self.vertices_data[v].clone());
+                    self.vertices.push(vertex);
+                    duplicated.push((v, self.vertices.len() - 1));
+                    self.vertices.len() - 1
+                };
+                first = false;
+
+                let mut it = start;
+                loop {
+                    visited.insert(it);
+                    self.halfedges[it].vertex = target;
+                    let twin = self.halfedges[it].twin;
+                    if self.is_boundary_halfedge(twin) {
+                        break;
+                    }
+                    it = self.halfedges[twin].next;
+                }
+                self.halfedges[out_boundary].vertex = target;
+                self.vertices[target].halfedge = out_boundary;
+            }
+        }
+
+        // relink boundary loops
+        let boundary_halfedges = (0..self.halfedges.len())
+            .filter(|he| self.is_boundary_halfedge(*he))
+            .collect::<Vec<_>>();
+        for &he in &boundary_halfedges {
+            let start = self.fan_start(self.halfedges[he].twin);
+            self.halfedges[he].next = self.halfedges[self.prev_halfedge(start)].twin;
+        }
+
+        // recreate boundary faces, interior faces keep their order
+        let faces = std::mem::take(&mut self.faces);
+        let faces_data = std::mem::take(&mut self.faces_data);
+        let mut face_map = vec![usize::MAX; faces.len()];
+        for (face, data) in faces.into_iter().zip(faces_data) {
+            if !face.is_boundary {
+                face_map[face.id] = self.faces.len();
+                self.faces.push(Face {
+                    id: self.faces.len(),
+                    halfedge: face.halfedge,
+                    is_boundary: false,
+                });
+                self.faces_data.push(data);
+            }
+        }
+        for he in &mut self.halfedges {
+            he.face = face_map.get(he.face).copied().unwrap_or(usize::MAX);
+        }
+        for &he in &boundary_halfedges {
+            if self.halfedges[he].face != usize::MAX {
+                continue;
+            }
+            let face = Face {
+                id: self.faces.len(),
+                halfedge: he,
+                is_boundary: true,
+            };
+            let mut it = he;
+            loop {
+                self.halfedges[it].face = face.id;
+                it = self.halfedges[it].next;
+                if it == he {
+                    break;
+                }
+            }
+            self.faces.push(face);
+            self.faces_data.push(FData::default());
+        }
+
+        self.token = self.token.wrapping_add(1);
+        duplicated
+            .into_iter()
+            .map(|(original, copy)| (self.vertex_ref(original), self.vertex_ref(copy)))
+            .collect()
+    }
+}
+
+impl<VData, EData, FData> HalfEdgeMesh<VData, EData, FData> {
+    fn is_boundary_halfedge(&self, he: usize) -> bool {
+        let face = self.halfedges[he].face;
+        face == usize::MAX || self.faces[face].is_boundary
+    }
+
+    fn prev_halfedge(&self, he: usize) -> usize {
+        let mut it = he;
+        loop {
+            let next = self.halfedges[it].next;
+            if next == he {
+                return it;
+            }
+            it = next;
+        }
+    }
+
+    /// rotate an interior outgoing half-edge around its vertex until the previous one is on boundary
+    fn fan_start(&self, he: usize) -> usize {
+        let mut it = he;
+        loop {
+            let prev_twin = self.halfedges[self.prev_halfedge(it)].twin;
+            if self.is_boundary_halfedge(prev_twin) || prev_twin == he {
+                return it;
+            }
+            it = prev_twin;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn cut_closed_mesh() {
+        // octahedron with apexes 0 and 5, vertex data is the input id
+        let faces = vec![
+            vec![0, 1, 2],
+            vec![0, 2, 3],
+            vec![0, 3, 4],
+            vec![0, 4, 1],
+            vec![5, 2, 1],
+            vec![5, 3, 2],
+            vec![5, 4, 3],
+            vec![5, 1, 4],
+        ];
+        let vertices = (0..6).map(|v| (v, v)).collect();
+        let mut mesh: HalfEdgeMesh<usize, (), ()> =
+            HalfEdgeMesh::new(faces, vertices, HashMap::new(), HashMap::new());
+        assert_eq!(mesh.num_faces_with_boundary(), 8);
+
+        let find = |mesh: &HalfEdgeMesh<usize, (), ()>, a: usize, b: usize| {
+            mesh.halfedges()
+                .find(|he| {
+                    *he.vertex(mesh).data(mesh) == a && *he.next(mesh).vertex(mesh).data(mesh) == b
+                })
+                .unwrap()
+        };
+        // path 0 - 1 - 5 over the equator vertex 1
+        let path = [find(&mesh, 0, 1), find(&mesh, 1, 5)];
+        let duplicated = mesh.cut_edges(&path);
+
+        assert_eq!(duplicated.len(), 1);
+        let (original, copy) = duplicated[0];
+        assert_eq!(*original.data(&mesh), 1);
+        assert_eq!(*copy.data(&mesh), 1);
+        assert_eq!(mesh.num_vertices(), 7);
+        assert_eq!(mesh.num_faces(), 8);
+        assert_eq!(mesh.num_faces_with_boundary(), 9);
+
+        let boundary = mesh.faces().find(|face| face.is_boundary(&mesh)).unwrap();
+        assert_eq!(boundary.degree(&mesh), 4);
+        let start = boundary.halfedge(&mesh);
+        let mut he = start;
+        let mut loop_vertices = vec![];
+        loop {
+            assert!(he.twin(&mesh).twin(&mesh) == he);
+            loop_vertices.push(he.vertex(&mesh));
+            he = he.next(&mesh);
+            if he == start {
+                break;
+            }
+        }
+        assert!(loop_vertices.contains(&original));
+        assert!(loop_vertices.contains(&copy));
+        let mut ids = loop_vertices
+            .iter()
+            .map(|v| *v.data(&mesh))
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![0, 1, 1, 5]);
+
+        let on_boundary = mesh.vertices().filter(|v| v.on_boundary(&mesh)).count();
+        assert_eq!(on_boundary, 4);
+    }
+}
@@ -1,5 +1,6 @@
 use super::{HalfEdgeMesh, HalfEdgeRef, VertexRef};
 
+#[derive(Clone)]
 pub(crate) struct Face {
     pub(crate) id: usize,
     pub(crate) halfedge: usize,
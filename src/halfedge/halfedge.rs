@@ -1,5 +1,6 @@
 use super::{FaceRef, HalfEdgeMesh, VertexRef};
 
+#[derive(Clone)]
 pub(crate) struct HalfEdge {
     pub(crate) id: usize,
     pub(crate) edge: usize,
@@ -1,15 +1,16 @@
 use std::collections::HashMap;
 
 use super::{Face, FaceRef, HalfEdge, HalfEdgeRef, Vertex, VertexRef};
+use crate::util::{find, union};
 
 pub struct HalfEdgeMesh<VData, EData, FData> {
     pub(crate) vertices: Vec<Vertex>,
     pub(crate) halfedges: Vec<HalfEdge>,
     pub(crate) faces: Vec<Face>,
-    vertices_data: Vec<VData>,
-    edges_data: Vec<EData>,
-    faces_data: Vec<FData>,
-    token: u128,
+    pub(crate) vertices_data: Vec<VData>,
+    pub(crate) edges_data: Vec<EData>,
+    pub(crate) faces_data: Vec<FData>,
+    pub(crate) token: u128,
 }
 
 impl<VData, EData, FData> HalfEdgeMesh<VData, EData, FData>
@@ -50,7 +51,7 @@ where
                 is_boundary: false,
             };
             faces.push(he_face);
-            faces_data.push(in_faces_data.remove(&fid).unwrap_or(FData::default()));
+            faces_data.push(in_faces_data.remove(&fid).unwrap_or_default());
 
             for vid_input in face {
                 if !vertices_map.contains_key(vid_input) {
@@ -59,11 +60,7 @@ where
                         halfedge: usize::MAX,
                     };
                     vertices_map.insert(vid_input, vert.id);
-                    vertices_data.push(
-                        in_vertices_data
-                            .remove(&vid_input)
-                            .unwrap_or(VData::default()),
-                    );
+                    vertices_data.push(in_vertices_data.remove(vid_input).unwrap_or_default());
                     vertices.push(vert);
                 }
             }
@@ -83,9 +80,9 @@ where
                 };
 
                 let key = (a.min(b), a.max(b));
-                if !edges_map.contains_key(&key) {
-                    edges_map.insert(key, edges_data.len());
-                    edges_data.push(in_edges_data.remove(&key).unwrap_or(EData::default()));
+                if let std::collections::hash_map::Entry::Vacant(entry) = edges_map.entry(key) {
+                    entry.insert(edges_data.len());
+                    edges_data.push(in_edges_data.remove(&key).unwrap_or_default());
                 }
             }
         }
@@ -219,7 +216,150 @@ where
     }
 }
 
+/// check whether `faces` can be used by `HalfEdgeMesh::new`, return reason if it can't
+///
+/// faces must have at least 3 different vertices, an edge can be used by at most two faces with
+/// opposite directions, and faces around each vertex must be connected through their edges
+pub(crate) fn check_manifold(faces: &[Vec<usize>]) -> Result<(), String> {
+    if faces.is_empty() {
+        return Err("there are no faces".to_owned());
+    }
+
+    let mut first_corner = Vec::with_capacity(faces.len());
+    let mut num_corners = 0;
+    let mut directed_edges = HashMap::new();
+    for (fid, face) in faces.iter().enumerate() {
+        first_corner.push(num_corners);
+        num_corners += face.len();
+        if face.len() < 3 {
+            return Err(format!("face {} has less than 3 vertices", fid));
+        }
+        for i in 0..face.len() {
+            if face[i + 1..].contains(&face[i]) {
+                return Err(format!("face {} uses vertex {} twice", fid, face[i]));
+            }
+            let edge = (face[i], face[(i + 1) % face.len()]);
+            if directed_edges.insert(edge, (fid, i)).is_some() {
+                return Err(format!(
+                    "edge ({}, {}) is used by more than one face in the same direction",
+                    edge.0, edge.1
+                ));
+            }
+        }
+    }
+
+    // corners around a vertex belong to the same fan if they are connected by edges
+    let mut parents = (0..num_corners).collect::<Vec<_>>();
+    for (&(a, b), &(fid, i)) in &directed_edges {
+        if let Some(&(twin_fid, j)) = directed_edges.get(&(b, a)) {
+            let len = faces[fid].len();
+            let twin_len = faces[twin_fid].len();
+            // `a` is corner `i` of this face and corner `j + 1` of the twin face, and so on
+            let pairs = [
+                (
+                    first_corner[fid] + i,
+                    first_corner[twin_fid] + (j + 1) % twin_len,
+                ),
+                (
+                    first_corner[fid] + (i + 1) % len,
+                    first_corner[twin_fid] + j,
+                ),
+            ];
+            for (x, y) in pairs {
+                union(&mut parents, x, y);
+            }
+        }
+    }
+    let mut fan_of_vertex = HashMap::new();
+    for (fid, face) in faces.iter().enumerate() {
+        for (i, v) in face.iter().enumerate() {
+            let fan = find(&mut parents, first_corner[fid] + i);
+            if *fan_of_vertex.entry(*v).or_insert(fan) != fan {
+                return Err(format!("vertex {} is non-manifold", v));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 impl<VData, EData, FData> HalfEdgeMesh<VData, EData, FData> {
+    pub(crate) fn vertex_ref(&self, id: usize) -> VertexRef {
+        VertexRef {
+            id,
+            token: self.token,
+        }
+    }
+
+    pub(crate) fn face_ref(&self, id: usize) -> FaceRef {
+        FaceRef {
+            id,
+            token: self.token,
+        }
+    }
+
+    /// convert data of vertices, edges and faces, refs of the mesh are still valid,
+    /// stop at the first conversion that fails
+    pub(crate) fn try_map_data<VData2, EData2, FData2, E, VMap, EMap, FMap>(
+        self,
+        vertex_map: VMap,
+        edge_map: EMap,
+        face_map: FMap,
+    ) -> Result<HalfEdgeMesh<VData2, EData2, FData2>, E>
+    where
+        VMap: FnMut((usize, VData)) -> Result<VData2, E>,
+        EMap: FnMut((usize, EData)) -> Result<EData2, E>,
+        FMap: FnMut((usize, FData)) -> Result<FData2, E>,
+    {
+        Ok(HalfEdgeMesh {
+            vertices: self.vertices,
+            halfedges: self.halfedges,
+            faces: self.faces,
+            vertices_data: self
+                .vertices_data
+                .into_iter()
+                .enumerate()
+                .map(vertex_map)
+                .collect::<Result<_, _>>()?,
+            edges_data: self
+                .edges_data
+                .into_iter()
+                .enumerate()
+                .map(edge_map)
+                .collect::<Result<_, _>>()?,
+            faces_data: self
+                .faces_data
+                .into_iter()
+                .enumerate()
+                .map(face_map)
+                .collect::<Result<_, _>>()?,
+            token: self.token,
+        })
+    }
+
+    /// copy of the mesh with data converted from data of the mesh
+    pub(crate) fn map_data_ref<VData2, EData2, FData2, VMap, EMap, FMap>(
+        &self,
+        vertex_map: VMap,
+        edge_map: EMap,
+        face_map: FMap,
+    ) -> HalfEdgeMesh<VData2, EData2, FData2>
+    where
+        VMap: FnMut(&VData) -> VData2,
+        EMap: FnMut(&EData) -> EData2,
+        FMap: FnMut(&FData) -> FData2,
+    {
+        HalfEdgeMesh {
+            vertices: self.vertices.clone(),
+            halfedges: self.halfedges.clone(),
+            faces: self.faces.clone(),
+            vertices_data: self.vertices_data.iter().map(vertex_map).collect(),
+            edges_data: self.edges_data.iter().map(edge_map).collect(),
+            faces_data: self.faces_data.iter().map(face_map).collect(),
+            token: self.token,
+        }
+    }
+
     pub fn is_vertex_ref_valid(&self, vref: &VertexRef) -> bool {
         self.token == vref.token && vref.id < self.vertices.len()
     }
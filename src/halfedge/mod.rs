@@ -1,4 +1,6 @@
+mod cut;
 mod face;
+#[allow(clippy::module_inception)]
 mod halfedge;
 mod halfedge_mesh;
 mod vertex;
@@ -1,5 +1,6 @@
 use super::{FaceRef, HalfEdgeMesh, HalfEdgeRef};
 
+#[derive(Clone)]
 pub(crate) struct Vertex {
     pub(crate) id: usize,
     pub(crate) halfedge: usize,
@@ -41,10 +42,7 @@ impl VertexRef {
         }
     }
 
-    pub fn face<'a, VData, EData, FData>(
-        &self,
-        mesh: &HalfEdgeMesh<VData, EData, FData>,
-    ) -> FaceRef {
+    pub fn face<VData, EData, FData>(&self, mesh: &HalfEdgeMesh<VData, EData, FData>) -> FaceRef {
         assert!(mesh.is_vertex_ref_valid(self));
         self.halfedge(mesh).face(mesh)
     }
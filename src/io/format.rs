@@ -0,0 +1,564 @@
+use std::{borrow::Cow, collections::HashMap, io::Read, path::Path};
+
+use crate::{
+    halfedge::HalfEdgeMesh,
+    io::{
+        obj, off,
+        ply::{self, FromPropertyMap, Property, PropertyMap, ToPropertyMap},
+        stl, LoadError, SaveError,
+    },
+    trimesh::{TriMesh, VertexAttribute},
+};
+
+/// half-edge mesh whose data are the properties of the file
+pub type PropertyHalfEdgeMesh = HalfEdgeMesh<PropertyMap, PropertyMap, PropertyMap>;
+
+/// a mesh file format that can be registered to `FormatRegistry`
+///
+/// methods which are not overridden return an error saying that the format doesn't support them
+pub trait MeshFormat {
+    /// name of the format used in error messages
+    fn name(&self) -> &str;
+
+    /// lowercase file extensions without `.`
+    fn extensions(&self) -> &[&str];
+
+    /// whether a file of `file_size` bytes starting with `header` is of this format,
+    /// `header` holds the first 256 bytes of the file, or less if the file is shorter
+    fn sniff(&self, _header: &[u8], _file_size: u64) -> bool {
+        false
+    }
+
+    fn load_trimesh(&self, _path: &Path) -> Result<TriMesh, LoadError> {
+        Err(LoadError::UnsupportedFormat(format!(
+            "{} doesn\'t support loading TriMesh",
+            self.name()
+        )))
+    }
+
+    fn save_trimesh(&self, _path: &Path, _mesh: &TriMesh) -> Result<(), SaveError> {
+        Err(SaveError::UnsupportedFormat(format!(
+            "{} doesn\'t support saving TriMesh",
+            self.name()
+        )))
+    }
+
+    fn load_halfedge(&self, _path: &Path) -> Result<PropertyHalfEdgeMesh, LoadError> {
+        Err(LoadError::UnsupportedFormat(format!(
+            "{} doesn\'t support loading HalfEdgeMesh",
+            self.name()
+        )))
+    }
+
+    fn save_halfedge(&self, _path: &Path, _mesh: &PropertyHalfEdgeMesh) -> Result<(), SaveError> {
+        Err(SaveError::UnsupportedFormat(format!(
+            "{} doesn\'t support saving HalfEdgeMesh",
+            self.name()
+        )))
+    }
+}
+
+/// formats used by format-agnostic load and save
+///
+/// when loading, a format whose `sniff()` accepts the file is used, otherwise the format is chosen
+/// by the file extension; when saving, the format is chosen by the file extension.
+/// formats registered later take precedence
+pub struct FormatRegistry {
+    formats: Vec<Box<dyn MeshFormat>>,
+}
+
+impl Default for FormatRegistry {
+    /// registry with all builtin formats
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(PlyFormat);
+        registry.register(ObjFormat);
+        registry.register(StlFormat);
+        registry.register(OffFormat);
+        #[cfg(feature = "gltf")]
+        registry.register(GltfFormat);
+        registry
+    }
+}
+
+impl FormatRegistry {
+    /// registry without any format
+    pub fn new() -> Self {
+        Self { formats: vec![] }
+    }
+
+    pub fn register<Format: MeshFormat + 'static>(&mut self, format: Format) {
+        self.formats.push(Box::new(format));
+    }
+
+    pub fn load_trimesh<Path: AsRef<std::path::Path>>(
+        &self,
+        path: Path,
+    ) -> Result<TriMesh, LoadError> {
+        let path = path.as_ref();
+        self.format_to_load(path)?.load_trimesh(path)
+    }
+
+    pub fn save_trimesh<Path: AsRef<std::path::Path>>(
+        &self,
+        path: Path,
+        mesh: &TriMesh,
+    ) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        self.format_to_save(path)?.save_trimesh(path, mesh)
+    }
+
+    /// data are created from properties in the same way as the `load_to_halfedge` of the format
+    pub fn load_halfedge<Path, VData, EData, FData>(
+        &self,
+        path: Path,
+    ) -> Result<HalfEdgeMesh<VData, EData, FData>, LoadError>
+    where
+        Path: AsRef<std::path::Path>,
+        VData: FromPropertyMap,
+        EData: FromPropertyMap,
+        FData: FromPropertyMap,
+    {
+        let path = path.as_ref();
+        let mesh = self.format_to_load(path)?.load_halfedge(path)?;
+        mesh.try_map_data(
+            |(v, props)| ply::data_from_props(props, "vertex", v),
+            |(e, props)| ply::data_from_props(props, "edge", e),
+            |(f, props)| ply::data_from_props(props, "face", f),
+        )
+    }
+
+    pub fn save_halfedge<Path, VData, EData, FData>(
+        &self,
+        path: Path,
+        mesh: &HalfEdgeMesh<VData, EData, FData>,
+    ) -> Result<(), SaveError>
+    where
+        Path: AsRef<std::path::Path>,
+        VData: ToPropertyMap,
+        EData: ToPropertyMap,
+        FData: ToPropertyMap,
+    {
+        let path = path.as_ref();
+        let format = self.format_to_save(path)?;
+        let mesh = mesh.map_data_ref(
+            VData::to_proprety_map,
+            EData::to_proprety_map,
+            FData::to_proprety_map,
+        );
+        format.save_halfedge(path, &mesh)
+    }
+
+    fn format_to_load(&self, path: &Path) -> Result<&dyn MeshFormat, LoadError> {
+        let file = std::fs::File::open(path)?;
+        let file_size = file.metadata()?.len();
+        let mut header = Vec::with_capacity(256);
+        file.take(256).read_to_end(&mut header)?;
+
+        self.formats
+            .iter()
+            .rev()
+            .find(|format| format.sniff(&header, file_size))
+            .map(|format| format.as_ref())
+            .or_else(|| self.format_of_extension(path))
+            .ok_or_else(|| {
+                LoadError::UnsupportedFormat(format!("unknown mesh format of '{}'", path.display()))
+            })
+    }
+
+    fn format_to_save(&self, path: &Path) -> Result<&dyn MeshFormat, SaveError> {
+        self.format_of_extension(path).ok_or_else(|| {
+            SaveError::UnsupportedFormat(format!("unknown mesh format of '{}'", path.display()))
+        })
+    }
+
+    fn format_of_extension(&self, path: &Path) -> Option<&dyn MeshFormat> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        self.formats
+            .iter()
+            .rev()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+            .map(|format| format.as_ref())
+    }
+}
+
+/// load a `TriMesh` with builtin formats, see `FormatRegistry`
+pub fn load_trimesh<Path: AsRef<std::path::Path>>(path: Path) -> Result<TriMesh, LoadError> {
+    FormatRegistry::default().load_trimesh(path)
+}
+
+/// save a `TriMesh` with builtin formats, see `FormatRegistry`
+pub fn save_trimesh<Path: AsRef<std::path::Path>>(
+    path: Path,
+    mesh: &TriMesh,
+) -> Result<(), SaveError> {
+    FormatRegistry::default().save_trimesh(path, mesh)
+}
+
+/// load a `HalfEdgeMesh` with builtin formats, see `FormatRegistry`
+pub fn load_halfedge<Path, VData, EData, FData>(
+    path: Path,
+) -> Result<HalfEdgeMesh<VData, EData, FData>, LoadError>
+where
+    Path: AsRef<std::path::Path>,
+    VData: FromPropertyMap,
+    EData: FromPropertyMap,
+    FData: FromPropertyMap,
+{
+    FormatRegistry::default().load_halfedge(path)
+}
+
+/// save a `HalfEdgeMesh` with builtin formats, see `FormatRegistry`
+pub fn save_halfedge<Path, VData, EData, FData>(
+    path: Path,
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+) -> Result<(), SaveError>
+where
+    Path: AsRef<std::path::Path>,
+    VData: ToPropertyMap,
+    EData: ToPropertyMap,
+    FData: ToPropertyMap,
+{
+    FormatRegistry::default().save_halfedge(path, mesh)
+}
+
+/// `.ply`, `TriMesh` has `POSITION`, and `NORMAL`, `TEXCOORD`, `COLOR` if the file has them
+pub struct PlyFormat;
+
+impl MeshFormat for PlyFormat {
+    fn name(&self) -> &str {
+        "ply"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ply"]
+    }
+
+    fn sniff(&self, header: &[u8], _file_size: u64) -> bool {
+        header.starts_with(b"ply\n") || header.starts_with(b"ply\r\n")
+    }
+
+    fn load_trimesh(&self, path: &Path) -> Result<TriMesh, LoadError> {
+        ply::load_to_trimesh(path, |_| HashMap::new(), ply_vertex_to_attributes)
+    }
+
+    fn save_trimesh(&self, path: &Path, mesh: &TriMesh) -> Result<(), SaveError> {
+        mesh.attribute_as::<[f32; 3]>(TriMesh::POSITION)?;
+        ply::save_trimesh(path, mesh, ply_attributes_to_vertex)
+    }
+
+    fn load_halfedge(&self, path: &Path) -> Result<PropertyHalfEdgeMesh, LoadError> {
+        ply::load_to_halfedge(path)
+    }
+
+    fn save_halfedge(&self, path: &Path, mesh: &PropertyHalfEdgeMesh) -> Result<(), SaveError> {
+        ply::save_halfedge(path, mesh)
+    }
+}
+
+/// vertex attributes from ply properties `x`, `y`, `z`, `nx`, `ny`, `nz`, `s`, `t` (or `u`, `v`)
+/// and `red`, `green`, `blue`
+fn ply_vertex_to_attributes(
+    vertex_attributes: &mut HashMap<Cow<'static, str>, VertexAttribute>,
+    props: PropertyMap,
+) {
+    // all vertices of a ply file have the same properties, attributes are created at the first one
+    if let Ok(position) = props.get_vec3(["x", "y", "z"]) {
+        attribute(
+            vertex_attributes,
+            TriMesh::POSITION,
+            VertexAttribute::Float3,
+        )
+        .push_float3(position);
+    }
+    if let Ok(normal) = props.get_vec3(["nx", "ny", "nz"]) {
+        attribute(vertex_attributes, TriMesh::NORMAL, VertexAttribute::Float3).push_float3(normal);
+    }
+    if let Ok(texcoord) = props
+        .get_vec2(["s", "t"])
+        .or_else(|_| props.get_vec2(["u", "v"]))
+    {
+        attribute(
+            vertex_attributes,
+            TriMesh::TEXCOORD,
+            VertexAttribute::Float2,
+        )
+        .push_float2(texcoord);
+    }
+    if let Ok(color) = props.get_color_rgb() {
+        attribute(vertex_attributes, TriMesh::COLOR, VertexAttribute::Float3).push_float3(color);
+    }
+}
+
+fn attribute<'a, T>(
+    vertex_attributes: &'a mut HashMap<Cow<'static, str>, VertexAttribute>,
+    name: &'static str,
+    variant: fn(Vec<T>) -> VertexAttribute,
+) -> &'a mut VertexAttribute {
+    vertex_attributes
+        .entry(name.into())
+        .or_insert_with(|| variant(vec![]))
+}
+
+/// inverse of `ply_vertex_to_attributes`, colors are saved as `U8`
+fn ply_attributes_to_vertex(
+    vertex_attributes: &HashMap<Cow<'static, str>, VertexAttribute>,
+    index: usize,
+) -> PropertyMap {
+    let mut props = PropertyMap::default();
+    let mut insert = |name: &str, keys: &[&'static str]| {
+        let values = match vertex_attributes.get(name) {
+            Some(VertexAttribute::Float2(val)) => val[index].to_vec(),
+            Some(VertexAttribute::Float3(val)) => val[index].to_vec(),
+            _ => return,
+        };
+        for (key, value) in keys.iter().zip(values) {
+            let prop = if name == TriMesh::COLOR {
+                Property::U8((value.clamp(0.0, 1.0) * 255.0).round() as u8)
+            } else {
+                Property::F32(value)
+            };
+            props.map.insert((*key).into(), prop);
+        }
+    };
+    insert(TriMesh::POSITION, &["x", "y", "z"]);
+    insert(TriMesh::NORMAL, &["nx", "ny", "nz"]);
+    insert(TriMesh::TEXCOORD, &["s", "t"]);
+    insert(TriMesh::COLOR, &["red", "green", "blue"]);
+    props
+}
+
+/// `.obj`, groups and materials are dropped
+pub struct ObjFormat;
+
+impl MeshFormat for ObjFormat {
+    fn name(&self) -> &str {
+        "obj"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["obj"]
+    }
+
+    fn load_trimesh(&self, path: &Path) -> Result<TriMesh, LoadError> {
+        Ok(obj::load_to_trimesh(path)?.mesh)
+    }
+
+    fn save_trimesh(&self, path: &Path, mesh: &TriMesh) -> Result<(), SaveError> {
+        obj::save_trimesh(path, mesh, &[], &[])
+    }
+
+    fn load_halfedge(&self, path: &Path) -> Result<PropertyHalfEdgeMesh, LoadError> {
+        Ok(obj::load_to_halfedge(path)?.mesh)
+    }
+
+    fn save_halfedge(&self, path: &Path, mesh: &PropertyHalfEdgeMesh) -> Result<(), SaveError> {
+        obj::save_halfedge(path, mesh, &[], &[])
+    }
+}
+
+/// `.stl`, saved as binary stl
+pub struct StlFormat;
+
+impl MeshFormat for StlFormat {
+    fn name(&self) -> &str {
+        "stl"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["stl"]
+    }
+
+    /// binary stl is detected by its size, which is given by the triangle count after the header
+    fn sniff(&self, header: &[u8], file_size: u64) -> bool {
+        if header.len() >= 84 {
+            let count = u32::from_le_bytes([header[80], header[81], header[82], header[83]]);
+            if file_size == 84 + 50 * count as u64 {
+                return true;
+            }
+        }
+        header.trim_ascii_start().starts_with(b"solid")
+    }
+
+    fn load_trimesh(&self, path: &Path) -> Result<TriMesh, LoadError> {
+        Ok(stl::load_to_trimesh(path)?.mesh)
+    }
+
+    fn save_trimesh(&self, path: &Path, mesh: &TriMesh) -> Result<(), SaveError> {
+        stl::save_trimesh(path, mesh, "", &[], stl::StlEncoding::Binary)
+    }
+
+    fn load_halfedge(&self, path: &Path) -> Result<PropertyHalfEdgeMesh, LoadError> {
+        stl::load_to_halfedge(path)
+    }
+
+    fn save_halfedge(&self, path: &Path, mesh: &PropertyHalfEdgeMesh) -> Result<(), SaveError> {
+        stl::save_halfedge(path, mesh, "", stl::StlEncoding::Binary)
+    }
+}
+
+/// `.off`, including `COFF`, `NOFF` and `STOFF`
+pub struct OffFormat;
+
+impl MeshFormat for OffFormat {
+    fn name(&self) -> &str {
+        "off"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["off"]
+    }
+
+    fn sniff(&self, header: &[u8], _file_size: u64) -> bool {
+        let keyword = header
+            .trim_ascii_start()
+            .split(|b| b.is_ascii_whitespace())
+            .next()
+            .unwrap_or(&[]);
+        keyword
+            .strip_suffix(b"OFF")
+            .is_some_and(|prefix| prefix.iter().all(|b| b"STCN".contains(b)))
+    }
+
+    fn load_trimesh(&self, path: &Path) -> Result<TriMesh, LoadError> {
+        off::load_to_trimesh(path)
+    }
+
+    fn save_trimesh(&self, path: &Path, mesh: &TriMesh) -> Result<(), SaveError> {
+        off::save_trimesh(path, mesh)
+    }
+
+    fn load_halfedge(&self, path: &Path) -> Result<PropertyHalfEdgeMesh, LoadError> {
+        off::load_to_halfedge(path)
+    }
+
+    fn save_halfedge(&self, path: &Path, mesh: &PropertyHalfEdgeMesh) -> Result<(), SaveError> {
+        off::save_halfedge(path, mesh)
+    }
+}
+
+/// `.gltf` and `.glb`, the first primitive of the first mesh is loaded
+#[cfg(feature = "gltf")]
+pub struct GltfFormat;
+
+#[cfg(feature = "gltf")]
+impl MeshFormat for GltfFormat {
+    fn name(&self) -> &str {
+        "gltf"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gltf", "glb"]
+    }
+
+    fn sniff(&self, header: &[u8], _file_size: u64) -> bool {
+        header.starts_with(b"glTF")
+    }
+
+    fn load_trimesh(&self, path: &Path) -> Result<TriMesh, LoadError> {
+        super::gltf::load_to_trimesh(path)?
+            .into_iter()
+            .flat_map(|mesh| mesh.primitives)
+            .next()
+            .map(|primitive| primitive.mesh)
+            .ok_or_else(|| LoadError::parse_in_element("mesh", "gltf file has no primitive"))
+    }
+
+    fn save_trimesh(&self, path: &Path, mesh: &TriMesh) -> Result<(), SaveError> {
+        super::gltf::save_trimesh(path, mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::TriMeshBuilder;
+
+    /// path in the temporary directory, unique to each test
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("pep-mesh-{}-{}", std::process::id(), name))
+    }
+
+    fn triangle() -> TriMesh {
+        TriMeshBuilder::new()
+            .attribute(
+                TriMesh::POSITION,
+                vec![[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            )
+            .indices(vec![0, 1, 2])
+            .build()
+            .unwrap()
+    }
+
+    /// writes a marker and loads a point
+    struct CustomFormat;
+
+    impl MeshFormat for CustomFormat {
+        fn name(&self) -> &str {
+            "custom"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["ply"]
+        }
+
+        fn sniff(&self, header: &[u8], _file_size: u64) -> bool {
+            header.starts_with(b"custom")
+        }
+
+        fn load_trimesh(&self, _path: &Path) -> Result<TriMesh, LoadError> {
+            Ok(TriMesh::new(
+                [(TriMesh::POSITION.into(), vec![[0.0f32; 3]].into())].into(),
+                None,
+            ))
+        }
+
+        fn save_trimesh(&self, path: &Path, _mesh: &TriMesh) -> Result<(), SaveError> {
+            std::fs::write(path, "custom")?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn custom_format_overrides_builtin() {
+        let mut registry = FormatRegistry::default();
+        registry.register(CustomFormat);
+        let path = temp_path("custom.ply");
+        registry.save_trimesh(&path, &triangle()).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"custom");
+        assert_eq!(registry.load_trimesh(&path).unwrap().num_vertices(), 1);
+
+        // builtin ply is still found by its magic bytes
+        FormatRegistry::default()
+            .save_trimesh(&path, &triangle())
+            .unwrap();
+        assert_eq!(registry.load_trimesh(&path).unwrap().num_vertices(), 3);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn format_is_sniffed_before_extension() {
+        let ply_path = temp_path("sniff.ply");
+        let obj_path = temp_path("sniff.obj");
+        save_trimesh(&ply_path, &triangle()).unwrap();
+        std::fs::rename(&ply_path, &obj_path).unwrap();
+        let mesh = load_trimesh(&obj_path).unwrap();
+        assert_eq!(mesh.triangles(), vec![[0, 1, 2]]);
+        std::fs::remove_file(&obj_path).unwrap();
+    }
+
+    #[test]
+    fn unknown_extension_is_unsupported() {
+        let path = temp_path("unknown.xyz");
+        assert!(matches!(
+            save_trimesh(&path, &triangle()),
+            Err(SaveError::UnsupportedFormat(_))
+        ));
+        std::fs::write(&path, "0 0 0\n1 0 0\n0 1 0\n").unwrap();
+        assert!(matches!(
+            load_trimesh(&path),
+            Err(LoadError::UnsupportedFormat(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,526 @@
+mod trimesh;
+
+pub use trimesh::*;
+
+use std::{borrow::Cow, collections::HashMap};
+
+use serde_json::Value;
+
+use super::LoadError;
+use crate::trimesh::{TriMesh, VertexAttribute};
+
+/// a mesh of a gltf file
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>,
+    /// default weights of morph targets
+    pub weights: Vec<f32>,
+    /// names of morph targets given by `extras.targetNames`
+    pub target_names: Vec<String>,
+}
+
+/// a primitive of a gltf mesh, its mode is kept as the topology of `mesh`, line loops become line strips
+pub struct GltfPrimitive {
+    pub mesh: TriMesh,
+    /// index of material in the gltf file
+    pub material: Option<usize>,
+    /// displacements of morph targets, named in the same way as vertex attributes of `mesh`
+    pub targets: Vec<HashMap<Cow<'static, str>, VertexAttribute>>,
+}
+
+const GLB_MAGIC: u32 = 0x4654_6c67;
+const GLB_CHUNK_JSON: u32 = 0x4e4f_534a;
+const GLB_CHUNK_BIN: u32 = 0x004e_4942;
+
+const COMPONENT_BYTE: u64 = 5120;
+const COMPONENT_UNSIGNED_BYTE: u64 = 5121;
+const COMPONENT_SHORT: u64 = 5122;
+const COMPONENT_UNSIGNED_SHORT: u64 = 5123;
+const COMPONENT_UNSIGNED_INT: u64 = 5125;
+const COMPONENT_FLOAT: u64 = 5126;
+
+const TARGET_ARRAY_BUFFER: u64 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u64 = 34963;
+
+/// vertex attribute name of a gltf attribute semantic,
+/// `TEXCOORD_0` and `COLOR_0` are mapped to `TriMesh::TEXCOORD` and `TriMesh::COLOR`,
+/// other semantics are lowercased with the leading `_` of custom semantics removed
+fn attribute_name(semantic: &str) -> Cow<'static, str> {
+    match semantic {
+        "POSITION" => TriMesh::POSITION.into(),
+        "NORMAL" => TriMesh::NORMAL.into(),
+        "TANGENT" => TriMesh::TANGENT.into(),
+        "TEXCOORD_0" => TriMesh::TEXCOORD.into(),
+        "COLOR_0" => TriMesh::COLOR.into(),
+        _ => semantic.trim_start_matches('_').to_lowercase().into(),
+    }
+}
+
+/// inverse of `attribute_name`, names that are not gltf semantics get a leading `_`
+fn attribute_semantic(name: &str) -> String {
+    match name {
+        TriMesh::POSITION => "POSITION".to_owned(),
+        TriMesh::NORMAL => "NORMAL".to_owned(),
+        TriMesh::TANGENT => "TANGENT".to_owned(),
+        TriMesh::TEXCOORD => "TEXCOORD_0".to_owned(),
+        TriMesh::COLOR => "COLOR_0".to_owned(),
+        _ => {
+            let semantic = name.to_uppercase();
+            let is_standard = ["TEXCOORD_", "COLOR_", "JOINTS_", "WEIGHTS_"]
+                .iter()
+                .any(|prefix| {
+                    semantic.strip_prefix(prefix).is_some_and(|set| {
+                        !set.is_empty() && set.bytes().all(|b| b.is_ascii_digit())
+                    })
+                });
+            if is_standard {
+                semantic
+            } else {
+                format!("_{}", semantic)
+            }
+        }
+    }
+}
+
+/// json and buffers of a gltf file
+struct GltfDocument {
+    json: Value,
+    buffers: Vec<Vec<u8>>,
+}
+
+/// values of an accessor, integers are kept exactly in `f64`
+struct AccessorData {
+    values: Vec<f64>,
+    components: usize,
+    /// whether the component type is float or integers are normalized
+    is_float: bool,
+}
+
+impl GltfDocument {
+    /// `bytes` is a `.glb` file or a `.gltf` file, buffers with an uri which is not a data uri
+    /// are loaded relative to `base`, or fail if `base` is `None`
+    fn parse(bytes: &[u8], base: Option<&std::path::Path>) -> Result<Self, LoadError> {
+        let (json, mut glb_buffer) = if bytes.len() >= 12 && read_u32(bytes, 0) == GLB_MAGIC {
+            let (json, bin) = parse_glb(bytes)?;
+            (json, bin.map(|bin| bin.to_vec()))
+        } else {
+            (bytes, None)
+        };
+        let json: Value = serde_json::from_slice(json).map_err(|err| LoadError::Parse {
+            element: None,
+            line: Some(err.line()),
+            offset: None,
+            message: err.to_string(),
+        })?;
+
+        let mut buffers = vec![];
+        for buffer in array(&json, "buffers") {
+            let length = usize_field(buffer, "byteLength").unwrap_or(0);
+            let mut data = match buffer.get("uri").and_then(Value::as_str) {
+                Some(uri) if uri.starts_with("data:") => {
+                    let (_, data) = uri.split_once(";base64,").ok_or_else(|| {
+                        LoadError::parse_in_element("buffer", "data uri must be base64")
+                    })?;
+                    decode_base64(data).ok_or_else(|| {
+                        LoadError::parse_in_element("buffer", "invalid base64 data uri")
+                    })?
+                }
+                Some(uri) => {
+                    let base = base.ok_or_else(|| {
+                        LoadError::parse_in_element(
+                            "buffer",
+                            format!("can't load external buffer '{}'", uri),
+                        )
+                    })?;
+                    let path = base.join(decode_uri(uri));
+                    std::fs::read(&path).map_err(|err| {
+                        let info = format!("failed to load '{}', {}", path.display(), err);
+                        LoadError::Io(std::io::Error::new(err.kind(), info))
+                    })?
+                }
+                None => glb_buffer.take().ok_or_else(|| {
+                    LoadError::parse_in_element(
+                        "buffer",
+                        "buffer without uri must be the glb binary chunk",
+                    )
+                })?,
+            };
+            if data.len() < length {
+                return Err(LoadError::parse_in_element(
+                    "buffer",
+                    "buffer is shorter than its length",
+                ));
+            }
+            data.truncate(length);
+            buffers.push(data);
+        }
+
+        Ok(Self { json, buffers })
+    }
+
+    /// bytes of a buffer view and its stride
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), String> {
+        let view = array(&self.json, "bufferViews")
+            .get(index)
+            .ok_or_else(|| format!("buffer view {} doesn't exist", index))?;
+        let buffer = usize_field(view, "buffer")
+            .and_then(|buffer| self.buffers.get(buffer))
+            .ok_or_else(|| format!("buffer of buffer view {} doesn't exist", index))?;
+        let offset = usize_field(view, "byteOffset").unwrap_or(0);
+        let length = usize_field(view, "byteLength").unwrap_or(0);
+        let bytes = offset
+            .checked_add(length)
+            .and_then(|end| buffer.get(offset..end))
+            .ok_or_else(|| format!("buffer view {} is out of range", index))?;
+        Ok((bytes, usize_field(view, "byteStride")))
+    }
+
+    fn accessor(&self, index: usize) -> Result<AccessorData, String> {
+        let accessor = array(&self.json, "accessors")
+            .get(index)
+            .ok_or_else(|| format!("accessor {} doesn't exist", index))?;
+        let count = usize_field(accessor, "count").unwrap_or(0);
+        let component_type = accessor
+            .get("componentType")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        let component_bytes = component_size(component_type)
+            .ok_or_else(|| format!("unknown component type {}", component_type))?;
+        let normalized = accessor
+            .get("normalized")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let components = match accessor.get("type").and_then(Value::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            ty => return Err(format!("accessor type {:?} is not supported", ty)),
+        };
+        let element_size = components * component_bytes;
+
+        // an accessor without buffer view is initialized with zeros, its elements are limited by
+        // the size of all buffers so that a malformed count can't allocate arbitrary memory
+        let max_count = self.buffers.iter().map(Vec::len).sum::<usize>();
+        let len = count
+            .checked_mul(components)
+            .filter(|_| count <= max_count)
+            .ok_or_else(|| format!("accessor {} has too many elements", index))?;
+        let mut values = vec![0.0; len];
+        if let Some(view) = usize_field(accessor, "bufferView") {
+            let (bytes, stride) = self.buffer_view(view)?;
+            let offset = usize_field(accessor, "byteOffset").unwrap_or(0);
+            let stride = stride.unwrap_or(element_size);
+            let end = match count {
+                0 => Some(0),
+                _ => stride
+                    .checked_mul(count - 1)
+                    .and_then(|end| end.checked_add(offset))
+                    .and_then(|end| end.checked_add(element_size)),
+            };
+            if end.is_none_or(|end| end > bytes.len()) {
+                return Err(format!("accessor {} is out of range", index));
+            }
+            for (i, value) in values.iter_mut().enumerate() {
+                let start = offset + i / components * stride + i % components * component_bytes;
+                *value = read_component(&bytes[start..], component_type, normalized);
+            }
+        }
+
+        if let Some(sparse) = accessor.get("sparse") {
+            let sparse_count = usize_field(sparse, "count").unwrap_or(0);
+            let indices = sparse
+                .get("indices")
+                .ok_or_else(|| "sparse accessor has no indices".to_owned())?;
+            let sparse_values = sparse
+                .get("values")
+                .ok_or_else(|| "sparse accessor has no values".to_owned())?;
+            let indices_type = indices
+                .get("componentType")
+                .and_then(Value::as_u64)
+                .unwrap_or(0);
+            let indices_size = component_size(indices_type)
+                .ok_or_else(|| format!("unknown component type {}", indices_type))?;
+
+            let sparse_bytes = |info: &Value, size: usize| -> Result<&[u8], String> {
+                let view = usize_field(info, "bufferView")
+                    .ok_or_else(|| "sparse accessor has no buffer view".to_owned())?;
+                let offset = usize_field(info, "byteOffset").unwrap_or(0);
+                let bytes = self.buffer_view(view)?.0;
+                size.checked_mul(sparse_count)
+                    .and_then(|length| length.checked_add(offset))
+                    .and_then(|end| bytes.get(offset..end))
+                    .ok_or_else(|| format!("sparse accessor {} is out of range", index))
+            };
+            let indices_bytes = sparse_bytes(indices, indices_size)?;
+            let values_bytes = sparse_bytes(sparse_values, element_size)?;
+            for i in 0..sparse_count {
+                let target = read_component(&indices_bytes[i * indices_size..], indices_type, false)
+                    as usize;
+                if target >= count {
+                    return Err(format!(
+                        "sparse index of accessor {} is out of range",
+                        index
+                    ));
+                }
+                for c in 0..components {
+                    values[target * components + c] = read_component(
+                        &values_bytes[i * element_size + c * component_bytes..],
+                        component_type,
+                        normalized,
+                    );
+                }
+            }
+        }
+
+        Ok(AccessorData {
+            values,
+            components,
+            is_float: component_type == COMPONENT_FLOAT || normalized,
+        })
+    }
+
+    fn vertex_attribute(&self, index: usize) -> Result<VertexAttribute, String> {
+        let data = self.accessor(index)?;
+        let values = &data.values;
+        let c = data.components;
+        let float = |i: usize| values[i] as f32;
+        let int = |i: usize| values[i] as i64 as i32;
+        let count = values.len() / c;
+        Ok(match (c, data.is_float) {
+            (1, true) => VertexAttribute::Float((0..count).map(float).collect()),
+            (2, true) => VertexAttribute::Float2(
+                (0..count)
+                    .map(|i| [float(2 * i), float(2 * i + 1)])
+                    .collect(),
+            ),
+            (3, true) => VertexAttribute::Float3(
+                (0..count)
+                    .map(|i| [float(3 * i), float(3 * i + 1), float(3 * i + 2)])
+                    .collect(),
+            ),
+            (_, true) => VertexAttribute::Float4(
+                (0..count)
+                    .map(|i| {
+                        [
+                            float(4 * i),
+                            float(4 * i + 1),
+                            float(4 * i + 2),
+                            float(4 * i + 3),
+                        ]
+                    })
+                    .collect(),
+            ),
+            (1, false) => VertexAttribute::Int((0..count).map(int).collect()),
+            (2, false) => {
+                VertexAttribute::Int2((0..count).map(|i| [int(2 * i), int(2 * i + 1)]).collect())
+            }
+            (3, false) => VertexAttribute::Int3(
+                (0..count)
+                    .map(|i| [int(3 * i), int(3 * i + 1), int(3 * i + 2)])
+                    .collect(),
+            ),
+            (_, false) => VertexAttribute::Int4(
+                (0..count)
+                    .map(|i| [int(4 * i), int(4 * i + 1), int(4 * i + 2), int(4 * i + 3)])
+                    .collect(),
+            ),
+        })
+    }
+}
+
+fn array<'a>(json: &'a Value, key: &str) -> &'a [Value] {
+    json.get(key)
+        .and_then(Value::as_array)
+        .map_or(&[], |array| array.as_slice())
+}
+
+fn usize_field(json: &Value, key: &str) -> Option<usize> {
+    json.get(key)
+        .and_then(Value::as_u64)
+        .map(|val| val as usize)
+}
+
+fn component_size(component_type: u64) -> Option<usize> {
+    match component_type {
+        COMPONENT_BYTE | COMPONENT_UNSIGNED_BYTE => Some(1),
+        COMPONENT_SHORT | COMPONENT_UNSIGNED_SHORT => Some(2),
+        COMPONENT_UNSIGNED_INT | COMPONENT_FLOAT => Some(4),
+        _ => None,
+    }
+}
+
+/// normalized integers are converted as described in the gltf specification
+fn read_component(bytes: &[u8], component_type: u64, normalized: bool) -> f64 {
+    let (value, max) = match component_type {
+        COMPONENT_BYTE => (bytes[0] as i8 as f64, i8::MAX as f64),
+        COMPONENT_UNSIGNED_BYTE => (bytes[0] as f64, u8::MAX as f64),
+        COMPONENT_SHORT => (
+            i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            i16::MAX as f64,
+        ),
+        COMPONENT_UNSIGNED_SHORT => (
+            u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            u16::MAX as f64,
+        ),
+        COMPONENT_UNSIGNED_INT => (read_u32(bytes, 0) as f64, u32::MAX as f64),
+        _ => return f32::from_bits(read_u32(bytes, 0)) as f64,
+    };
+    if normalized {
+        (value / max).max(-1.0)
+    } else {
+        value
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+/// json chunk and optional binary chunk of a glb file
+fn parse_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), LoadError> {
+    let version = read_u32(bytes, 4);
+    if version != 2 {
+        return Err(LoadError::UnsupportedFormat(format!(
+            "glb version {} is not supported",
+            version
+        )));
+    }
+    let length = (read_u32(bytes, 8) as usize).min(bytes.len());
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = read_u32(bytes, offset) as usize;
+        let chunk_type = read_u32(bytes, offset + 4);
+        let chunk = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or_else(|| LoadError::parse("glb chunk is out of range"))?;
+        match chunk_type {
+            GLB_CHUNK_JSON if json.is_none() => json = Some(chunk),
+            GLB_CHUNK_BIN if bin.is_none() => bin = Some(chunk),
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+    let json = json.ok_or_else(|| LoadError::parse("glb has no json chunk"))?;
+    Ok((json, bin))
+}
+
+/// write a glb file with a json chunk and a binary chunk
+fn write_glb<W: std::io::Write>(mut writer: W, json: &[u8], bin: &[u8]) -> std::io::Result<()> {
+    let json_length = json.len().div_ceil(4) * 4;
+    let bin_length = bin.len().div_ceil(4) * 4;
+    let length = 12 + 8 + json_length + if bin.is_empty() { 0 } else { 8 + bin_length };
+
+    writer.write_all(&GLB_MAGIC.to_le_bytes())?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&(length as u32).to_le_bytes())?;
+
+    writer.write_all(&(json_length as u32).to_le_bytes())?;
+    writer.write_all(&GLB_CHUNK_JSON.to_le_bytes())?;
+    writer.write_all(json)?;
+    writer.write_all(&b"   "[..json_length - json.len()])?;
+
+    if !bin.is_empty() {
+        writer.write_all(&(bin_length as u32).to_le_bytes())?;
+        writer.write_all(&GLB_CHUNK_BIN.to_le_bytes())?;
+        writer.write_all(bin)?;
+        writer.write_all(&[0; 3][..bin_length - bin.len()])?;
+    }
+    writer.flush()
+}
+
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(data.len() / 4 * 3);
+    let mut bits = 0u32;
+    let mut num_bits = 0;
+    for c in data.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+        bits = bits << 6 | value as u32;
+        num_bits += 6;
+        if num_bits >= 8 {
+            num_bits -= 8;
+            bytes.push((bits >> num_bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// decode percent-encoded characters of an uri
+fn decode_uri(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(json: &str) -> GltfDocument {
+        GltfDocument::parse(json.as_bytes(), None).unwrap()
+    }
+
+    #[test]
+    fn malformed_accessors_are_errors() {
+        let buffer = r#""buffers": [{ "byteLength": 4, "uri": "data:;base64,AAAAAA==" }]"#;
+        let huge_count = document(&format!(
+            r#"{{ {}, "accessors": [{{ "count": 18446744073709551615, "componentType": 5126, "type": "VEC4" }}] }}"#,
+            buffer
+        ));
+        assert!(huge_count.accessor(0).is_err());
+
+        let view_overflow = document(&format!(
+            r#"{{ {}, "bufferViews": [{{ "buffer": 0, "byteOffset": 18446744073709551615, "byteLength": 4 }}],
+                "accessors": [{{ "bufferView": 0, "count": 1, "componentType": 5126, "type": "SCALAR" }}] }}"#,
+            buffer
+        ));
+        assert!(view_overflow.accessor(0).is_err());
+
+        let stride_overflow = document(&format!(
+            r#"{{ {}, "bufferViews": [{{ "buffer": 0, "byteLength": 4, "byteStride": 9223372036854775807 }}],
+                "accessors": [{{ "bufferView": 0, "count": 3, "componentType": 5126, "type": "SCALAR" }}] }}"#,
+            buffer
+        ));
+        assert!(stride_overflow.accessor(0).is_err());
+
+        let valid = document(&format!(
+            r#"{{ {}, "bufferViews": [{{ "buffer": 0, "byteLength": 4 }}],
+                "accessors": [{{ "bufferView": 0, "count": 1, "componentType": 5126, "type": "SCALAR" }}] }}"#,
+            buffer
+        ));
+        assert_eq!(valid.accessor(0).unwrap().values, vec![0.0]);
+    }
+}
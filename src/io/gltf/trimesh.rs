@@ -0,0 +1,642 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    io::{
+        create_file,
+        gltf::{
+            array, attribute_name, attribute_semantic, component_size, usize_field, GltfDocument,
+            GltfMesh, GltfPrimitive, COMPONENT_BYTE, COMPONENT_FLOAT, COMPONENT_SHORT,
+            COMPONENT_UNSIGNED_BYTE, COMPONENT_UNSIGNED_INT, COMPONENT_UNSIGNED_SHORT,
+            TARGET_ARRAY_BUFFER, TARGET_ELEMENT_ARRAY_BUFFER,
+        },
+        LoadError, SaveError,
+    },
+    trimesh::{
+        lines_of, triangles_of, MeshIndices, PrimitiveTopology, TriMesh, TriMeshError,
+        VertexAttribute, VertexAttributeFormat, RESTART,
+    },
+};
+
+/// load all meshes of a `.gltf` or `.glb` file, external buffers are loaded relative to the file
+pub fn load_to_trimesh<Path: AsRef<std::path::Path>>(
+    path: Path,
+) -> Result<Vec<GltfMesh>, LoadError> {
+    let bytes = std::fs::read(path.as_ref())?;
+    let base = path
+        .as_ref()
+        .parent()
+        .unwrap_or_else(|| std::path::Path::new(""));
+    load_meshes(&GltfDocument::parse(&bytes, Some(base))?)
+}
+
+/// load all meshes of a `.glb` file, or a `.gltf` file whose buffers are data uris
+pub fn load_to_trimesh_from_reader<Reader: Read>(
+    mut reader: Reader,
+) -> Result<Vec<GltfMesh>, LoadError> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+    load_meshes(&GltfDocument::parse(&bytes, None)?)
+}
+
+fn load_meshes(document: &GltfDocument) -> Result<Vec<GltfMesh>, LoadError> {
+    array(&document.json, "meshes")
+        .iter()
+        .enumerate()
+        .map(|(index, mesh)| {
+            let primitives = array(mesh, "primitives")
+                .iter()
+                .map(|primitive| load_primitive(document, primitive))
+                .collect::<Result<_, _>>()
+                .map_err(|err| {
+                    LoadError::parse_in_element("mesh", format!("mesh {}, {}", index, err))
+                })?;
+            let weights = array(mesh, "weights")
+                .iter()
+                .map(|weight| weight.as_f64().unwrap_or(0.0) as f32)
+                .collect();
+            let target_names = mesh
+                .get("extras")
+                .map_or(&[][..], |extras| array(extras, "targetNames"))
+                .iter()
+                .map(|name| name.as_str().unwrap_or("").to_owned())
+                .collect();
+            Ok(GltfMesh {
+                name: mesh.get("name").and_then(Value::as_str).map(str::to_owned),
+                primitives,
+                weights,
+                target_names,
+            })
+        })
+        .collect()
+}
+
+/// read attributes of a primitive or a morph target
+fn load_attributes(
+    document: &GltfDocument,
+    attributes: &Value,
+) -> Result<HashMap<Cow<'static, str>, VertexAttribute>, String> {
+    let attributes = attributes
+        .as_object()
+        .ok_or_else(|| "attributes must be an object".to_owned())?;
+    let mut vertex_attributes = HashMap::with_capacity(attributes.len());
+    let mut num_vertices = None;
+    for (semantic, accessor) in attributes {
+        let accessor = accessor
+            .as_u64()
+            .ok_or_else(|| format!("accessor of '{}' must be an index", semantic))?;
+        let attribute = document.vertex_attribute(accessor as usize)?;
+        if *num_vertices.get_or_insert(attribute.len()) != attribute.len() {
+            return Err(format!("'{}' has a different number of vertices", semantic));
+        }
+        vertex_attributes.insert(attribute_name(semantic), attribute);
+    }
+    Ok(vertex_attributes)
+}
+
+fn load_primitive(document: &GltfDocument, primitive: &Value) -> Result<GltfPrimitive, String> {
+    let vertex_attributes = load_attributes(
+        document,
+        primitive.get("attributes").unwrap_or(&Value::Null),
+    )?;
+    let num_vertices = vertex_attributes
+        .values()
+        .next()
+        .map_or(0, |attribute| attribute.len());
+
+    let (indices, prefer_u16) = match usize_field(primitive, "indices") {
+        Some(accessor) => {
+            let component_type = array(&document.json, "accessors")
+                .get(accessor)
+                .and_then(|accessor| accessor.get("componentType"))
+                .and_then(Value::as_u64);
+            let indices = document
+                .accessor(accessor)?
+                .values
+                .into_iter()
+                .map(|index| index as usize)
+                .collect::<Vec<_>>();
+            if indices.iter().any(|index| *index >= num_vertices) {
+                return Err("vertex index is out of range".to_owned());
+            }
+            (
+                Some(indices),
+                component_type != Some(COMPONENT_UNSIGNED_INT),
+            )
+        }
+        None => (None, true),
+    };
+
+    let mode = primitive.get("mode").and_then(Value::as_u64).unwrap_or(4);
+    let topology = primitive_topology(mode)
+        .ok_or_else(|| format!("primitive mode {} is not supported", mode))?;
+    let indices = match mode {
+        // a line loop is a line strip back to its first vertex
+        MODE_LINE_LOOP => {
+            let mut vertices = indices.unwrap_or_else(|| (0..num_vertices).collect());
+            if let Some(first) = vertices.first() {
+                vertices.push(*first);
+            }
+            Some(vertices)
+        }
+        _ => indices,
+    };
+    // the restart index of strips and fans can't be a vertex
+    let max_vertices = num_vertices + topology.has_restart() as usize;
+    let indices = indices.map(|indices| MeshIndices::from_vec(&indices, max_vertices, prefer_u16));
+
+    let targets = array(primitive, "targets")
+        .iter()
+        .map(|target| load_attributes(document, target))
+        .collect::<Result<_, _>>()?;
+
+    let mut mesh = TriMesh::new(vertex_attributes, indices);
+    mesh.set_topology(topology);
+    Ok(GltfPrimitive {
+        mesh,
+        material: usize_field(primitive, "material"),
+        targets,
+    })
+}
+
+impl GltfMesh {
+    /// merge meshes of all primitives into one mesh with a submesh for each primitive,
+    /// material ids of submeshes are materials of primitives, see `TriMesh::merge()`
+    ///
+    /// primitives with different triangle or line modes are converted to lists first,
+    /// morph targets are not merged
+    pub fn merge_primitives(&self) -> Result<TriMesh, TriMeshError> {
+        let topologies = self
+            .primitives
+            .iter()
+            .map(|primitive| primitive.mesh.topology())
+            .collect::<Vec<_>>();
+        let list = if topologies.windows(2).all(|pair| pair[0] == pair[1]) {
+            None
+        } else if topologies.iter().all(|topology| topology.is_triangles()) {
+            Some(PrimitiveTopology::TriangleList)
+        } else if topologies.iter().all(|topology| topology.is_lines()) {
+            Some(PrimitiveTopology::LineList)
+        } else {
+            None
+        };
+        let converted = match list {
+            Some(list) => self
+                .primitives
+                .iter()
+                .map(|primitive| {
+                    let mut mesh = primitive.mesh.clone();
+                    mesh.convert_topology(list).map(|_| mesh)
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => vec![],
+        };
+        let meshes = match list {
+            Some(_) => converted.iter().collect::<Vec<_>>(),
+            None => self
+                .primitives
+                .iter()
+                .map(|primitive| &primitive.mesh)
+                .collect(),
+        };
+        let mut mesh = TriMesh::merge(&meshes)?;
+        let mut submeshes = mesh.submeshes.iter_mut();
+        for primitive in &self.primitives {
+            for submesh in submeshes
+                .by_ref()
+                .take(primitive.mesh.submeshes.len().max(1))
+            {
+                submesh.material_id = submesh.material_id.or(primitive.material);
+            }
+        }
+        Ok(mesh)
+    }
+}
+
+/// save `mesh` to a `.glb` file if the extension of `path` is `glb`, otherwise to a `.gltf` file
+/// and a `.bin` file with the same name
+///
+/// each vertex attribute is saved to an accessor, names of vertex attributes are mapped to
+/// gltf semantics, `TriMesh::TEXCOORD` and `TriMesh::COLOR` are saved as `TEXCOORD_0` and
+/// `COLOR_0`, and names that are not gltf semantics are uppercased with a leading `_`;
+/// integer attributes are saved as `SHORT` or `UNSIGNED_SHORT` if their values fit, otherwise
+/// as `FLOAT`, and `JOINTS_n` are saved as `UNSIGNED_SHORT`; an error is returned if the component
+/// type of a gltf semantic is not allowed by gltf, e.g. `JOINTS_n` with values above `65535`
+///
+/// each submesh is saved as a primitive whose material is the material id of the submesh,
+/// placeholder materials are written for all material ids
+pub fn save_trimesh<Path: AsRef<std::path::Path>>(
+    path: Path,
+    mesh: &TriMesh,
+) -> Result<(), SaveError> {
+    let path = path.as_ref();
+    let is_glb = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("glb"));
+    let writer = create_file(path)?;
+    if is_glb {
+        return save_trimesh_to_writer(writer, mesh);
+    }
+
+    let bin_path = path.with_extension("bin");
+    let bin_uri = bin_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+    let (json, bin) = build_gltf(mesh, bin_uri)?;
+    std::fs::write(&bin_path, bin)?;
+    let write = || -> std::io::Result<()> {
+        let mut writer = writer;
+        serde_json::to_writer_pretty(&mut writer, &json)?;
+        writer.flush()
+    };
+    write().map_err(SaveError::Io)
+}
+
+/// save `mesh` as a `.glb` file
+pub fn save_trimesh_to_writer<Writer: Write>(
+    writer: Writer,
+    mesh: &TriMesh,
+) -> Result<(), SaveError> {
+    let (json, bin) = build_gltf(mesh, None)?;
+    let json = serde_json::to_vec(&json).map_err(|err| SaveError::Invalid(err.to_string()))?;
+    super::write_glb(writer, &json, &bin).map_err(SaveError::Io)
+}
+
+/// build json and binary buffer of a gltf file with a single mesh,
+/// the buffer is the glb binary chunk if `bin_uri` is `None`
+fn build_gltf(mesh: &TriMesh, bin_uri: Option<String>) -> Result<(Value, Vec<u8>), SaveError> {
+    let positions = mesh.attribute_as::<[f32; 3]>(TriMesh::POSITION)?;
+
+    let mut bin = vec![];
+    let mut views = vec![];
+    let mut accessors = vec![];
+    // add a buffer view and an accessor, elements are padded to multiples of 4 bytes,
+    // values of normalized accessors are in `[0, 1]` or `[-1, 1]`
+    let mut add_accessor =
+        |component_type: u64, normalized: bool, components: usize, values: &[f64], target: u64| {
+            let element_size = component_size(component_type).unwrap_or(4) * components;
+            let stride = element_size.div_ceil(4) * 4;
+            let count = values.len() / components;
+
+            let offset = bin.len();
+            for element in values.chunks(components) {
+                for value in element {
+                    let int = |max: f64| {
+                        if normalized {
+                            (value * max).round()
+                        } else {
+                            *value
+                        }
+                    };
+                    match component_type {
+                        COMPONENT_FLOAT => bin.extend_from_slice(&(*value as f32).to_le_bytes()),
+                        COMPONENT_UNSIGNED_INT => {
+                            bin.extend_from_slice(&(*value as u32).to_le_bytes())
+                        }
+                        COMPONENT_BYTE => bin.push(int(i8::MAX as f64) as i8 as u8),
+                        COMPONENT_UNSIGNED_BYTE => bin.push(int(u8::MAX as f64) as u8),
+                        COMPONENT_SHORT => {
+                            bin.extend_from_slice(&(int(i16::MAX as f64) as i16).to_le_bytes())
+                        }
+                        _ => bin.extend_from_slice(&(int(u16::MAX as f64) as u16).to_le_bytes()),
+                    }
+                }
+                if target == TARGET_ARRAY_BUFFER {
+                    bin.resize(bin.len() + stride - element_size, 0);
+                }
+            }
+            let length = bin.len() - offset;
+            bin.resize(bin.len().div_ceil(4) * 4, 0);
+
+            let mut view = json!({
+                "buffer": 0,
+                "byteOffset": offset,
+                "byteLength": length,
+                "target": target,
+            });
+            if target == TARGET_ARRAY_BUFFER && stride != element_size {
+                view["byteStride"] = json!(stride);
+            }
+            views.push(view);
+
+            let ty = match components {
+                1 => "SCALAR",
+                2 => "VEC2",
+                3 => "VEC3",
+                _ => "VEC4",
+            };
+            let mut accessor = json!({
+                "bufferView": views.len() - 1,
+                "componentType": component_type,
+                "count": count,
+                "type": ty,
+            });
+            if normalized {
+                accessor["normalized"] = json!(true);
+            }
+            accessors.push(accessor);
+            accessors.len() - 1
+        };
+
+    let mut names = mesh.vertex_attributes.keys().collect::<Vec<_>>();
+    names.sort();
+    let mut attributes = serde_json::Map::new();
+    for name in names {
+        let attribute = &mesh.vertex_attributes[name];
+        let semantic = attribute_semantic(name);
+        let format = attribute.format();
+        let values = attribute.to_f64_values();
+        let fit_unsigned_short = || values.iter().all(|v| *v >= 0.0 && *v <= u16::MAX as f64);
+        let component_type = if let Some(component_type) = small_component_type(format) {
+            component_type
+        } else if format.is_float() {
+            COMPONENT_FLOAT
+        } else if semantic.starts_with("JOINTS_") && fit_unsigned_short() {
+            COMPONENT_UNSIGNED_SHORT
+        } else if values
+            .iter()
+            .all(|v| *v >= i16::MIN as f64 && *v <= i16::MAX as f64)
+        {
+            COMPONENT_SHORT
+        } else if fit_unsigned_short() {
+            COMPONENT_UNSIGNED_SHORT
+        } else {
+            COMPONENT_FLOAT
+        };
+        if !component_type_allowed(&semantic, component_type, format.is_normalized()) {
+            return Err(SaveError::Invalid(format!(
+                "'{}' of format {:?} can't be saved with a component type allowed by gltf",
+                semantic, format
+            )));
+        }
+        let accessor = add_accessor(
+            component_type,
+            format.is_normalized(),
+            format.components(),
+            &values,
+            TARGET_ARRAY_BUFFER,
+        );
+        attributes.insert(semantic, json!(accessor));
+    }
+
+    // each submesh is a primitive with its own indices sharing the attribute accessors
+    mesh.validate_submeshes()
+        .map_err(|err| SaveError::Invalid(err.to_string()))?;
+    let index_list = mesh.index_list();
+    let parts = if mesh.submeshes.is_empty() {
+        vec![(mesh.indices.as_ref().map(|_| &index_list[..]), None)]
+    } else {
+        mesh.submeshes
+            .iter()
+            .map(|submesh| {
+                let indices = &index_list[submesh.range()];
+                (Some(indices), submesh.material_id)
+            })
+            .collect()
+    };
+    // gltf doesn't allow the maximum value of the component type in indices
+    let u16_indices = !matches!(mesh.indices, Some(MeshIndices::U32(_)))
+        && mesh.num_vertices() <= u16::MAX as usize;
+    let mut primitives = vec![];
+    for (indices, material) in parts {
+        // gltf doesn't allow restart, strips and fans with restart are saved as lists
+        let restarted = indices.is_some_and(|indices| indices.contains(&RESTART));
+        let topology = mesh.topology();
+        let (mode, converted) = match topology {
+            PrimitiveTopology::PointList => (0, None),
+            PrimitiveTopology::LineList => (1, None),
+            PrimitiveTopology::LineStrip if restarted => (
+                1,
+                indices.map(|indices| lines_of(indices, topology).concat()),
+            ),
+            PrimitiveTopology::LineStrip => (3, None),
+            PrimitiveTopology::TriangleList => (4, None),
+            PrimitiveTopology::TriangleStrip | PrimitiveTopology::TriangleFan if restarted => (
+                4,
+                indices.map(|indices| triangles_of(indices, topology).concat()),
+            ),
+            PrimitiveTopology::TriangleStrip => (5, None),
+            PrimitiveTopology::TriangleFan => (6, None),
+        };
+
+        let mut primitive = json!({ "attributes": attributes.clone(), "mode": mode });
+        if let Some(indices) = converted.as_deref().or(indices) {
+            let values = indices.iter().map(|i| *i as f64).collect::<Vec<_>>();
+            let component_type = if u16_indices {
+                COMPONENT_UNSIGNED_SHORT
+            } else {
+                COMPONENT_UNSIGNED_INT
+            };
+            primitive["indices"] = json!(add_accessor(
+                component_type,
+                false,
+                1,
+                &values,
+                TARGET_ELEMENT_ARRAY_BUFFER
+            ));
+        }
+        if let Some(material) = material {
+            primitive["material"] = json!(material);
+        }
+        primitives.push(primitive);
+    }
+
+    // gltf requires bounds of positions
+    let position_accessor = attributes["POSITION"].as_u64().unwrap() as usize;
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in positions {
+        for i in 0..3 {
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+        }
+    }
+    if !positions.is_empty() {
+        accessors[position_accessor]["min"] = json!(min);
+        accessors[position_accessor]["max"] = json!(max);
+    }
+
+    let mut buffer = json!({ "byteLength": bin.len() });
+    if let Some(uri) = bin_uri {
+        buffer["uri"] = json!(uri);
+    }
+    let mut json = json!({
+        "asset": { "version": "2.0", "generator": "pep-mesh" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{ "primitives": primitives }],
+        "accessors": accessors,
+        "bufferViews": views,
+        "buffers": [buffer],
+    });
+    // material ids of submeshes refer to default materials
+    if let Some(max_material) = mesh.submeshes.iter().filter_map(|s| s.material_id).max() {
+        json["materials"] = json!(vec![json!({}); max_material + 1]);
+    }
+    Ok((json, bin))
+}
+
+/// 8-bit and 16-bit formats keep their component types, normalized or not
+fn small_component_type(format: VertexAttributeFormat) -> Option<u64> {
+    use VertexAttributeFormat as F;
+    match format {
+        F::Uint8x2 | F::Uint8x4 | F::Unorm8x2 | F::Unorm8x4 => Some(COMPONENT_UNSIGNED_BYTE),
+        F::Snorm8x2 | F::Snorm8x4 => Some(COMPONENT_BYTE),
+        F::Uint16x2 | F::Uint16x4 | F::Unorm16x2 | F::Unorm16x4 => Some(COMPONENT_UNSIGNED_SHORT),
+        F::Snorm16x2 | F::Snorm16x4 => Some(COMPONENT_SHORT),
+        _ => None,
+    }
+}
+
+/// component types that gltf allows for vertex attributes, any for application-specific ones
+fn component_type_allowed(semantic: &str, component_type: u64, normalized: bool) -> bool {
+    let small_unsigned = matches!(
+        component_type,
+        COMPONENT_UNSIGNED_BYTE | COMPONENT_UNSIGNED_SHORT
+    );
+    match semantic {
+        "POSITION" | "NORMAL" | "TANGENT" => component_type == COMPONENT_FLOAT,
+        _ if semantic.starts_with('_') => true,
+        _ if semantic.starts_with("JOINTS_") => small_unsigned && !normalized,
+        // `TEXCOORD_n`, `COLOR_n` and `WEIGHTS_n`
+        _ => component_type == COMPONENT_FLOAT || (small_unsigned && normalized),
+    }
+}
+
+const MODE_LINE_LOOP: u64 = 2;
+
+fn primitive_topology(mode: u64) -> Option<PrimitiveTopology> {
+    match mode {
+        0 => Some(PrimitiveTopology::PointList),
+        1 => Some(PrimitiveTopology::LineList),
+        MODE_LINE_LOOP | 3 => Some(PrimitiveTopology::LineStrip),
+        4 => Some(PrimitiveTopology::TriangleList),
+        5 => Some(PrimitiveTopology::TriangleStrip),
+        6 => Some(PrimitiveTopology::TriangleFan),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::{Submesh, TriMeshBuilder};
+
+    #[test]
+    fn glb_round_trip() {
+        let positions = vec![
+            [0.0f32, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ];
+        let texcoords = vec![[0.0f32, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        let mut mesh = TriMeshBuilder::new()
+            .attribute(TriMesh::POSITION, positions.clone())
+            .attribute(TriMesh::TEXCOORD, texcoords.clone())
+            .indices(vec![0, 1, 2, 0, 2, 3])
+            .build()
+            .unwrap();
+        mesh.set_submeshes(vec![Submesh::new(0, 3, Some(1)), Submesh::new(3, 3, None)]);
+
+        let mut out_of_range = mesh.clone();
+        out_of_range.set_submeshes(vec![Submesh::new(3, 6, None)]);
+        assert!(matches!(
+            save_trimesh_to_writer(vec![], &out_of_range),
+            Err(SaveError::Invalid(_))
+        ));
+
+        let mut bytes = vec![];
+        save_trimesh_to_writer(&mut bytes, &mesh).unwrap();
+        let meshes = load_to_trimesh_from_reader(bytes.as_slice()).unwrap();
+        let primitives = &meshes[0].primitives;
+        assert_eq!(primitives.len(), 2);
+        assert_eq!(primitives[0].material, Some(1));
+        assert_eq!(primitives[1].material, None);
+
+        let merged = meshes[0].merge_primitives().unwrap();
+        let triangles = merged.triangles();
+        let merged_positions = merged.attribute_as::<[f32; 3]>(TriMesh::POSITION).unwrap();
+        let merged_texcoords = merged.attribute_as::<[f32; 2]>(TriMesh::TEXCOORD).unwrap();
+        for (tri, expected) in triangles.iter().zip([[0, 1, 2], [0, 2, 3]]) {
+            for (v, e) in tri.iter().zip(expected) {
+                assert_eq!(merged_positions[*v], positions[e]);
+                assert_eq!(merged_texcoords[*v], texcoords[e]);
+            }
+        }
+        assert_eq!(
+            merged
+                .submeshes()
+                .iter()
+                .map(|s| s.material_id)
+                .collect::<Vec<_>>(),
+            vec![Some(1), None]
+        );
+    }
+
+    #[test]
+    fn indices_never_use_max_value() {
+        let num_vertices = u16::MAX as usize + 1;
+        let mesh = TriMeshBuilder::new()
+            .attribute(TriMesh::POSITION, vec![[0.0f32; 3]; num_vertices])
+            .indices(vec![0, 1, 65535])
+            .build()
+            .unwrap();
+        assert!(matches!(mesh.indices(), Some(MeshIndices::U16(_))));
+
+        let (json, _) = build_gltf(&mesh, None).unwrap();
+        let indices = json["meshes"][0]["primitives"][0]["indices"]
+            .as_u64()
+            .unwrap();
+        assert_eq!(
+            json["accessors"][indices as usize]["componentType"],
+            json!(COMPONENT_UNSIGNED_INT)
+        );
+    }
+
+    #[test]
+    fn semantic_component_types() {
+        let mesh = |name: &'static str, values: VertexAttribute| {
+            TriMeshBuilder::new()
+                .attribute(TriMesh::POSITION, vec![[0.0f32; 3]; 3])
+                .attribute(name, values)
+                .build()
+                .unwrap()
+        };
+        let component_type = |mesh: &TriMesh, semantic: &str| {
+            let (json, _) = build_gltf(mesh, None).unwrap();
+            let accessor = json["meshes"][0]["primitives"][0]["attributes"][semantic]
+                .as_u64()
+                .unwrap();
+            json["accessors"][accessor as usize]["componentType"].clone()
+        };
+
+        // joints are narrowed to unsigned short, even if they would fit short
+        let joints = mesh("joints_0", vec![[0u32, 1, 2, 300]; 3].into());
+        assert_eq!(
+            component_type(&joints, "JOINTS_0"),
+            json!(COMPONENT_UNSIGNED_SHORT)
+        );
+        let joints = mesh("joints_0", vec![[0u32, 1, 2, 70000]; 3].into());
+        assert!(matches!(
+            build_gltf(&joints, None),
+            Err(SaveError::Invalid(_))
+        ));
+
+        let normals = mesh(TriMesh::NORMAL, vec![[0i32, 0, 1]; 3].into());
+        assert!(matches!(
+            build_gltf(&normals, None),
+            Err(SaveError::Invalid(_))
+        ));
+
+        // application-specific attributes may use any component type
+        let custom = mesh("id", vec![70000u32; 3].into());
+        assert_eq!(component_type(&custom, "_ID"), json!(COMPONENT_FLOAT));
+    }
+}
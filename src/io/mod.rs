@@ -1,39 +1,193 @@
+mod format;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+pub mod obj;
+pub mod off;
 pub mod ply;
+pub mod stl;
+mod validation;
+
+pub use format::*;
+pub use validation::*;
+
+use crate::trimesh::AttributeError;
 
 #[derive(Debug)]
-pub struct LoadError {
-    info: String,
+pub enum LoadError {
+    /// failed to read the file
+    Io(std::io::Error),
+    /// content of the file is malformed, parts of the location that are unknown are `None`
+    Parse {
+        /// element (or other kind of item) being read
+        element: Option<String>,
+        /// 1-based line number of text files
+        line: Option<usize>,
+        /// byte offset from the start of the file
+        offset: Option<u64>,
+        message: String,
+    },
+    /// format of the file is unknown, or it doesn't support the requested mesh
+    UnsupportedFormat(String),
+    /// a property required by the mesh is missing
+    MissingProperty { element: String, name: String },
+    /// record `record` of `element` refers to item `index`, but there are only `count` items
+    IndexOutOfRange {
+        element: String,
+        record: usize,
+        index: usize,
+        count: usize,
+    },
+    /// faces can't form the requested mesh, e.g. a half-edge mesh from non-manifold faces
+    Topology(String),
 }
 
 impl LoadError {
-    pub(crate) fn new(info: String) -> Self {
-        Self { info }
+    pub(crate) fn parse<S: Into<String>>(message: S) -> Self {
+        LoadError::Parse {
+            element: None,
+            line: None,
+            offset: None,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn parse_at_line<S: Into<String>>(line: usize, message: S) -> Self {
+        LoadError::Parse {
+            element: None,
+            line: Some(line),
+            offset: None,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn parse_in_element<S: Into<String>>(element: &str, message: S) -> Self {
+        LoadError::Parse {
+            element: Some(element.to_owned()),
+            line: None,
+            offset: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<std::io::Error> for LoadError {
+    fn from(err: std::io::Error) -> Self {
+        LoadError::Io(err)
     }
 }
 
 impl std::fmt::Display for LoadError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "failed to load mesh, inner error: '{}'", self.info)
+        write!(f, "failed to load mesh, ")?;
+        match self {
+            LoadError::Io(err) => write!(f, "{}", err),
+            LoadError::Parse {
+                element,
+                line,
+                offset,
+                message,
+            } => {
+                if let Some(element) = element {
+                    write!(f, "element '{}', ", element)?;
+                }
+                if let Some(line) = line {
+                    write!(f, "line {}, ", line)?;
+                }
+                if let Some(offset) = offset {
+                    write!(f, "byte {}, ", offset)?;
+                }
+                write!(f, "{}", message)
+            }
+            LoadError::UnsupportedFormat(info) => write!(f, "{}", info),
+            LoadError::MissingProperty { element, name } => {
+                write!(f, "property '{}' of element '{}' is missing", name, element)
+            }
+            LoadError::IndexOutOfRange {
+                element,
+                record,
+                index,
+                count,
+            } => write!(
+                f,
+                "index {} used by {} {} is out of range, there are {} items",
+                index, element, record, count
+            ),
+            LoadError::Topology(info) => write!(f, "{}", info),
+        }
     }
 }
 
-impl std::error::Error for LoadError {}
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug)]
-pub struct SaveError {
-    info: String,
+pub enum SaveError {
+    /// failed to write the file
+    Io(std::io::Error),
+    /// format is unknown from the path, or it doesn't support the given mesh
+    UnsupportedFormat(String),
+    /// a property (or vertex attribute) required by the format is missing
+    MissingProperty { element: String, name: String },
+    /// an index is larger than `max`, which is the largest value of the chosen index type
+    IndexOutOfRange { index: usize, max: usize },
+    /// the mesh or options can't be written, e.g. an attribute has an unexpected format
+    Invalid(String),
 }
 
-impl SaveError {
-    pub(crate) fn new(info: String) -> Self {
-        Self { info }
+/// buffered writer to a new file at `path`
+pub(crate) fn create_file<Path: AsRef<std::path::Path>>(
+    path: Path,
+) -> Result<std::io::BufWriter<std::fs::File>, SaveError> {
+    let file = std::fs::File::create(path.as_ref())?;
+    Ok(std::io::BufWriter::new(file))
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<AttributeError> for SaveError {
+    fn from(err: AttributeError) -> Self {
+        match err {
+            AttributeError::Missing(name) => SaveError::MissingProperty {
+                element: "vertex".to_owned(),
+                name: name.into_owned(),
+            },
+            err => SaveError::Invalid(err.to_string()),
+        }
     }
 }
 
 impl std::fmt::Display for SaveError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "failed to save mesh, inner error: '{}'", self.info)
+        write!(f, "failed to save mesh, ")?;
+        match self {
+            SaveError::Io(err) => write!(f, "{}", err),
+            SaveError::UnsupportedFormat(info) => write!(f, "{}", info),
+            SaveError::MissingProperty { element, name } => {
+                write!(f, "property '{}' of element '{}' is missing", name, element)
+            }
+            SaveError::IndexOutOfRange { index, max } => {
+                write!(f, "index {} is larger than {}", index, max)
+            }
+            SaveError::Invalid(info) => write!(f, "{}", info),
+        }
     }
 }
 
-impl std::error::Error for SaveError {}
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SaveError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use crate::{
+    halfedge::{check_manifold, HalfEdgeMesh},
+    io::{
+        obj::{ObjContent, ObjMaterial, ObjSubmesh, SubmeshWriter},
+        ply::{data_from_props, FromPropertyMap, Property, PropertyMap, ToPropertyMap},
+        LoadError, SaveError,
+    },
+};
+
+pub struct ObjHalfEdgeMesh<VData, EData, FData> {
+    pub mesh: HalfEdgeMesh<VData, EData, FData>,
+    /// face ranges are face ids
+    pub submeshes: Vec<ObjSubmesh>,
+    /// materials of all `mtllib` files that could be loaded
+    pub materials: Vec<ObjMaterial>,
+    /// file names given by `mtllib`
+    pub material_libraries: Vec<String>,
+}
+
+/// load an obj file, vertex data is created from properties `x`, `y`, `z` (and `red`, `green`, `blue`
+/// if the file has vertex colors), face data is created from `F32List` properties `texcoord` and `normal`
+/// holding values of all corners if all corners of the face have them, corners are in the order of
+/// half-edges starting from `FaceRef::halfedge()`
+pub fn load_to_halfedge<Path, VData, EData, FData>(
+    path: Path,
+) -> Result<ObjHalfEdgeMesh<VData, EData, FData>, LoadError>
+where
+    Path: AsRef<std::path::Path>,
+    VData: Default + FromPropertyMap,
+    EData: Default + FromPropertyMap,
+    FData: Default + FromPropertyMap,
+{
+    let file = std::fs::File::open(path.as_ref())?;
+    let mut mesh = load_to_halfedge_from_reader(std::io::BufReader::new(file))?;
+    mesh.materials = super::load_material_libraries(path.as_ref(), &mesh.material_libraries)?;
+    Ok(mesh)
+}
+
+/// `mtllib` files are not loaded, `ObjHalfEdgeMesh::materials` is always empty
+pub fn load_to_halfedge_from_reader<Reader, VData, EData, FData>(
+    reader: Reader,
+) -> Result<ObjHalfEdgeMesh<VData, EData, FData>, LoadError>
+where
+    Reader: BufRead,
+    VData: Default + FromPropertyMap,
+    EData: Default + FromPropertyMap,
+    FData: Default + FromPropertyMap,
+{
+    let content = ObjContent::parse(reader)?;
+    if content.faces.is_empty() {
+        return Err(LoadError::Topology("obj file has no faces".to_owned()));
+    }
+
+    let mut vertices_data = HashMap::with_capacity(content.positions.len());
+    for (v, p) in content.positions.iter().enumerate() {
+        let mut props = PropertyMap::default();
+        props.map.insert("x".into(), Property::F32(p[0]));
+        props.map.insert("y".into(), Property::F32(p[1]));
+        props.map.insert("z".into(), Property::F32(p[2]));
+        if let Some(c) = content.colors.get(v) {
+            props.map.insert("red".into(), Property::F32(c[0]));
+            props.map.insert("green".into(), Property::F32(c[1]));
+            props.map.insert("blue".into(), Property::F32(c[2]));
+        }
+        vertices_data.insert(v, data_from_props::<VData>(props, "vertex", v)?);
+    }
+
+    let mut faces = Vec::with_capacity(content.faces.len());
+    let mut faces_data = HashMap::with_capacity(content.faces.len());
+    for face in &content.faces {
+        // `HalfEdgeMesh::new` sets halfedge of a face to the one starting from its last vertex
+        let corners = face[face.len() - 1..]
+            .iter()
+            .chain(&face[..face.len() - 1])
+            .collect::<Vec<_>>();
+        let mut props = PropertyMap::default();
+        if face.iter().all(|c| c.1.is_some()) {
+            let texcoords = corners
+                .iter()
+                .flat_map(|c| content.texcoords[c.1.unwrap()])
+                .collect();
+            props
+                .map
+                .insert("texcoord".into(), Property::F32List(texcoords));
+        }
+        if face.iter().all(|c| c.2.is_some()) {
+            let normals = corners
+                .iter()
+                .flat_map(|c| content.normals[c.2.unwrap()])
+                .collect();
+            props
+                .map
+                .insert("normal".into(), Property::F32List(normals));
+        }
+        let data = data_from_props::<FData>(props, "face", faces.len())?;
+        faces_data.insert(faces.len(), data);
+        faces.push(face.iter().map(|c| c.0).collect());
+    }
+    check_manifold(&faces).map_err(LoadError::Topology)?;
+
+    Ok(ObjHalfEdgeMesh {
+        mesh: HalfEdgeMesh::new(faces, vertices_data, HashMap::new(), faces_data),
+        submeshes: content.submeshes,
+        materials: vec![],
+        material_libraries: content.material_libraries,
+    })
+}
+
+/// save a half-edge mesh, properties are the same as those of `load_to_halfedge`,
+/// non-empty `materials` are saved to a `.mtl` file with the same name
+pub fn save_halfedge<Path, VData, EData, FData>(
+    path: Path,
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+    submeshes: &[ObjSubmesh],
+    materials: &[ObjMaterial],
+) -> Result<(), SaveError>
+where
+    Path: AsRef<std::path::Path>,
+    VData: ToPropertyMap,
+    FData: ToPropertyMap,
+{
+    let library = super::save_material_library(path.as_ref(), materials)?;
+    save_halfedge_to_writer(
+        super::create_file(path)?,
+        mesh,
+        submeshes,
+        library.as_deref(),
+    )
+}
+
+/// `material_library` is written as `mtllib`
+pub fn save_halfedge_to_writer<Writer, VData, EData, FData>(
+    mut writer: Writer,
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+    submeshes: &[ObjSubmesh],
+    material_library: Option<&str>,
+) -> Result<(), SaveError>
+where
+    Writer: Write,
+    VData: ToPropertyMap,
+    FData: ToPropertyMap,
+{
+    let mut write = || -> std::io::Result<()> {
+        if let Some(library) = material_library {
+            writeln!(writer, "mtllib {}", library)?;
+        }
+        for vref in mesh.vertices() {
+            let props = mesh.vertex_data(&vref).to_proprety_map();
+            let value = |key: &str| props.get_f32(key).ok();
+            let p = [value("x"), value("y"), value("z")];
+            write!(
+                writer,
+                "v {} {} {}",
+                p[0].unwrap_or(0.0),
+                p[1].unwrap_or(0.0),
+                p[2].unwrap_or(0.0)
+            )?;
+            if let Ok([r, g, b]) = props.get_color_rgb() {
+                write!(writer, " {} {} {}", r, g, b)?;
+            }
+            writeln!(writer)?;
+        }
+
+        // corners of each face as `(vertex, texcoord, normal)`, texcoords and normals are deduplicated
+        let mut texcoords = HashMap::new();
+        let mut normals = HashMap::new();
+        let mut faces = vec![];
+        for fref in mesh.faces() {
+            if fref.is_boundary(mesh) {
+                continue;
+            }
+            let props = mesh.face_data(&fref).to_proprety_map();
+            let list = |key: &str| props.get_list::<f32>(key).ok();
+            let face_texcoords = list("texcoord");
+            let face_normals = list("normal");
+
+            let mut corners = vec![];
+            let mut he = fref.halfedge(mesh);
+            loop {
+                let i = corners.len();
+                let texcoord = face_texcoords
+                    .as_ref()
+                    .and_then(|t| t.get(2 * i..2 * i + 2))
+                    .map(|t| {
+                        let len = texcoords.len();
+                        *texcoords
+                            .entry([t[0].to_bits(), t[1].to_bits()])
+                            .or_insert(len)
+                    });
+                let normal = face_normals
+                    .as_ref()
+                    .and_then(|n| n.get(3 * i..3 * i + 3))
+                    .map(|n| {
+                        let len = normals.len();
+                        *normals
+                            .entry([n[0].to_bits(), n[1].to_bits(), n[2].to_bits()])
+                            .or_insert(len)
+                    });
+                corners.push((he.vertex(mesh).id, texcoord, normal));
+                he = he.next(mesh);
+                if he == fref.halfedge(mesh) {
+                    break;
+                }
+            }
+            faces.push((fref.id, corners));
+        }
+
+        let mut texcoords = texcoords.into_iter().collect::<Vec<_>>();
+        texcoords.sort_unstable_by_key(|(_, index)| *index);
+        for (t, _) in texcoords {
+            writeln!(
+                writer,
+                "vt {} {}",
+                f32::from_bits(t[0]),
+                f32::from_bits(t[1])
+            )?;
+        }
+        let mut normals = normals.into_iter().collect::<Vec<_>>();
+        normals.sort_unstable_by_key(|(_, index)| *index);
+        for (n, _) in normals {
+            writeln!(
+                writer,
+                "vn {} {} {}",
+                f32::from_bits(n[0]),
+                f32::from_bits(n[1]),
+                f32::from_bits(n[2])
+            )?;
+        }
+
+        let mut submesh_writer = SubmeshWriter::new(submeshes);
+        for (id, corners) in faces {
+            submesh_writer.before_face(&mut writer, id)?;
+            write!(writer, "f")?;
+            for (v, vt, vn) in corners {
+                super::write_corner(&mut writer, v, vt, vn)?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    };
+    write().map_err(SaveError::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_manifold_is_an_error() {
+        // three faces share the edge between the second and third vertices
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 1 0 1\nf 1 2 3\nf 2 4 3\nf 2 3 5\n";
+        let result = load_to_halfedge_from_reader::<_, (), (), ()>(obj.as_bytes());
+        assert!(matches!(result, Err(LoadError::Topology(_))));
+
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3\nf 1 3 4\n";
+        let result = load_to_halfedge_from_reader::<_, (), (), ()>(obj.as_bytes()).unwrap();
+        assert_eq!(result.mesh.num_faces(), 2);
+    }
+}
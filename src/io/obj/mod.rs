@@ -0,0 +1,452 @@
+mod halfedge;
+mod trimesh;
+
+pub use halfedge::*;
+pub use trimesh::*;
+
+use std::{
+    io::{BufRead, Write},
+    ops::Range,
+};
+
+use super::{create_file, LoadError, SaveError};
+
+/// consecutive faces sharing the same object, groups and material
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjSubmesh {
+    /// name given by `o`
+    pub object: Option<String>,
+    /// names given by `g`
+    pub groups: Vec<String>,
+    /// name given by `usemtl`
+    pub material: Option<String>,
+    /// range of triangles for `TriMesh`, or range of face ids for `HalfEdgeMesh`
+    pub faces: Range<usize>,
+}
+
+/// a material defined by `newmtl` in a `.mtl` file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    /// `Ka`
+    pub ambient: Option<[f32; 3]>,
+    /// `Kd`
+    pub diffuse: Option<[f32; 3]>,
+    /// `Ks`
+    pub specular: Option<[f32; 3]>,
+    /// `Ke`
+    pub emissive: Option<[f32; 3]>,
+    /// `Ns`
+    pub shininess: Option<f32>,
+    /// `d`, or `1 - Tr`
+    pub dissolve: Option<f32>,
+    /// `Ni`
+    pub optical_density: Option<f32>,
+    /// `illum`
+    pub illumination: Option<u32>,
+    /// file name of `map_Kd`
+    pub diffuse_texture: Option<String>,
+    /// file name of `map_Bump`, `bump` or `norm`
+    pub normal_texture: Option<String>,
+    /// other statements as keyword and the rest of the line
+    pub extra: Vec<(String, String)>,
+}
+
+/// a corner of a face, indices are 0-based
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// data of an obj file before it is converted to a mesh
+#[derive(Default)]
+struct ObjContent {
+    positions: Vec<[f32; 3]>,
+    /// empty if no vertex has a color
+    colors: Vec<[f32; 3]>,
+    texcoords: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+    faces: Vec<Vec<Corner>>,
+    /// face ranges are polygon ranges
+    submeshes: Vec<ObjSubmesh>,
+    material_libraries: Vec<String>,
+}
+
+impl ObjContent {
+    fn parse<R: BufRead>(reader: R) -> Result<Self, LoadError> {
+        let mut content = Self::default();
+        let mut object = None;
+        let mut groups = vec![];
+        let mut material = None;
+
+        for (line_number, line) in logical_lines(reader) {
+            let line = line?;
+            let error =
+                |info: &str| LoadError::parse_at_line(line_number, format!("{}, '{}'", info, line));
+            let mut tokens = line.split_whitespace();
+            let keyword = match tokens.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+            let rest = || line[keyword.len()..].trim().to_owned();
+
+            match keyword {
+                "v" => {
+                    let values = parse_floats(tokens).ok_or_else(|| error("invalid vertex"))?;
+                    if values.len() < 3 {
+                        return Err(error("vertex needs 3 coordinates"));
+                    }
+                    content.positions.push([values[0], values[1], values[2]]);
+                    // `x y z r g b` or `x y z w r g b`
+                    let color = match values.len() {
+                        6 => Some([values[3], values[4], values[5]]),
+                        7 => Some([values[4], values[5], values[6]]),
+                        _ => None,
+                    };
+                    if let Some(color) = color {
+                        content
+                            .colors
+                            .resize(content.positions.len() - 1, [1.0, 1.0, 1.0]);
+                        content.colors.push(color);
+                    } else if !content.colors.is_empty() {
+                        content.colors.push([1.0, 1.0, 1.0]);
+                    }
+                }
+                "vt" => {
+                    let values = parse_floats(tokens).ok_or_else(|| error("invalid texcoord"))?;
+                    if values.is_empty() {
+                        return Err(error("texcoord needs at least 1 coordinate"));
+                    }
+                    content
+                        .texcoords
+                        .push([values[0], values.get(1).copied().unwrap_or(0.0)]);
+                }
+                "vn" => {
+                    let values = parse_floats(tokens).ok_or_else(|| error("invalid normal"))?;
+                    if values.len() < 3 {
+                        return Err(error("normal needs 3 coordinates"));
+                    }
+                    content.normals.push([values[0], values[1], values[2]]);
+                }
+                "f" => {
+                    let face = tokens
+                        .map(|token| content.parse_corner(token))
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(|| error("invalid face"))?;
+                    if face.len() < 3 {
+                        return Err(error("face needs at least 3 vertices"));
+                    }
+
+                    let submesh_changed = content.submeshes.last().is_none_or(|last| {
+                        last.object != object || last.groups != groups || last.material != material
+                    });
+                    if submesh_changed {
+                        let start = content.faces.len();
+                        content.submeshes.push(ObjSubmesh {
+                            object: object.clone(),
+                            groups: groups.clone(),
+                            material: material.clone(),
+                            faces: start..start,
+                        });
+                    }
+                    content.submeshes.last_mut().unwrap().faces.end += 1;
+                    content.faces.push(face);
+                }
+                "o" => object = Some(rest()),
+                "g" => groups = tokens.map(str::to_owned).collect(),
+                "usemtl" => material = Some(rest()),
+                "mtllib" => content.material_libraries.push(rest()),
+                _ => {}
+            }
+        }
+
+        // vertices after the last colored one
+        if !content.colors.is_empty() {
+            content
+                .colors
+                .resize(content.positions.len(), [1.0, 1.0, 1.0]);
+        }
+
+        Ok(content)
+    }
+
+    /// parse `v`, `v/vt`, `v//vn` or `v/vt/vn`, negative indices are relative to the end
+    fn parse_corner(&self, token: &str) -> Option<Corner> {
+        let mut parts = token.split('/');
+        let position = resolve_index(parts.next()?, self.positions.len())?;
+        let texcoord = match parts.next() {
+            Some("") | None => None,
+            Some(index) => Some(resolve_index(index, self.texcoords.len())?),
+        };
+        let normal = match parts.next() {
+            Some("") | None => None,
+            Some(index) => Some(resolve_index(index, self.normals.len())?),
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some((position, texcoord, normal))
+    }
+}
+
+fn resolve_index(index: &str, len: usize) -> Option<usize> {
+    let index = index.parse::<i64>().ok()?;
+    let resolved = if index > 0 {
+        index - 1
+    } else {
+        len as i64 + index
+    };
+    if index == 0 || resolved < 0 || resolved >= len as i64 {
+        None
+    } else {
+        Some(resolved as usize)
+    }
+}
+
+fn parse_floats<'a>(tokens: impl Iterator<Item = &'a str>) -> Option<Vec<f32>> {
+    tokens.map(|token| token.parse().ok()).collect()
+}
+
+/// lines with comments removed and `\` continuations joined, together with their line numbers
+fn logical_lines<R: BufRead>(
+    reader: R,
+) -> impl Iterator<Item = (usize, Result<String, LoadError>)> {
+    let mut lines = reader.lines().enumerate();
+    std::iter::from_fn(move || {
+        let (number, line) = lines.next()?;
+        let mut line = match line {
+            Ok(line) => line,
+            Err(err) => return Some((number + 1, Err(LoadError::Io(err)))),
+        };
+        while line.trim_end().ends_with('\\') {
+            let trimmed = line.trim_end();
+            line.truncate(trimmed.len() - 1);
+            line.push(' ');
+            match lines.next() {
+                Some((_, Ok(next))) => line.push_str(&next),
+                Some((_, Err(err))) => return Some((number + 1, Err(LoadError::Io(err)))),
+                None => break,
+            }
+        }
+        if let Some(comment) = line.find('#') {
+            line.truncate(comment);
+        }
+        Some((number + 1, Ok(line)))
+    })
+}
+
+/// load materials from a `.mtl` file
+pub fn load_materials<Path: AsRef<std::path::Path>>(
+    path: Path,
+) -> Result<Vec<ObjMaterial>, LoadError> {
+    let file = std::fs::File::open(path.as_ref())?;
+    load_materials_from_reader(std::io::BufReader::new(file))
+}
+
+pub fn load_materials_from_reader<R: BufRead>(reader: R) -> Result<Vec<ObjMaterial>, LoadError> {
+    let mut materials: Vec<ObjMaterial> = vec![];
+    for (line_number, line) in logical_lines(reader) {
+        let line = line?;
+        let error =
+            |info: &str| LoadError::parse_at_line(line_number, format!("{}, '{}'", info, line));
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let rest = line[keyword.len()..].trim().to_owned();
+
+        if keyword == "newmtl" {
+            materials.push(ObjMaterial {
+                name: rest,
+                ..Default::default()
+            });
+            continue;
+        }
+        let material = materials
+            .last_mut()
+            .ok_or_else(|| error("statement before 'newmtl'"))?;
+        let color = |tokens| match parse_floats(tokens) {
+            Some(values) if values.len() >= 3 => Ok(Some([values[0], values[1], values[2]])),
+            _ => Err(error("invalid color")),
+        };
+        let float = |value: &str| {
+            value
+                .parse::<f32>()
+                .map(Some)
+                .map_err(|_| error("invalid number"))
+        };
+        // texture statements may have options before the file name
+        let texture = || rest.split_whitespace().last().map(str::to_owned);
+
+        match keyword {
+            "Ka" => material.ambient = color(tokens)?,
+            "Kd" => material.diffuse = color(tokens)?,
+            "Ks" => material.specular = color(tokens)?,
+            "Ke" => material.emissive = color(tokens)?,
+            "Ns" => material.shininess = float(&rest)?,
+            "d" => material.dissolve = float(&rest)?,
+            "Tr" => material.dissolve = float(&rest)?.map(|tr| 1.0 - tr),
+            "Ni" => material.optical_density = float(&rest)?,
+            "illum" => {
+                material.illumination =
+                    Some(rest.parse().map_err(|_| error("invalid illumination"))?)
+            }
+            "map_Kd" => material.diffuse_texture = texture(),
+            "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_texture = texture(),
+            _ => material.extra.push((keyword.to_owned(), rest)),
+        }
+    }
+    Ok(materials)
+}
+
+/// save materials to a `.mtl` file
+pub fn save_materials<Path: AsRef<std::path::Path>>(
+    path: Path,
+    materials: &[ObjMaterial],
+) -> Result<(), SaveError> {
+    save_materials_to_writer(create_file(path)?, materials)
+}
+
+pub fn save_materials_to_writer<W: Write>(
+    mut writer: W,
+    materials: &[ObjMaterial],
+) -> Result<(), SaveError> {
+    let mut write = || -> std::io::Result<()> {
+        for material in materials {
+            writeln!(writer, "newmtl {}", material.name)?;
+            let colors = [
+                ("Ka", material.ambient),
+                ("Kd", material.diffuse),
+                ("Ks", material.specular),
+                ("Ke", material.emissive),
+            ];
+            for (keyword, color) in colors {
+                if let Some(c) = color {
+                    writeln!(writer, "{} {} {} {}", keyword, c[0], c[1], c[2])?;
+                }
+            }
+            let floats = [
+                ("Ns", material.shininess),
+                ("d", material.dissolve),
+                ("Ni", material.optical_density),
+            ];
+            for (keyword, value) in floats {
+                if let Some(value) = value {
+                    writeln!(writer, "{} {}", keyword, value)?;
+                }
+            }
+            if let Some(illumination) = material.illumination {
+                writeln!(writer, "illum {}", illumination)?;
+            }
+            if let Some(texture) = &material.diffuse_texture {
+                writeln!(writer, "map_Kd {}", texture)?;
+            }
+            if let Some(texture) = &material.normal_texture {
+                writeln!(writer, "map_Bump {}", texture)?;
+            }
+            for (keyword, rest) in &material.extra {
+                writeln!(writer, "{} {}", keyword, rest)?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    };
+    write().map_err(SaveError::Io)
+}
+
+/// load all `mtllib` files, relative to the directory of the obj file,
+/// libraries that don't exist are skipped
+fn load_material_libraries(
+    obj_path: &std::path::Path,
+    libraries: &[String],
+) -> Result<Vec<ObjMaterial>, LoadError> {
+    let dir = obj_path
+        .parent()
+        .unwrap_or_else(|| std::path::Path::new(""));
+    let mut materials = vec![];
+    for library in libraries {
+        let path = dir.join(library);
+        if path.is_file() {
+            materials.extend(load_materials(path)?);
+        }
+    }
+    Ok(materials)
+}
+
+/// write `materials` to a `.mtl` file next to the obj file, and return the `mtllib` name
+fn save_material_library(
+    obj_path: &std::path::Path,
+    materials: &[ObjMaterial],
+) -> Result<Option<String>, SaveError> {
+    if materials.is_empty() {
+        return Ok(None);
+    }
+    let mtl_path = obj_path.with_extension("mtl");
+    save_materials(&mtl_path, materials)?;
+    Ok(mtl_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned()))
+}
+
+/// write `o`, `g` and `usemtl` statements where a submesh starts
+struct SubmeshWriter<'a> {
+    submeshes: &'a [ObjSubmesh],
+    next: usize,
+    object: Option<&'a String>,
+    groups: Option<&'a Vec<String>>,
+    material: Option<&'a String>,
+}
+
+impl<'a> SubmeshWriter<'a> {
+    fn new(submeshes: &'a [ObjSubmesh]) -> Self {
+        Self {
+            submeshes,
+            next: 0,
+            object: None,
+            groups: None,
+            material: None,
+        }
+    }
+
+    fn before_face<W: Write>(&mut self, writer: &mut W, face: usize) -> std::io::Result<()> {
+        while let Some(submesh) = self.submeshes.get(self.next) {
+            if submesh.faces.start > face {
+                break;
+            }
+            self.next += 1;
+            if submesh.faces.is_empty() {
+                continue;
+            }
+            if let Some(object) = &submesh.object {
+                if self.object != Some(object) {
+                    writeln!(writer, "o {}", object)?;
+                    self.object = Some(object);
+                }
+            }
+            if self.groups != Some(&submesh.groups) && !submesh.groups.is_empty() {
+                writeln!(writer, "g {}", submesh.groups.join(" "))?;
+            }
+            self.groups = Some(&submesh.groups);
+            if let Some(material) = &submesh.material {
+                if self.material != Some(material) {
+                    writeln!(writer, "usemtl {}", material)?;
+                    self.material = Some(material);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `v`, `v/vt`, `v//vn` or `v/vt/vn` with 1-based indices
+fn write_corner<W: Write>(
+    writer: &mut W,
+    position: usize,
+    texcoord: Option<usize>,
+    normal: Option<usize>,
+) -> std::io::Result<()> {
+    match (texcoord, normal) {
+        (None, None) => write!(writer, " {}", position + 1),
+        (Some(t), None) => write!(writer, " {}/{}", position + 1, t + 1),
+        (None, Some(n)) => write!(writer, " {}//{}", position + 1, n + 1),
+        (Some(t), Some(n)) => write!(writer, " {}/{}/{}", position + 1, t + 1, n + 1),
+    }
+}
@@ -0,0 +1,300 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use crate::{
+    io::{
+        obj::{ObjContent, ObjMaterial, ObjSubmesh, SubmeshWriter},
+        LoadError, SaveError,
+    },
+    trimesh::{MeshIndices, PrimitiveTopology, Submesh, TriMesh, VertexAttribute},
+};
+
+pub struct ObjTriMesh {
+    /// has `POSITION`, and `TEXCOORD`, `NORMAL` and `COLOR` if they are given by the file
+    pub mesh: TriMesh,
+    /// face ranges are triangle ranges
+    pub submeshes: Vec<ObjSubmesh>,
+    /// names given by `usemtl` in order of first use, material ids of submeshes of `mesh` are
+    /// indices of them
+    pub material_names: Vec<String>,
+    /// materials of all `mtllib` files that could be loaded
+    pub materials: Vec<ObjMaterial>,
+    /// file names given by `mtllib`
+    pub material_libraries: Vec<String>,
+}
+
+/// load an obj file, vertices are de-indexed so that each `v/vt/vn` triple becomes a vertex,
+/// and polygons are triangulated as fans
+pub fn load_to_trimesh<Path: AsRef<std::path::Path>>(path: Path) -> Result<ObjTriMesh, LoadError> {
+    let file = std::fs::File::open(path.as_ref())?;
+    let mut mesh = load_to_trimesh_from_reader(std::io::BufReader::new(file))?;
+    mesh.materials = super::load_material_libraries(path.as_ref(), &mesh.material_libraries)?;
+    Ok(mesh)
+}
+
+/// `mtllib` files are not loaded, `ObjTriMesh::materials` is always empty
+pub fn load_to_trimesh_from_reader<Reader: BufRead>(
+    reader: Reader,
+) -> Result<ObjTriMesh, LoadError> {
+    let content = ObjContent::parse(reader)?;
+
+    let has_texcoord = content.faces.iter().flatten().any(|c| c.1.is_some());
+    let has_normal = content.faces.iter().flatten().any(|c| c.2.is_some());
+    let has_color = !content.colors.is_empty();
+
+    let mut vertices = HashMap::new();
+    let mut positions = vec![];
+    let mut texcoords = vec![];
+    let mut normals = vec![];
+    let mut colors = vec![];
+    let mut indices = vec![];
+    let mut first_triangle = Vec::with_capacity(content.faces.len() + 1);
+    for face in &content.faces {
+        first_triangle.push(indices.len() / 3);
+        let face = face
+            .iter()
+            .map(|corner| {
+                *vertices.entry(*corner).or_insert_with(|| {
+                    let (v, vt, vn) = *corner;
+                    positions.push(content.positions[v]);
+                    if has_texcoord {
+                        texcoords.push(vt.map_or([0.0; 2], |vt| content.texcoords[vt]));
+                    }
+                    if has_normal {
+                        normals.push(vn.map_or([0.0; 3], |vn| content.normals[vn]));
+                    }
+                    if has_color {
+                        colors.push(content.colors[v]);
+                    }
+                    positions.len() as u32 - 1
+                })
+            })
+            .collect::<Vec<_>>();
+        for i in 1..face.len() - 1 {
+            indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+        }
+    }
+    first_triangle.push(indices.len() / 3);
+
+    let mut vertex_attributes = HashMap::new();
+    vertex_attributes.insert(TriMesh::POSITION.into(), VertexAttribute::Float3(positions));
+    if has_texcoord {
+        vertex_attributes.insert(TriMesh::TEXCOORD.into(), VertexAttribute::Float2(texcoords));
+    }
+    if has_normal {
+        vertex_attributes.insert(TriMesh::NORMAL.into(), VertexAttribute::Float3(normals));
+    }
+    if has_color {
+        vertex_attributes.insert(TriMesh::COLOR.into(), VertexAttribute::Float3(colors));
+    }
+
+    let submeshes = content
+        .submeshes
+        .into_iter()
+        .map(|submesh| ObjSubmesh {
+            faces: first_triangle[submesh.faces.start]..first_triangle[submesh.faces.end],
+            ..submesh
+        })
+        .collect::<Vec<_>>();
+
+    let mut material_names: Vec<String> = vec![];
+    let mut mesh = TriMesh::new(vertex_attributes, Some(MeshIndices::U32(indices)));
+    mesh.set_submeshes(
+        submeshes
+            .iter()
+            .map(|submesh| {
+                let material_id = submesh.material.as_ref().map(|material| {
+                    match material_names.iter().position(|name| name == material) {
+                        Some(id) => id,
+                        None => {
+                            material_names.push(material.clone());
+                            material_names.len() - 1
+                        }
+                    }
+                });
+                let faces = &submesh.faces;
+                Submesh::new(3 * faces.start, 3 * faces.len(), material_id)
+            })
+            .collect(),
+    );
+
+    Ok(ObjTriMesh {
+        mesh,
+        submeshes,
+        material_names,
+        materials: vec![],
+        material_libraries: content.material_libraries,
+    })
+}
+
+impl ObjSubmesh {
+    /// submeshes of a triangle list `TriMesh` sorted by start, `usemtl` is the name of the material id
+    /// in `material_names`
+    pub fn from_submeshes(submeshes: &[Submesh], material_names: &[String]) -> Vec<ObjSubmesh> {
+        let mut submeshes = submeshes.to_vec();
+        submeshes.sort_by_key(|submesh| submesh.start);
+        submeshes
+            .into_iter()
+            .map(|submesh| ObjSubmesh {
+                object: None,
+                groups: vec![],
+                material: submesh
+                    .material_id
+                    .and_then(|id| material_names.get(id).cloned()),
+                faces: submesh.start / 3..(submesh.start + submesh.count) / 3,
+            })
+            .collect()
+    }
+}
+
+/// save `POSITION`, `TEXCOORD`, `NORMAL` and `COLOR` of `mesh`,
+/// non-empty `materials` are saved to a `.mtl` file with the same name
+pub fn save_trimesh<Path: AsRef<std::path::Path>>(
+    path: Path,
+    mesh: &TriMesh,
+    submeshes: &[ObjSubmesh],
+    materials: &[ObjMaterial],
+) -> Result<(), SaveError> {
+    let library = super::save_material_library(path.as_ref(), materials)?;
+    save_trimesh_to_writer(
+        super::create_file(path)?,
+        mesh,
+        submeshes,
+        library.as_deref(),
+    )
+}
+
+/// `material_library` is written as `mtllib`
+pub fn save_trimesh_to_writer<Writer: Write>(
+    mut writer: Writer,
+    mesh: &TriMesh,
+    submeshes: &[ObjSubmesh],
+    material_library: Option<&str>,
+) -> Result<(), SaveError> {
+    let positions = mesh.attribute_as::<[f32; 3]>(TriMesh::POSITION)?;
+    let texcoords = mesh
+        .attribute(TriMesh::TEXCOORD)
+        .map(|_| mesh.attribute_as::<[f32; 2]>(TriMesh::TEXCOORD))
+        .transpose()?;
+    let normals = mesh
+        .attribute(TriMesh::NORMAL)
+        .map(|_| mesh.attribute_as::<[f32; 3]>(TriMesh::NORMAL))
+        .transpose()?;
+    let colors = mesh
+        .attribute(TriMesh::COLOR)
+        .map(|_| mesh.attribute_as::<[f32; 3]>(TriMesh::COLOR))
+        .transpose()?;
+
+    let mut write = || -> std::io::Result<()> {
+        if let Some(library) = material_library {
+            writeln!(writer, "mtllib {}", library)?;
+        }
+        for (i, p) in positions.iter().enumerate() {
+            match colors {
+                Some(colors) => {
+                    let c = colors[i];
+                    writeln!(
+                        writer,
+                        "v {} {} {} {} {} {}",
+                        p[0], p[1], p[2], c[0], c[1], c[2]
+                    )?
+                }
+                None => writeln!(writer, "v {} {} {}", p[0], p[1], p[2])?,
+            }
+        }
+        for t in texcoords.into_iter().flatten() {
+            writeln!(writer, "vt {} {}", t[0], t[1])?;
+        }
+        for n in normals.into_iter().flatten() {
+            writeln!(writer, "vn {} {} {}", n[0], n[1], n[2])?;
+        }
+
+        let mut submesh_writer = SubmeshWriter::new(submeshes);
+        for (i, tri) in mesh.triangles().into_iter().enumerate() {
+            submesh_writer.before_face(&mut writer, i)?;
+            write!(writer, "f")?;
+            for v in tri {
+                super::write_corner(&mut writer, v, texcoords.map(|_| v), normals.map(|_| v))?;
+            }
+            writeln!(writer)?;
+        }
+        // lines and points are not part of submeshes
+        for [v1, v2] in mesh.lines() {
+            writeln!(writer, "l {} {}", v1 + 1, v2 + 1)?;
+        }
+        if mesh.topology() == PrimitiveTopology::PointList {
+            for v in mesh.vertex_runs().concat() {
+                writeln!(writer, "p {}", v + 1)?;
+            }
+        }
+        writer.flush()
+    };
+    write().map_err(SaveError::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::TriMeshBuilder;
+
+    #[test]
+    fn round_trip() {
+        let positions = vec![
+            [0.0f32, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.25],
+        ];
+        let texcoords = vec![[0.0f32, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.75]];
+        let normals = vec![
+            [0.0f32, 0.0, 1.0],
+            [0.0, 0.0, 1.0],
+            [0.0, 0.6, 0.8],
+            [0.0, 0.6, 0.8],
+        ];
+        let mut mesh = TriMeshBuilder::new()
+            .attribute(TriMesh::POSITION, positions.clone())
+            .attribute(TriMesh::TEXCOORD, texcoords.clone())
+            .attribute(TriMesh::NORMAL, normals.clone())
+            .indices(vec![0, 1, 2, 0, 2, 3])
+            .build()
+            .unwrap();
+        mesh.set_submeshes(vec![
+            Submesh::new(0, 3, Some(0)),
+            Submesh::new(3, 3, Some(1)),
+        ]);
+        let material_names = vec!["red".to_owned(), "blue".to_owned()];
+
+        let mut bytes = vec![];
+        let submeshes = ObjSubmesh::from_submeshes(mesh.submeshes(), &material_names);
+        save_trimesh_to_writer(&mut bytes, &mesh, &submeshes, None).unwrap();
+        let loaded = load_to_trimesh_from_reader(bytes.as_slice()).unwrap();
+
+        assert_eq!(loaded.material_names, material_names);
+        assert_eq!(loaded.mesh.submeshes(), mesh.submeshes());
+        assert_eq!(loaded.mesh.triangles(), mesh.triangles());
+        assert_eq!(
+            loaded
+                .mesh
+                .attribute_as::<[f32; 3]>(TriMesh::POSITION)
+                .unwrap(),
+            positions.as_slice()
+        );
+        assert_eq!(
+            loaded
+                .mesh
+                .attribute_as::<[f32; 2]>(TriMesh::TEXCOORD)
+                .unwrap(),
+            texcoords.as_slice()
+        );
+        assert_eq!(
+            loaded
+                .mesh
+                .attribute_as::<[f32; 3]>(TriMesh::NORMAL)
+                .unwrap(),
+            normals.as_slice()
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use crate::{
+    halfedge::{check_manifold, HalfEdgeMesh},
+    io::{
+        off::OffContent,
+        ply::{data_from_props, FromPropertyMap, Property, PropertyMap, ToPropertyMap},
+        LoadError, SaveError,
+    },
+};
+
+/// load an off file, vertex data is created from properties `x`, `y`, `z`, and `nx`, `ny`, `nz`,
+/// `red`, `green`, `blue`, `alpha`, `s`, `t` if they are given by the file, face data is created
+/// from properties `red`, `green`, `blue`, `alpha` if the face has a color, colors are in `[0, 1]`
+///
+/// an error is returned if faces don't form a manifold
+pub fn load_to_halfedge<Path, VData, EData, FData>(
+    path: Path,
+) -> Result<HalfEdgeMesh<VData, EData, FData>, LoadError>
+where
+    Path: AsRef<std::path::Path>,
+    VData: Default + FromPropertyMap,
+    EData: Default + FromPropertyMap,
+    FData: Default + FromPropertyMap,
+{
+    let file = std::fs::File::open(path.as_ref())?;
+    load_to_halfedge_from_reader(std::io::BufReader::new(file))
+}
+
+pub fn load_to_halfedge_from_reader<Reader, VData, EData, FData>(
+    reader: Reader,
+) -> Result<HalfEdgeMesh<VData, EData, FData>, LoadError>
+where
+    Reader: BufRead,
+    VData: Default + FromPropertyMap,
+    EData: Default + FromPropertyMap,
+    FData: Default + FromPropertyMap,
+{
+    let content = OffContent::parse(reader)?;
+    check_manifold(&content.faces).map_err(LoadError::Topology)?;
+
+    let mut vertices_data = HashMap::with_capacity(content.positions.len());
+    for (v, p) in content.positions.iter().enumerate() {
+        let mut props = PropertyMap::default();
+        insert_f32s(&mut props, &["x", "y", "z"], p);
+        if let Some(normals) = &content.normals {
+            insert_f32s(&mut props, &["nx", "ny", "nz"], &normals[v]);
+        }
+        if let Some(colors) = &content.colors {
+            insert_f32s(&mut props, &["red", "green", "blue", "alpha"], &colors[v]);
+        }
+        if let Some(texcoords) = &content.texcoords {
+            insert_f32s(&mut props, &["s", "t"], &texcoords[v]);
+        }
+        vertices_data.insert(v, data_from_props::<VData>(props, "vertex", v)?);
+    }
+
+    let faces_data = content
+        .face_colors
+        .iter()
+        .enumerate()
+        .map(|(f, color)| {
+            let mut props = PropertyMap::default();
+            if let Some(color) = color {
+                insert_f32s(&mut props, &["red", "green", "blue", "alpha"], color);
+            }
+            Ok((f, data_from_props::<FData>(props, "face", f)?))
+        })
+        .collect::<Result<_, LoadError>>()?;
+
+    Ok(HalfEdgeMesh::new(
+        content.faces,
+        vertices_data,
+        HashMap::new(),
+        faces_data,
+    ))
+}
+
+/// save a half-edge mesh, properties are the same as those of `load_to_halfedge`,
+/// normals, colors and texcoords of vertices are saved if all vertices have them,
+/// missing alpha is `1`
+pub fn save_halfedge<Path, VData, EData, FData>(
+    path: Path,
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+) -> Result<(), SaveError>
+where
+    Path: AsRef<std::path::Path>,
+    VData: ToPropertyMap,
+    FData: ToPropertyMap,
+{
+    save_halfedge_to_writer(super::create_file(path)?, mesh)
+}
+
+pub fn save_halfedge_to_writer<Writer, VData, EData, FData>(
+    writer: Writer,
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+) -> Result<(), SaveError>
+where
+    Writer: Write,
+    VData: ToPropertyMap,
+    FData: ToPropertyMap,
+{
+    let mut positions = vec![];
+    let mut normals = Some(vec![]);
+    let mut colors = Some(vec![]);
+    let mut texcoords = Some(vec![]);
+    for vref in mesh.vertices() {
+        let props = mesh.vertex_data(&vref).to_proprety_map();
+        positions.push(props.get_vec3(["x", "y", "z"]).unwrap_or([0.0; 3]));
+        normals = normals
+            .zip(props.get_vec3(["nx", "ny", "nz"]).ok())
+            .map(push);
+        colors = colors.zip(props.get_color_rgba().ok()).map(push);
+        texcoords = texcoords.zip(props.get_vec2(["s", "t"]).ok()).map(push);
+    }
+
+    let mut faces = vec![];
+    let mut face_colors = vec![];
+    for fref in mesh.faces() {
+        if fref.is_boundary(mesh) {
+            continue;
+        }
+        let mut face = vec![];
+        let mut he = fref.halfedge(mesh);
+        loop {
+            face.push(he.vertex(mesh).id);
+            he = he.next(mesh);
+            if he == fref.halfedge(mesh) {
+                break;
+            }
+        }
+        faces.push(face);
+        let props = mesh.face_data(&fref).to_proprety_map();
+        face_colors.push(props.get_color_rgba().ok());
+    }
+
+    let content = OffContent {
+        positions,
+        normals,
+        colors,
+        texcoords,
+        faces,
+        face_colors,
+    };
+    content.write(writer)
+}
+
+fn insert_f32s(props: &mut PropertyMap, keys: &[&'static str], values: &[f32]) {
+    for (key, value) in keys.iter().zip(values) {
+        props.map.insert((*key).into(), Property::F32(*value));
+    }
+}
+
+fn push<T>((mut values, value): (Vec<T>, T)) -> Vec<T> {
+    values.push(value);
+    values
+}
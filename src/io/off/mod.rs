@@ -0,0 +1,282 @@
+mod halfedge;
+mod trimesh;
+
+pub use halfedge::*;
+pub use trimesh::*;
+
+use std::io::{BufRead, Write};
+
+use super::{create_file, LoadError, SaveError};
+
+/// data of an off file before it is converted to a mesh
+#[derive(Default)]
+struct OffContent {
+    positions: Vec<[f32; 3]>,
+    /// given by `NOFF`
+    normals: Option<Vec<[f32; 3]>>,
+    /// given by `COFF`, alpha is `1` if the file only has rgb
+    colors: Option<Vec<[f32; 4]>>,
+    /// given by `STOFF`
+    texcoords: Option<Vec<[f32; 2]>>,
+    faces: Vec<Vec<usize>>,
+    face_colors: Vec<Option<[f32; 4]>>,
+}
+
+impl OffContent {
+    fn parse<R: BufRead>(reader: R) -> Result<Self, LoadError> {
+        // lines with comments stripped, empty lines are skipped
+        let mut lines = reader
+            .lines()
+            .enumerate()
+            .filter_map(|(line_number, line)| match line {
+                Ok(line) => {
+                    let line = line.split('#').next().unwrap_or("").trim().to_owned();
+                    (!line.is_empty()).then(|| Ok((line_number + 1, line)))
+                }
+                Err(err) => Some(Err(LoadError::Io(err))),
+            });
+        let mut next_line = || {
+            lines
+                .next()
+                .unwrap_or_else(|| Err(LoadError::parse("unexpected end of file")))
+        };
+        let error = |line_number: usize, info: &str| LoadError::parse_at_line(line_number, info);
+
+        let (line_number, header) = next_line()?;
+        let keyword = header.split_whitespace().next().unwrap_or("");
+        let prefix = keyword
+            .strip_suffix("OFF")
+            .ok_or_else(|| error(line_number, "expect keyword 'OFF'"))?;
+        let has_texcoord = prefix.contains("ST");
+        let has_color = prefix.contains('C');
+        let has_normal = prefix.contains('N');
+        if prefix.contains('4') || prefix.contains('n') || prefix.contains("BINARY") {
+            return Err(error(
+                line_number,
+                "only 3-dimensional ascii off is supported",
+            ));
+        }
+
+        // counts may be on the same line as the keyword
+        let mut counts = header.split_whitespace().skip(1).collect::<Vec<_>>();
+        let counts_line;
+        if counts.is_empty() {
+            counts_line = next_line()?;
+            counts = counts_line.1.split_whitespace().collect();
+        }
+        let counts = counts
+            .iter()
+            .map(|token| token.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| error(line_number, "invalid element counts"))?;
+        if counts.len() < 2 {
+            return Err(error(line_number, "expect numbers of vertices and faces"));
+        }
+        let (num_vertices, num_faces) = (counts[0], counts[1]);
+
+        let mut content = Self {
+            normals: has_normal.then(|| Vec::with_capacity(num_vertices)),
+            colors: has_color.then(|| Vec::with_capacity(num_vertices)),
+            texcoords: has_texcoord.then(|| Vec::with_capacity(num_vertices)),
+            ..Default::default()
+        };
+        for _ in 0..num_vertices {
+            let (line_number, line) = next_line()?;
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            let values = tokens
+                .iter()
+                .map(|token| token.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| error(line_number, "invalid number"))?;
+
+            // color has 3 or 4 components, and it is followed by texcoord
+            let normal_start = 3;
+            let color_start = normal_start + if has_normal { 3 } else { 0 };
+            let texcoord_len = if has_texcoord { 2 } else { 0 };
+            let color_len = values.len().saturating_sub(color_start + texcoord_len);
+            let texcoord_start = color_start
+                + match (has_color, color_len >= 4) {
+                    (true, true) => 4,
+                    (true, false) => 3,
+                    (false, _) => 0,
+                };
+            let len = texcoord_start + texcoord_len;
+            if values.len() < len {
+                return Err(error(line_number, "too few values of vertex"));
+            }
+
+            content.positions.push([values[0], values[1], values[2]]);
+            if let Some(normals) = &mut content.normals {
+                let n = &values[normal_start..];
+                normals.push([n[0], n[1], n[2]]);
+            }
+            if let Some(colors) = &mut content.colors {
+                colors.push(to_color(
+                    &values[color_start..texcoord_start],
+                    &tokens[color_start..texcoord_start],
+                ));
+            }
+            if let Some(texcoords) = &mut content.texcoords {
+                let t = &values[texcoord_start..];
+                texcoords.push([t[0], t[1]]);
+            }
+        }
+
+        for _ in 0..num_faces {
+            let (line_number, line) = next_line()?;
+            let mut tokens = line.split_whitespace();
+            let len = tokens
+                .next()
+                .and_then(|token| token.parse::<usize>().ok())
+                .ok_or_else(|| error(line_number, "invalid number of face vertices"))?;
+            let face = tokens
+                .by_ref()
+                .take(len)
+                .map(|token| token.parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| error(line_number, "invalid vertex index"))?;
+            if face.len() != len {
+                return Err(error(line_number, "too few vertex indices"));
+            }
+            if len < 3 {
+                return Err(error(line_number, "face has less than 3 vertices"));
+            }
+            if face.iter().any(|v| *v >= num_vertices) {
+                return Err(error(line_number, "vertex index out of range"));
+            }
+
+            // a single value is a colormap index, which is ignored
+            let rest = tokens.collect::<Vec<_>>();
+            let color = if rest.len() >= 3 {
+                let tokens = &rest[..rest.len().min(4)];
+                let values = tokens
+                    .iter()
+                    .map(|token| token.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| error(line_number, "invalid face color"))?;
+                Some(to_color(&values, tokens))
+            } else {
+                None
+            };
+
+            content.faces.push(face);
+            content.face_colors.push(color);
+        }
+
+        Ok(content)
+    }
+
+    /// keyword of the header
+    fn keyword(&self) -> String {
+        let mut keyword = String::new();
+        if self.texcoords.is_some() {
+            keyword.push_str("ST");
+        }
+        if self.colors.is_some() {
+            keyword.push('C');
+        }
+        if self.normals.is_some() {
+            keyword.push('N');
+        }
+        keyword + "OFF"
+    }
+
+    fn write<W: Write>(&self, mut writer: W) -> Result<(), SaveError> {
+        let mut write = || -> std::io::Result<()> {
+            writeln!(writer, "{}", self.keyword())?;
+            writeln!(writer, "{} {} 0", self.positions.len(), self.faces.len())?;
+            for (v, p) in self.positions.iter().enumerate() {
+                write!(writer, "{} {} {}", p[0], p[1], p[2])?;
+                if let Some(normals) = &self.normals {
+                    let n = normals[v];
+                    write!(writer, " {} {} {}", n[0], n[1], n[2])?;
+                }
+                if let Some(colors) = &self.colors {
+                    write_color(&mut writer, colors[v])?;
+                }
+                if let Some(texcoords) = &self.texcoords {
+                    let t = texcoords[v];
+                    write!(writer, " {} {}", t[0], t[1])?;
+                }
+                writeln!(writer)?;
+            }
+            for (face, color) in self.faces.iter().zip(&self.face_colors) {
+                write!(writer, "{}", face.len())?;
+                for v in face {
+                    write!(writer, " {}", v)?;
+                }
+                if let Some(color) = color {
+                    write_color(&mut writer, *color)?;
+                }
+                writeln!(writer)?;
+            }
+            writer.flush()
+        };
+        write().map_err(SaveError::Io)
+    }
+}
+
+/// colors are written with a decimal point, so that they aren't read back as integers in `[0, 255]`
+fn write_color<W: Write>(writer: &mut W, c: [f32; 4]) -> std::io::Result<()> {
+    write!(writer, " {:?} {:?} {:?} {:?}", c[0], c[1], c[2], c[3])
+}
+
+/// colors written as integers are in `[0, 255]`
+fn to_color(values: &[f32], tokens: &[&str]) -> [f32; 4] {
+    let integral = tokens.iter().all(|token| !token.contains(['.', 'e', 'E']));
+    let scale = if integral { 1.0 / 255.0 } else { 1.0 };
+    let alpha = values.get(3).map_or(1.0, |a| a * scale);
+    [
+        values[0] * scale,
+        values[1] * scale,
+        values[2] * scale,
+        alpha,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_colors_round_trip() {
+        let content = OffContent {
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            faces: vec![vec![0, 1, 2]],
+            face_colors: vec![Some([1.0, 1.0, 0.0, 1.0])],
+            ..Default::default()
+        };
+        let mut bytes = vec![];
+        content.write(&mut bytes).unwrap();
+        let parsed = OffContent::parse(bytes.as_slice()).unwrap();
+        assert_eq!(parsed.face_colors, content.face_colors);
+    }
+
+    #[test]
+    fn vertex_colors_before_texcoords() {
+        let rgb = "STCOFF\n3 1 0\n0 0 0 1.0 0.5 0.0 0.25 0.75\n1 0 0 0 0 255 1 0\n\
+                   0 1 0 0.0 1.0 0.0 0 1\n3 0 1 2\n";
+        let parsed = OffContent::parse(rgb.as_bytes()).unwrap();
+        assert_eq!(
+            parsed.colors.unwrap(),
+            [
+                [1.0, 0.5, 0.0, 1.0],
+                [0.0, 0.0, 1.0, 1.0],
+                [0.0, 1.0, 0.0, 1.0]
+            ]
+        );
+        assert_eq!(
+            parsed.texcoords.unwrap(),
+            [[0.25, 0.75], [1.0, 0.0], [0.0, 1.0]]
+        );
+
+        let rgba = "STCOFF\n3 1 0\n0 0 0 1.0 0.5 0.0 0.5 0.25 0.75\n1 0 0 0 0 255 255 1 0\n\
+                    0 1 0 0.0 1.0 0.0 1.0 0 1\n3 0 1 2\n";
+        let parsed = OffContent::parse(rgba.as_bytes()).unwrap();
+        assert_eq!(parsed.colors.unwrap()[0], [1.0, 0.5, 0.0, 0.5]);
+        assert_eq!(
+            parsed.texcoords.unwrap(),
+            [[0.25, 0.75], [1.0, 0.0], [0.0, 1.0]]
+        );
+    }
+}
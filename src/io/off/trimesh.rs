@@ -0,0 +1,135 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use crate::{
+    io::{off::OffContent, LoadError, SaveError},
+    trimesh::{AttributeError, MeshIndices, TriMesh, VertexAttribute, VertexAttributeFormat},
+};
+
+/// load an off file, polygons are triangulated as fans
+///
+/// the mesh has `POSITION`, and `NORMAL`, `COLOR` and `TEXCOORD` if they are given by the file,
+/// `COLOR` is `Float3` if all alpha values are `1` and `Float4` otherwise; face colors are ignored
+pub fn load_to_trimesh<Path: AsRef<std::path::Path>>(path: Path) -> Result<TriMesh, LoadError> {
+    let file = std::fs::File::open(path.as_ref())?;
+    load_to_trimesh_from_reader(std::io::BufReader::new(file))
+}
+
+pub fn load_to_trimesh_from_reader<Reader: BufRead>(reader: Reader) -> Result<TriMesh, LoadError> {
+    let content = OffContent::parse(reader)?;
+
+    let mut vertex_attributes = HashMap::new();
+    vertex_attributes.insert(
+        TriMesh::POSITION.into(),
+        VertexAttribute::Float3(content.positions),
+    );
+    if let Some(normals) = content.normals {
+        vertex_attributes.insert(TriMesh::NORMAL.into(), VertexAttribute::Float3(normals));
+    }
+    if let Some(colors) = content.colors {
+        let colors = if colors.iter().all(|c| c[3] == 1.0) {
+            VertexAttribute::Float3(colors.iter().map(|c| [c[0], c[1], c[2]]).collect())
+        } else {
+            VertexAttribute::Float4(colors)
+        };
+        vertex_attributes.insert(TriMesh::COLOR.into(), colors);
+    }
+    if let Some(texcoords) = content.texcoords {
+        vertex_attributes.insert(TriMesh::TEXCOORD.into(), VertexAttribute::Float2(texcoords));
+    }
+
+    let mut indices = vec![];
+    for face in &content.faces {
+        for i in 1..face.len() - 1 {
+            indices.extend_from_slice(&[face[0] as u32, face[i] as u32, face[i + 1] as u32]);
+        }
+    }
+
+    Ok(TriMesh::new(
+        vertex_attributes,
+        Some(MeshIndices::U32(indices)),
+    ))
+}
+
+/// save `POSITION`, `NORMAL`, `COLOR` (`Float3` or `Float4`) and `TEXCOORD` of `mesh`
+pub fn save_trimesh<Path: AsRef<std::path::Path>>(
+    path: Path,
+    mesh: &TriMesh,
+) -> Result<(), SaveError> {
+    save_trimesh_to_writer(super::create_file(path)?, mesh)
+}
+
+pub fn save_trimesh_to_writer<Writer: Write>(
+    writer: Writer,
+    mesh: &TriMesh,
+) -> Result<(), SaveError> {
+    let positions = mesh.attribute_as::<[f32; 3]>(TriMesh::POSITION)?;
+    let normals = mesh
+        .attribute(TriMesh::NORMAL)
+        .map(|_| mesh.attribute_as::<[f32; 3]>(TriMesh::NORMAL))
+        .transpose()?;
+    let texcoords = mesh
+        .attribute(TriMesh::TEXCOORD)
+        .map(|_| mesh.attribute_as::<[f32; 2]>(TriMesh::TEXCOORD))
+        .transpose()?;
+    let colors = match mesh.attribute(TriMesh::COLOR) {
+        Some(VertexAttribute::Float3(colors)) => {
+            Some(colors.iter().map(|c| [c[0], c[1], c[2], 1.0]).collect())
+        }
+        Some(VertexAttribute::Float4(colors)) => Some(colors.clone()),
+        Some(attribute) => {
+            return Err(AttributeError::FormatMismatch {
+                name: TriMesh::COLOR.into(),
+                expected: VertexAttributeFormat::Float4,
+                found: attribute.format(),
+            }
+            .into())
+        }
+        None => None,
+    };
+
+    let triangles = mesh.triangles();
+    let content = OffContent {
+        positions: positions.to_vec(),
+        normals: normals.map(|normals| normals.to_vec()),
+        colors,
+        texcoords: texcoords.map(|texcoords| texcoords.to_vec()),
+        face_colors: vec![None; triangles.len()],
+        faces: triangles.into_iter().map(|tri| tri.to_vec()).collect(),
+    };
+    content.write(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::TriMeshBuilder;
+
+    #[test]
+    fn colors_round_trip() {
+        let colors = vec![
+            [1.0f32, 0.0, 0.0, 1.0],
+            [0.0, 1.0, 0.0, 0.5],
+            [0.0, 0.0, 1.0, 0.0],
+        ];
+        let mesh = TriMeshBuilder::new()
+            .attribute(
+                TriMesh::POSITION,
+                vec![[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            )
+            .attribute(TriMesh::COLOR, colors.clone())
+            .indices(vec![0, 1, 2])
+            .build()
+            .unwrap();
+
+        let mut bytes = vec![];
+        save_trimesh_to_writer(&mut bytes, &mesh).unwrap();
+        let loaded = load_to_trimesh_from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(
+            loaded.attribute_as::<[f32; 4]>(TriMesh::COLOR).unwrap(),
+            colors.as_slice()
+        );
+    }
+}
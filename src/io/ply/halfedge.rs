@@ -1,21 +1,44 @@
-use std::collections::HashMap;
-
-use ply_rs::{
-    parser,
-    ply::{self, Addable},
-    writer,
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
 };
 
+use ply_rs::ply;
+
 use crate::{
-    halfedge::HalfEdgeMesh,
+    halfedge::{check_manifold, HalfEdgeMesh},
     io::{
-        ply::{Property, PropertyMap},
+        ply::{
+            ElementData, ExtrasCollector, LoadOptions, LoadReport, PlyExtras, PlyReader,
+            PropertyError, PropertyMap, SaveOptions,
+        },
+        validation::Validator,
         LoadError, SaveError,
     },
 };
 
-pub trait FromPropertyMap {
+pub trait FromPropertyMap: Sized {
     fn from_proprety_map(props: PropertyMap) -> Self;
+
+    /// like `from_proprety_map()`, but fail if a property can't be converted,
+    /// loaders use it to report such properties
+    fn try_from_proprety_map(props: PropertyMap) -> Result<Self, PropertyError> {
+        Ok(Self::from_proprety_map(props))
+    }
+}
+
+/// convert record `record` of `element` by `FromPropertyMap::try_from_proprety_map()`
+pub(crate) fn data_from_props<T: FromPropertyMap>(
+    props: PropertyMap,
+    element: &str,
+    record: usize,
+) -> Result<T, LoadError> {
+    T::try_from_proprety_map(props).map_err(|err| LoadError::Parse {
+        element: Some(element.to_owned()),
+        line: None,
+        offset: None,
+        message: format!("failed to convert record {}, {}", record, err),
+    })
 }
 
 impl<T: From<PropertyMap>> FromPropertyMap for T {
@@ -25,9 +48,7 @@ impl<T: From<PropertyMap>> FromPropertyMap for T {
 }
 
 impl FromPropertyMap for () {
-    fn from_proprety_map(_: PropertyMap) -> Self {
-        ()
-    }
+    fn from_proprety_map(_: PropertyMap) -> Self {}
 }
 
 pub trait ToPropertyMap {
@@ -40,6 +61,12 @@ impl ToPropertyMap for () {
     }
 }
 
+impl ToPropertyMap for PropertyMap {
+    fn to_proprety_map(&self) -> PropertyMap {
+        self.clone()
+    }
+}
+
 pub fn load_to_halfedge<Path, VData, EData, FData>(
     path: Path,
 ) -> Result<HalfEdgeMesh<VData, EData, FData>, LoadError>
@@ -49,104 +76,133 @@ where
     EData: Default + FromPropertyMap,
     FData: Default + FromPropertyMap,
 {
-    let file = std::fs::File::open(path.as_ref()).map_err(|err| LoadError::new(err.to_string()))?;
-    let mut reader = std::io::BufReader::new(file);
-
-    let vertex_parser = parser::Parser::<PropertyMap>::new();
-    let face_parser = parser::Parser::<PropertyMap>::new();
-    let edge_parser = parser::Parser::<PropertyMap>::new();
-
-    let header = vertex_parser
-        .read_header(&mut reader)
-        .map_err(|err| LoadError::new(err.to_string()))?;
-
-    let mut vertices_list = vec![];
-    let mut faces_list = vec![];
-    let mut edges_list = vec![];
-
-    for (_, element) in &header.elements {
-        match element.name.as_ref() {
-            "vertex" => {
-                vertices_list = vertex_parser
-                    .read_payload_for_element(&mut reader, &element, &header)
-                    .map_err(|err| LoadError::new(err.to_string()))?
-            }
-            "face" => {
-                faces_list = face_parser
-                    .read_payload_for_element(&mut reader, &element, &header)
-                    .map_err(|err| LoadError::new(err.to_string()))?
-            }
-            "edge" => {
-                edges_list = edge_parser
-                    .read_payload_for_element(&mut reader, &element, &header)
-                    .map_err(|err| LoadError::new(err.to_string()))?
-            }
-            _ => {}
-        }
-    }
+    let file = std::fs::File::open(path.as_ref())?;
+    load_to_halfedge_from_reader(std::io::BufReader::new(file))
+}
 
-    let mut faces = Vec::with_capacity(faces_list.len());
-    let mut faces_data = HashMap::with_capacity(faces_list.len());
-    for mut f in faces_list {
-        let vertex_index = if f.map.contains_key("vertex_index") {
-            f.map.remove("vertex_index".into())
-        } else if f.map.contains_key("vertex_indices") {
-            f.map.remove("vertex_indices".into())
-        } else {
-            None
-        };
-        if let Some(vertex_index) = vertex_index {
-            if let Property::I32List(vertex_index) = vertex_index {
-                let vertex_index = vertex_index.iter().map(|i| *i as usize).collect::<Vec<_>>();
-                faces_data.insert(faces.len(), FData::from_proprety_map(f));
-                faces.push(vertex_index);
-            } else if let Property::U32List(vertex_index) = vertex_index {
-                let vertex_index = vertex_index.iter().map(|i| *i as usize).collect::<Vec<_>>();
-                faces_data.insert(faces.len(), FData::from_proprety_map(f));
-                faces.push(vertex_index);
-            } else if let Property::I16List(vertex_index) = vertex_index {
-                let vertex_index = vertex_index.iter().map(|i| *i as usize).collect::<Vec<_>>();
-                faces_data.insert(faces.len(), FData::from_proprety_map(f));
-                faces.push(vertex_index);
-            } else if let Property::U16List(vertex_index) = vertex_index {
-                let vertex_index = vertex_index.iter().map(|i| *i as usize).collect::<Vec<_>>();
-                faces_data.insert(faces.len(), FData::from_proprety_map(f));
+pub fn load_to_halfedge_from_reader<Reader, VData, EData, FData>(
+    reader: Reader,
+) -> Result<HalfEdgeMesh<VData, EData, FData>, LoadError>
+where
+    Reader: BufRead,
+    VData: Default + FromPropertyMap,
+    EData: Default + FromPropertyMap,
+    FData: Default + FromPropertyMap,
+{
+    Ok(load_to_halfedge_from_reader_with_options(reader, &LoadOptions::default())?.0)
+}
+
+/// also return comments, obj_info and elements other than `vertex`, `face` and `edge`,
+/// which can be saved again with `SaveOptions::with_extras()`
+pub fn load_to_halfedge_with_extras<Path, VData, EData, FData>(
+    path: Path,
+) -> Result<(HalfEdgeMesh<VData, EData, FData>, PlyExtras), LoadError>
+where
+    Path: AsRef<std::path::Path>,
+    VData: Default + FromPropertyMap,
+    EData: Default + FromPropertyMap,
+    FData: Default + FromPropertyMap,
+{
+    let file = std::fs::File::open(path.as_ref())?;
+    load_to_halfedge_from_reader_with_extras(std::io::BufReader::new(file))
+}
+
+pub fn load_to_halfedge_from_reader_with_extras<Reader, VData, EData, FData>(
+    reader: Reader,
+) -> Result<(HalfEdgeMesh<VData, EData, FData>, PlyExtras), LoadError>
+where
+    Reader: BufRead,
+    VData: Default + FromPropertyMap,
+    EData: Default + FromPropertyMap,
+    FData: Default + FromPropertyMap,
+{
+    let options = LoadOptions {
+        keep_extras: true,
+        ..Default::default()
+    };
+    let (mesh, report) = load_to_halfedge_from_reader_with_options(reader, &options)?;
+    Ok((mesh, report.extras))
+}
+
+pub fn load_to_halfedge_with_options<Path, VData, EData, FData>(
+    path: Path,
+    options: &LoadOptions,
+) -> Result<(HalfEdgeMesh<VData, EData, FData>, LoadReport), LoadError>
+where
+    Path: AsRef<std::path::Path>,
+    VData: Default + FromPropertyMap,
+    EData: Default + FromPropertyMap,
+    FData: Default + FromPropertyMap,
+{
+    let file = std::fs::File::open(path.as_ref())?;
+    load_to_halfedge_from_reader_with_options(std::io::BufReader::new(file), options)
+}
+
+/// faces and edges are checked against the number of vertices,
+/// skipped faces are not part of the mesh, so the mesh must still be manifold without them
+pub fn load_to_halfedge_from_reader_with_options<Reader, VData, EData, FData>(
+    reader: Reader,
+    options: &LoadOptions,
+) -> Result<(HalfEdgeMesh<VData, EData, FData>, LoadReport), LoadError>
+where
+    Reader: BufRead,
+    VData: Default + FromPropertyMap,
+    EData: Default + FromPropertyMap,
+    FData: Default + FromPropertyMap,
+{
+    let reader = PlyReader::new(reader)?;
+    let vertex_element = reader.element_index("vertex");
+    let face_element = reader.element_index("face");
+    let edge_element = reader.element_index("edge");
+    let mut extras = ExtrasCollector::new(&reader, &["vertex", "face", "edge"]);
+    let num_vertices = reader.element_count("vertex");
+    let mut validator = Validator::new(options.validation);
+
+    let mut vertices_data = HashMap::with_capacity(reader.element_count("vertex"));
+    let mut faces = Vec::with_capacity(reader.element_count("face"));
+    let mut faces_data = HashMap::with_capacity(reader.element_count("face"));
+    let mut edges_data = HashMap::with_capacity(reader.element_count("edge"));
+    let mut num_faces = 0;
+    let mut num_edges = 0;
+    for record in reader {
+        let mut record = record?;
+        if Some(record.element) == vertex_element {
+            let data = data_from_props::<VData>(record.props, "vertex", vertices_data.len())?;
+            vertices_data.insert(vertices_data.len(), data);
+        } else if Some(record.element) == face_element {
+            let vertex_index = super::take_vertex_index(
+                &mut record.props,
+                num_faces,
+                num_vertices,
+                &mut validator,
+            )?;
+            num_faces += 1;
+            if let Some(vertex_index) = vertex_index {
+                let data = data_from_props::<FData>(record.props, "face", num_faces - 1)?;
+                faces_data.insert(faces.len(), data);
                 faces.push(vertex_index);
             }
-        }
-    }
-
-    let vertices_data = vertices_list
-        .into_iter()
-        .enumerate()
-        .map(|(vid, prop)| (vid, VData::from_proprety_map(prop)))
-        .collect::<HashMap<_, _>>();
-
-    let mut edges_data = HashMap::with_capacity(edges_list.len());
-    for mut e in edges_list {
-        if let Some(vertex1) = e.map.remove("vertex1".into()) {
-            if let Some(vertex2) = e.map.remove("vertex2".into()) {
-                let pair = match (vertex1, vertex2) {
-                    (Property::I32(v1), Property::I32(v2)) => Some((v1 as usize, v2 as usize)),
-                    (Property::U32(v1), Property::U32(v2)) => Some((v1 as usize, v2 as usize)),
-                    (Property::I16(v1), Property::I16(v2)) => Some((v1 as usize, v2 as usize)),
-                    (Property::U16(v1), Property::U16(v2)) => Some((v1 as usize, v2 as usize)),
-                    _ => None,
-                };
-                if let Some((v1, v2)) = pair {
-                    let key = (v1.min(v2), v1.max(v2));
-                    edges_data.insert(key, EData::from_proprety_map(e));
-                }
+        } else if Some(record.element) == edge_element {
+            let mut e = record.props;
+            let vertices =
+                super::take_edge_vertices(&mut e, num_edges, num_vertices, &mut validator)?;
+            num_edges += 1;
+            if let Some([v1, v2]) = vertices {
+                let data = data_from_props::<EData>(e, "edge", num_edges - 1)?;
+                edges_data.insert((v1.min(v2), v1.max(v2)), data);
             }
+        } else if options.keep_extras {
+            extras.push(record);
         }
     }
 
-    Ok(HalfEdgeMesh::new(
-        faces,
-        vertices_data,
-        edges_data,
-        faces_data,
-    ))
+    check_manifold(&faces).map_err(LoadError::Topology)?;
+    let mesh = HalfEdgeMesh::new(faces, vertices_data, edges_data, faces_data);
+    let report = LoadReport {
+        extras: extras.extras,
+        issues: validator.issues,
+    };
+    Ok((mesh, report))
 }
 
 pub fn save_halfedge<Path, VData, EData, FData>(
@@ -159,46 +215,59 @@ where
     EData: Default + ToPropertyMap,
     FData: Default + ToPropertyMap,
 {
-    let mut ply = ply::Ply::<ply::DefaultElement>::new();
-    ply.header.encoding = ply::Encoding::Ascii;
+    save_halfedge_with_options(path, mesh, &SaveOptions::default())
+}
+
+pub fn save_halfedge_with_options<Path, VData, EData, FData>(
+    path: Path,
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+    options: &SaveOptions,
+) -> Result<(), SaveError>
+where
+    Path: AsRef<std::path::Path>,
+    VData: Default + ToPropertyMap,
+    EData: Default + ToPropertyMap,
+    FData: Default + ToPropertyMap,
+{
+    save_halfedge_to_writer(super::create_file(path)?, mesh, options)
+}
+
+pub fn save_halfedge_to_writer<Writer, VData, EData, FData>(
+    writer: Writer,
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+    options: &SaveOptions,
+) -> Result<(), SaveError>
+where
+    Writer: Write,
+    VData: Default + ToPropertyMap,
+    EData: Default + ToPropertyMap,
+    FData: Default + ToPropertyMap,
+{
+    let mut elements = vec![];
 
     if mesh.num_vertices() > 0 {
-        let mut vertex_element = ply::ElementDef::new("vertex".to_owned());
-        let vdata = mesh.vertices().next().unwrap().data(mesh).to_proprety_map();
-        for (k, v) in &vdata.map {
-            let prop = ply::PropertyDef::new(k.to_string(), super::get_property_type(v));
-            vertex_element.properties.add(prop);
-        }
-        ply.header.elements.add(vertex_element);
-
-        let mut vertices = Vec::with_capacity(mesh.num_vertices());
-        for vref in mesh.vertices() {
-            let mut vertex = ply::DefaultElement::new();
-            let vdata = mesh.vertex_data(&vref).to_proprety_map();
-            for (k, v) in &vdata.map {
-                vertex.insert(k.to_string(), super::get_ply_property(v));
-            }
-            vertices.push(vertex);
-        }
-        ply.payload.insert("vertex".to_owned(), vertices);
+        let mut vertex_element = ElementData::new("vertex");
+        vertex_element.items = mesh
+            .vertices()
+            .map(|vref| {
+                mesh.vertex_data(&vref)
+                    .to_proprety_map()
+                    .with_precision(options.float_precision)
+            })
+            .collect();
+        vertex_element.add_properties_of_first();
+        elements.push(vertex_element);
     }
 
-    let num_faces = mesh.num_faces();
-    if num_faces > 0 {
-        let mut face_element = ply::ElementDef::new("face".to_owned());
-        let prop = ply::PropertyDef::new(
-            "vertex_index".to_owned(),
-            ply::PropertyType::List(ply::ScalarType::UChar, ply::ScalarType::Int),
+    if mesh.num_faces() > 0 {
+        let mut face_element = ElementData::new("face");
+        face_element.add_property(
+            "vertex_index",
+            ply::PropertyType::List(
+                options.list_count_type.scalar_type(),
+                options.index_type.scalar_type(),
+            ),
         );
-        face_element.properties.add(prop);
-        let fdata = mesh.faces().next().unwrap().data(mesh).to_proprety_map();
-        for (k, v) in &fdata.map {
-            let prop = ply::PropertyDef::new(k.to_string(), super::get_property_type(v));
-            face_element.properties.add(prop);
-        }
-        ply.header.elements.add(face_element);
-
-        let mut faces = Vec::with_capacity(num_faces);
         for fref in mesh.faces() {
             if fref.is_boundary(mesh) {
                 continue;
@@ -207,52 +276,32 @@ where
             let mut he = fref.halfedge(mesh);
             let mut vertex_index = vec![];
             loop {
-                vertex_index.push(he.vertex(mesh).id as i32);
+                vertex_index.push(he.vertex(mesh).id);
                 he = he.next(mesh);
                 if he == fref.halfedge(mesh) {
                     break;
                 }
             }
 
-            let mut face = ply::DefaultElement::new();
-            face.insert(
-                "vertex_index".to_owned(),
-                ply::Property::ListInt(vertex_index),
+            let mut face = mesh
+                .face_data(&fref)
+                .to_proprety_map()
+                .with_precision(options.float_precision);
+            face.map.insert(
+                "vertex_index".into(),
+                options.index_type.index_list(&vertex_index)?,
             );
-            let fdata = mesh.face_data(&fref).to_proprety_map();
-            for (k, v) in &fdata.map {
-                face.insert(k.to_string(), super::get_ply_property(v));
-            }
-            faces.push(face);
+            face_element.items.push(face);
         }
-        ply.payload.insert("face".to_owned(), faces);
+        face_element.add_properties_of_first();
+        elements.push(face_element);
     }
 
     if mesh.num_edges() > 0 {
-        let mut edge_element = ply::ElementDef::new("edge".to_owned());
-        let prop = ply::PropertyDef::new(
-            "vertex1".to_owned(),
-            ply::PropertyType::Scalar(ply::ScalarType::Int),
-        );
-        edge_element.properties.add(prop);
-        let prop = ply::PropertyDef::new(
-            "vertex2".to_owned(),
-            ply::PropertyType::Scalar(ply::ScalarType::Int),
-        );
-        edge_element.properties.add(prop);
-        let edata = mesh
-            .halfedges()
-            .next()
-            .unwrap()
-            .data(mesh)
-            .to_proprety_map();
-        for (k, v) in &edata.map {
-            let prop = ply::PropertyDef::new(k.to_string(), super::get_property_type(v));
-            edge_element.properties.add(prop);
-        }
-        ply.header.elements.add(edge_element);
-
-        let mut edges = Vec::with_capacity(mesh.num_edges());
+        let mut edge_element = ElementData::new("edge");
+        let index_type = ply::PropertyType::Scalar(options.index_type.scalar_type());
+        edge_element.add_property("vertex1", index_type.clone());
+        edge_element.add_property("vertex2", index_type);
         for heref in mesh.halfedges() {
             let twin = heref.twin(mesh);
             let v1 = mesh.halfedges[heref.id].vertex;
@@ -261,27 +310,96 @@ where
                 continue;
             }
 
-            let mut edge = ply::DefaultElement::new();
-            edge.insert("vertex1".to_owned(), ply::Property::Int(v1 as i32));
-            edge.insert("vertex2".to_owned(), ply::Property::Int(v2 as i32));
-            let edata = mesh.edge_data(&heref).to_proprety_map();
-            for (k, v) in &edata.map {
-                edge.insert(k.to_string(), super::get_ply_property(v));
-            }
-            edges.push(edge);
+            let mut edge = mesh
+                .edge_data(&heref)
+                .to_proprety_map()
+                .with_precision(options.float_precision);
+            edge.map
+                .insert("vertex1".into(), options.index_type.index(v1)?);
+            edge.map
+                .insert("vertex2".into(), options.index_type.index(v2)?);
+            edge_element.items.push(edge);
         }
-        ply.payload.insert("edge".to_owned(), edges);
+        edge_element.add_properties_of_first();
+        elements.push(edge_element);
     }
 
-    ply.make_consistent()
-        .map_err(|err| SaveError::new(err.to_string()))?;
+    super::write_ply(writer, elements, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::ply::Encoding;
 
-    let mut file =
-        std::fs::File::create(path.as_ref()).map_err(|err| SaveError::new(err.to_string()))?;
-    let writer = writer::Writer::new();
-    writer
-        .write_ply(&mut file, &mut ply)
-        .map_err(|err| SaveError::new(err.to_string()))?;
+    type PropertyMesh = HalfEdgeMesh<PropertyMap, PropertyMap, PropertyMap>;
 
-    Ok(())
+    #[test]
+    fn extras_round_trip() {
+        let ply = "ply\nformat ascii 1.0\ncomment made by hand\nobj_info scanner 3\n\
+                   element vertex 3\nproperty float z\nproperty uchar red\nproperty float x\n\
+                   property float y\nelement face 1\nproperty list uchar int vertex_index\n\
+                   element material 2\nproperty uchar id\nproperty double shininess\n\
+                   comment after elements\nend_header\n\
+                   0 255 0 0\n0 0 1 0\n0 128 0 1\n3 0 1 2\n0 0.5\n1 2.25\n";
+        let (mesh, extras) =
+            load_to_halfedge_from_reader_with_extras::<_, PropertyMap, PropertyMap, PropertyMap>(
+                ply.as_bytes(),
+            )
+            .unwrap();
+        assert_eq!(extras.comments, ["made by hand", "after elements"]);
+        assert_eq!(extras.obj_info, ["scanner 3"]);
+        assert_eq!(extras.elements.len(), 1);
+        assert_eq!(extras.elements[0].name, "material");
+        assert_eq!(
+            extras.elements[0].records[1].get_f64("shininess").unwrap(),
+            2.25
+        );
+
+        let property_order = |mesh: &PropertyMesh| {
+            mesh.vertices()
+                .map(|vref| {
+                    mesh.vertex_data(&vref)
+                        .map
+                        .keys()
+                        .map(|key| key.to_string())
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(property_order(&mesh)[0], ["z", "red", "x", "y"]);
+
+        for encoding in [
+            Encoding::Ascii,
+            Encoding::BinaryLittleEndian,
+            Encoding::BinaryBigEndian,
+        ] {
+            let options = SaveOptions {
+                encoding,
+                ..Default::default()
+            }
+            .with_extras(extras.clone());
+            let mut bytes = vec![];
+            save_halfedge_to_writer(&mut bytes, &mesh, &options).unwrap();
+            let (loaded, loaded_extras) = load_to_halfedge_from_reader_with_extras::<
+                _,
+                PropertyMap,
+                PropertyMap,
+                PropertyMap,
+            >(bytes.as_slice())
+            .unwrap();
+            assert_eq!(loaded_extras, extras);
+            assert_eq!(property_order(&loaded), property_order(&mesh));
+            // vertices are renumbered in the order faces use them
+            let data = |mesh: &PropertyMesh| {
+                let mut data = mesh
+                    .vertices()
+                    .map(|vref| format!("{:?}", mesh.vertex_data(&vref)))
+                    .collect::<Vec<_>>();
+                data.sort();
+                data
+            };
+            assert_eq!(data(&loaded), data(&mesh));
+        }
+    }
 }
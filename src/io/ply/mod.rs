@@ -1,12 +1,25 @@
 mod halfedge;
+mod reader;
 mod trimesh;
+mod value;
 
 pub use halfedge::*;
+pub use reader::*;
 pub use trimesh::*;
+pub use value::*;
 
-use std::{borrow::Cow, collections::HashMap};
+#[cfg(feature = "derive")]
+pub use pep_mesh_derive::{FromPropertyMap, ToPropertyMap};
 
-#[derive(Debug)]
+use std::{borrow::Cow, io::Write};
+
+use linked_hash_map::LinkedHashMap;
+
+use super::{
+    create_file, validation::Validator, LoadError, SaveError, ValidationIssue, ValidationPolicy,
+};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Property {
     I8(i8),
     U8(u8),
@@ -26,15 +39,17 @@ pub enum Property {
     F64List(Vec<f64>),
 }
 
-#[derive(Default)]
+/// properties of a record, kept in the order they are inserted,
+/// which is the order of the header for loaded records
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct PropertyMap {
-    pub map: HashMap<Cow<'static, str>, Property>,
+    pub map: LinkedHashMap<Cow<'static, str>, Property>,
 }
 
 impl ply_rs::ply::PropertyAccess for PropertyMap {
     fn new() -> Self {
         Self {
-            map: HashMap::new(),
+            map: LinkedHashMap::new(),
         }
     }
 
@@ -61,6 +76,146 @@ impl ply_rs::ply::PropertyAccess for PropertyMap {
     }
 }
 
+/// remove `vertex_index` (or `vertex_indices`) list from properties of face `record`,
+/// return `None` if the face is skipped by `validator`
+fn take_vertex_index(
+    props: &mut PropertyMap,
+    record: usize,
+    num_vertices: usize,
+    validator: &mut Validator,
+) -> Result<Option<Vec<usize>>, LoadError> {
+    let name =
+        if !props.map.contains_key("vertex_index") && props.map.contains_key("vertex_indices") {
+            "vertex_indices"
+        } else {
+            "vertex_index"
+        };
+    let vertex_index = match props.get_list::<u32>(name) {
+        Ok(vertex_index) => vertex_index,
+        Err(err) => {
+            validator.report(index_issue("face", record, props, err))?;
+            return Ok(None);
+        }
+    };
+    props.map.remove(name);
+
+    let vertex_index = vertex_index
+        .into_iter()
+        .map(|i| i as usize)
+        .collect::<Vec<_>>();
+    if vertex_index.len() < 3 {
+        validator.report(ValidationIssue::InvalidRecord {
+            element: "face".to_owned(),
+            record,
+            reason: format!("face has {} vertices", vertex_index.len()),
+        })?;
+        return Ok(None);
+    }
+    let valid = validator.check_indices("face", record, &vertex_index, num_vertices)?;
+    Ok(if valid { Some(vertex_index) } else { None })
+}
+
+/// remove `vertex1` and `vertex2` from properties of edge `record`,
+/// return `None` if the edge is skipped by `validator`
+fn take_edge_vertices(
+    props: &mut PropertyMap,
+    record: usize,
+    num_vertices: usize,
+    validator: &mut Validator,
+) -> Result<Option<[usize; 2]>, LoadError> {
+    let vertices = match props.get_array::<u32, 2>(["vertex1", "vertex2"]) {
+        Ok(vertices) => vertices.map(|v| v as usize),
+        Err(err) => {
+            validator.report(index_issue("edge", record, props, err))?;
+            return Ok(None);
+        }
+    };
+    props.map.remove("vertex1");
+    props.map.remove("vertex2");
+
+    let valid = validator.check_indices("edge", record, &vertices, num_vertices)?;
+    Ok(if valid { Some(vertices) } else { None })
+}
+
+/// issue of an index property which can't be read as `u32`, negative values are told apart
+fn index_issue(
+    element: &str,
+    record: usize,
+    props: &PropertyMap,
+    err: PropertyError,
+) -> ValidationIssue {
+    let reason = err.to_string();
+    match err {
+        PropertyError::Missing(name) => ValidationIssue::MissingProperty {
+            element: element.to_owned(),
+            record,
+            name,
+        },
+        PropertyError::Mismatch { name, .. } => {
+            let negative = props
+                .get_list::<i32>(&name)
+                .or_else(|_| props.get::<i32>(&name).map(|index| vec![index]))
+                .ok()
+                .and_then(|indices| indices.into_iter().find(|index| *index < 0));
+            match negative {
+                Some(index) => ValidationIssue::NegativeIndex {
+                    element: element.to_owned(),
+                    record,
+                    index: index as i64,
+                },
+                None => ValidationIssue::InvalidRecord {
+                    element: element.to_owned(),
+                    record,
+                    reason,
+                },
+            }
+        }
+    }
+}
+
+/// collect records of elements that are not used by the mesh
+struct ExtrasCollector {
+    extras: PlyExtras,
+    /// index in `extras.elements` of each element of the file
+    element_map: Vec<Option<usize>>,
+}
+
+impl ExtrasCollector {
+    /// elements named in `used` are not collected
+    fn new<R: std::io::BufRead>(reader: &PlyReader<R>, used: &[&str]) -> Self {
+        let mut extras = PlyExtras {
+            comments: reader.comments().to_vec(),
+            obj_info: reader.obj_info().to_vec(),
+            elements: vec![],
+        };
+        let element_map = reader
+            .element_defs()
+            .iter()
+            .map(|def| {
+                if used.contains(&def.name.as_str()) {
+                    return None;
+                }
+                extras.elements.push(PlyElement {
+                    name: def.name.clone(),
+                    records: Vec::with_capacity(def.count),
+                    def: Some(def.clone()),
+                });
+                Some(extras.elements.len() - 1)
+            })
+            .collect();
+        Self {
+            extras,
+            element_map,
+        }
+    }
+
+    fn push(&mut self, record: PlyRecord) {
+        if let Some(Some(index)) = self.element_map.get(record.element) {
+            self.extras.elements[*index].records.push(record.props);
+        }
+    }
+}
+
 fn get_property_type(prop: &Property) -> ply_rs::ply::PropertyType {
     match prop {
         Property::I8(_) => ply_rs::ply::PropertyType::Scalar(ply_rs::ply::ScalarType::Char),
@@ -106,23 +261,414 @@ fn get_property_type(prop: &Property) -> ply_rs::ply::PropertyType {
     }
 }
 
-fn get_ply_property(prop: &Property) -> ply_rs::ply::Property {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatPrecision {
+    /// use types given by property maps
+    Keep,
+    /// convert `f64` properties to `f32`
+    Single,
+    /// convert `f32` properties to `f64`
+    Double,
+}
+
+/// integer type of indices and list lengths
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexType {
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+}
+
+/// parts of a ply file that are not converted to a mesh
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlyExtras {
+    /// `comment` lines of the header
+    pub comments: Vec<String>,
+    /// `obj_info` lines of the header
+    pub obj_info: Vec<String>,
+    /// elements that are not used by the mesh, in the order they are stored
+    pub elements: Vec<PlyElement>,
+}
+
+/// an element of a ply file and all its records
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyElement {
+    pub name: String,
+    pub records: Vec<PropertyMap>,
+    /// definition from the header of a loaded file, so that property types are kept on save
+    def: Option<ply_rs::ply::ElementDef>,
+}
+
+impl PlyElement {
+    /// property types are given by the first record when saving
+    pub fn new(name: String, records: Vec<PropertyMap>) -> Self {
+        Self {
+            name,
+            records,
+            def: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadOptions {
+    pub validation: ValidationPolicy,
+    /// keep comments, obj_info and elements that are not used by the mesh in `LoadReport::extras`
+    pub keep_extras: bool,
+}
+
+/// what is found besides the mesh when loading with `LoadOptions`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadReport {
+    /// empty if `LoadOptions::keep_extras` is not set
+    pub extras: PlyExtras,
+    /// problems that are fixed by `ValidationPolicy::Lenient`
+    pub issues: Vec<ValidationIssue>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveOptions {
+    pub encoding: Encoding,
+    pub float_precision: FloatPrecision,
+    /// type of length of `vertex_index` lists
+    pub list_count_type: IndexType,
+    /// type of elements of `vertex_index` lists, also used by `vertex1` and `vertex2` of edges
+    pub index_type: IndexType,
+    /// `comment` lines of the header
+    pub comments: Vec<String>,
+    /// `obj_info` lines of the header
+    pub obj_info: Vec<String>,
+    /// elements written after those of the mesh, `float_precision` is not applied to them
+    pub elements: Vec<PlyElement>,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        Self {
+            encoding: Encoding::Ascii,
+            float_precision: FloatPrecision::Keep,
+            list_count_type: IndexType::U8,
+            index_type: IndexType::I32,
+            comments: vec![],
+            obj_info: vec![],
+            elements: vec![],
+        }
+    }
+}
+
+impl SaveOptions {
+    /// set comments, obj_info and elements to those of a loaded file
+    pub fn with_extras(self, extras: PlyExtras) -> Self {
+        Self {
+            comments: extras.comments,
+            obj_info: extras.obj_info,
+            elements: extras.elements,
+            ..self
+        }
+    }
+}
+
+impl IndexType {
+    fn scalar_type(self) -> ply_rs::ply::ScalarType {
+        match self {
+            IndexType::U8 => ply_rs::ply::ScalarType::UChar,
+            IndexType::I16 => ply_rs::ply::ScalarType::Short,
+            IndexType::U16 => ply_rs::ply::ScalarType::UShort,
+            IndexType::I32 => ply_rs::ply::ScalarType::Int,
+            IndexType::U32 => ply_rs::ply::ScalarType::UInt,
+        }
+    }
+
+    fn max_value(self) -> usize {
+        match self {
+            IndexType::U8 => u8::MAX as usize,
+            IndexType::I16 => i16::MAX as usize,
+            IndexType::U16 => u16::MAX as usize,
+            IndexType::I32 => i32::MAX as usize,
+            IndexType::U32 => u32::MAX as usize,
+        }
+    }
+
+    fn index(self, index: usize) -> Result<Property, SaveError> {
+        if index > self.max_value() {
+            return Err(SaveError::IndexOutOfRange {
+                index,
+                max: self.max_value(),
+            });
+        }
+        Ok(match self {
+            IndexType::U8 => Property::U8(index as u8),
+            IndexType::I16 => Property::I16(index as i16),
+            IndexType::U16 => Property::U16(index as u16),
+            IndexType::I32 => Property::I32(index as i32),
+            IndexType::U32 => Property::U32(index as u32),
+        })
+    }
+
+    fn index_list(self, indices: &[usize]) -> Result<Property, SaveError> {
+        if let Some(index) = indices.iter().find(|i| **i > self.max_value()) {
+            return Err(SaveError::IndexOutOfRange {
+                index: *index,
+                max: self.max_value(),
+            });
+        }
+        Ok(match self {
+            IndexType::U8 => Property::U8List(indices.iter().map(|i| *i as u8).collect()),
+            IndexType::I16 => Property::I16List(indices.iter().map(|i| *i as i16).collect()),
+            IndexType::U16 => Property::U16List(indices.iter().map(|i| *i as u16).collect()),
+            IndexType::I32 => Property::I32List(indices.iter().map(|i| *i as i32).collect()),
+            IndexType::U32 => Property::U32List(indices.iter().map(|i| *i as u32).collect()),
+        })
+    }
+}
+
+impl PropertyMap {
+    fn with_precision(self, precision: FloatPrecision) -> Self {
+        Self {
+            map: self
+                .map
+                .into_iter()
+                .map(|(k, v)| (k, v.with_precision(precision)))
+                .collect(),
+        }
+    }
+}
+
+impl Property {
+    fn with_precision(self, precision: FloatPrecision) -> Self {
+        match (precision, self) {
+            (FloatPrecision::Single, Property::F64(val)) => Property::F32(val as f32),
+            (FloatPrecision::Single, Property::F64List(val)) => {
+                Property::F32List(val.into_iter().map(|v| v as f32).collect())
+            }
+            (FloatPrecision::Double, Property::F32(val)) => Property::F64(val as f64),
+            (FloatPrecision::Double, Property::F32List(val)) => {
+                Property::F64List(val.into_iter().map(|v| v as f64).collect())
+            }
+            (_, prop) => prop,
+        }
+    }
+}
+
+/// an element of the header and its payload
+struct ElementData {
+    def: ply_rs::ply::ElementDef,
+    items: Vec<PropertyMap>,
+}
+
+impl ElementData {
+    fn new(name: &str) -> Self {
+        Self {
+            def: ply_rs::ply::ElementDef::new(name.to_owned()),
+            items: vec![],
+        }
+    }
+
+    fn add_property(&mut self, name: &str, data_type: ply_rs::ply::PropertyType) {
+        use ply_rs::ply::Addable;
+        self.def
+            .properties
+            .add(ply_rs::ply::PropertyDef::new(name.to_owned(), data_type));
+    }
+
+    /// add properties of the first item that are not defined yet
+    fn add_properties_of_first(&mut self) {
+        use ply_rs::ply::Addable;
+        if let Some(first) = self.items.first() {
+            for (k, v) in &first.map {
+                if self.def.properties.contains_key(k.as_ref()) {
+                    continue;
+                }
+                self.def.properties.add(ply_rs::ply::PropertyDef::new(
+                    k.to_string(),
+                    get_property_type(v),
+                ));
+            }
+        }
+    }
+}
+
+impl From<&PlyElement> for ElementData {
+    fn from(element: &PlyElement) -> Self {
+        let mut data = match &element.def {
+            Some(def) => Self {
+                def: def.clone(),
+                items: vec![],
+            },
+            None => Self::new(&element.name),
+        };
+        data.items = element.records.clone();
+        data.add_properties_of_first();
+        data
+    }
+}
+
+/// header is written by `ply-rs`, but payload is written here since binary writer of `ply-rs`
+/// doesn't write correct lengths of lists
+fn write_ply<W: Write>(
+    mut out: W,
+    mut elements: Vec<ElementData>,
+    options: &SaveOptions,
+) -> Result<(), SaveError> {
+    use ply_rs::ply::Addable;
+
+    let mut header = ply_rs::ply::Header::new();
+    header.encoding = match options.encoding {
+        Encoding::Ascii => ply_rs::ply::Encoding::Ascii,
+        Encoding::BinaryLittleEndian => ply_rs::ply::Encoding::BinaryLittleEndian,
+        Encoding::BinaryBigEndian => ply_rs::ply::Encoding::BinaryBigEndian,
+    };
+    for line in options.comments.iter().chain(&options.obj_info) {
+        if line.contains(['\n', '\r']) {
+            return Err(SaveError::Invalid(format!(
+                "header line '{}' contains line breaks",
+                line
+            )));
+        }
+    }
+    header.comments = options.comments.clone();
+    header.obj_infos = options.obj_info.clone();
+    elements.extend(options.elements.iter().map(ElementData::from));
+    for element in &mut elements {
+        if header.elements.contains_key(&element.def.name) {
+            return Err(SaveError::Invalid(format!(
+                "element '{}' is defined more than once",
+                element.def.name
+            )));
+        }
+        element.def.count = element.items.len();
+        header.elements.add(element.def.clone());
+    }
+
+    let writer = ply_rs::writer::Writer::<ply_rs::ply::DefaultElement>::new();
+    writer.write_header(&mut out, &header)?;
+
+    for element in &elements {
+        for item in &element.items {
+            for (i, (name, def)) in element.def.properties.iter().enumerate() {
+                let prop =
+                    item.map
+                        .get(name.as_str())
+                        .ok_or_else(|| SaveError::MissingProperty {
+                            element: element.def.name.clone(),
+                            name: name.clone(),
+                        })?;
+                if options.encoding == Encoding::Ascii && i > 0 {
+                    out.write_all(b" ")?;
+                }
+                write_property(&mut out, prop, &def.data_type, options.encoding).map_err(
+                    |err| match err.kind() {
+                        std::io::ErrorKind::InvalidInput => SaveError::Invalid(format!(
+                            "failed to write property '{}' of element '{}', {}",
+                            name, element.def.name, err
+                        )),
+                        _ => SaveError::Io(err),
+                    },
+                )?;
+            }
+            if options.encoding == Encoding::Ascii {
+                out.write_all(b"\n")?;
+            }
+        }
+    }
+
+    out.flush().map_err(SaveError::Io)
+}
+
+fn write_property<W: Write>(
+    out: &mut W,
+    prop: &Property,
+    data_type: &ply_rs::ply::PropertyType,
+    encoding: Encoding,
+) -> std::io::Result<()> {
+    use ply_rs::ply::{PropertyType, ScalarType};
+
+    macro_rules! write_value {
+        ($val:expr) => {
+            match encoding {
+                Encoding::Ascii => write!(out, "{}", $val),
+                Encoding::BinaryLittleEndian => out.write_all(&$val.to_le_bytes()),
+                Encoding::BinaryBigEndian => out.write_all(&$val.to_be_bytes()),
+            }
+        };
+    }
+    macro_rules! write_list {
+        ($count_type:expr, $list:expr) => {{
+            let len = $list.len();
+            let max_len = match $count_type {
+                ScalarType::Char => i8::MAX as usize,
+                ScalarType::UChar => u8::MAX as usize,
+                ScalarType::Short => i16::MAX as usize,
+                ScalarType::UShort => u16::MAX as usize,
+                ScalarType::Int => i32::MAX as usize,
+                _ => u32::MAX as usize,
+            };
+            if len > max_len {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("list length {} can't be stored as {:?}", len, $count_type),
+                ));
+            }
+            match $count_type {
+                ScalarType::Char => write_value!(len as i8)?,
+                ScalarType::UChar => write_value!(len as u8)?,
+                ScalarType::Short => write_value!(len as i16)?,
+                ScalarType::UShort => write_value!(len as u16)?,
+                ScalarType::Int => write_value!(len as i32)?,
+                _ => write_value!(len as u32)?,
+            }
+            for val in $list {
+                if encoding == Encoding::Ascii {
+                    out.write_all(b" ")?;
+                }
+                write_value!(val)?;
+            }
+            Ok(())
+        }};
+    }
+
+    let expected = get_property_type(prop);
+    let matched = match (data_type, &expected) {
+        (PropertyType::Scalar(ty), PropertyType::Scalar(expected)) => ty == expected,
+        (PropertyType::List(_, ty), PropertyType::List(_, expected)) => ty == expected,
+        _ => false,
+    };
+    if !matched {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("expected type {:?}, found {:?}", data_type, expected),
+        ));
+    }
+
+    let count_type = match data_type {
+        PropertyType::List(count_type, _) => count_type.clone(),
+        PropertyType::Scalar(_) => ScalarType::UChar,
+    };
     match prop {
-        Property::I8(val) => ply_rs::ply::Property::Char(*val),
-        Property::U8(val) => ply_rs::ply::Property::UChar(*val),
-        Property::I16(val) => ply_rs::ply::Property::Short(*val),
-        Property::U16(val) => ply_rs::ply::Property::UShort(*val),
-        Property::I32(val) => ply_rs::ply::Property::Int(*val),
-        Property::U32(val) => ply_rs::ply::Property::UInt(*val),
-        Property::F32(val) => ply_rs::ply::Property::Float(*val),
-        Property::F64(val) => ply_rs::ply::Property::Double(*val),
-        Property::I8List(val) => ply_rs::ply::Property::ListChar(val.clone()),
-        Property::U8List(val) => ply_rs::ply::Property::ListUChar(val.clone()),
-        Property::I16List(val) => ply_rs::ply::Property::ListShort(val.clone()),
-        Property::U16List(val) => ply_rs::ply::Property::ListUShort(val.clone()),
-        Property::I32List(val) => ply_rs::ply::Property::ListInt(val.clone()),
-        Property::U32List(val) => ply_rs::ply::Property::ListUInt(val.clone()),
-        Property::F32List(val) => ply_rs::ply::Property::ListFloat(val.clone()),
-        Property::F64List(val) => ply_rs::ply::Property::ListDouble(val.clone()),
+        Property::I8(val) => write_value!(val),
+        Property::U8(val) => write_value!(val),
+        Property::I16(val) => write_value!(val),
+        Property::U16(val) => write_value!(val),
+        Property::I32(val) => write_value!(val),
+        Property::U32(val) => write_value!(val),
+        Property::F32(val) => write_value!(val),
+        Property::F64(val) => write_value!(val),
+        Property::I8List(val) => write_list!(count_type, val),
+        Property::U8List(val) => write_list!(count_type, val),
+        Property::I16List(val) => write_list!(count_type, val),
+        Property::U16List(val) => write_list!(count_type, val),
+        Property::I32List(val) => write_list!(count_type, val),
+        Property::U32List(val) => write_list!(count_type, val),
+        Property::F32List(val) => write_list!(count_type, val),
+        Property::F64List(val) => write_list!(count_type, val),
     }
 }
@@ -0,0 +1,288 @@
+use std::io::{BufRead, Read};
+
+use ply_rs::{parser, ply};
+
+use crate::io::{ply::PropertyMap, LoadError};
+
+/// a record of an element, read by `PlyReader`
+pub struct PlyRecord {
+    /// index of the element in `PlyReader::elements()`
+    pub element: usize,
+    pub props: PropertyMap,
+}
+
+/// read records of a ply file one at a time in the order they are stored,
+/// so that the whole payload doesn't need to be kept in memory
+pub struct PlyReader<R: BufRead> {
+    reader: CountingReader<R>,
+    parser: parser::Parser<PropertyMap>,
+    encoding: ply::Encoding,
+    elements: Vec<ply::ElementDef>,
+    comments: Vec<String>,
+    obj_info: Vec<String>,
+    curr_element: usize,
+    curr_record: usize,
+    line: String,
+}
+
+impl<R: BufRead> PlyReader<R> {
+    /// read header from `reader`
+    pub fn new(reader: R) -> Result<Self, LoadError> {
+        let mut reader = CountingReader {
+            inner: reader,
+            offset: 0,
+            lines: 0,
+        };
+        let parser = parser::Parser::<PropertyMap>::new();
+        let header = parser.read_header(&mut reader).map_err(|err| {
+            if err.kind() == std::io::ErrorKind::InvalidInput {
+                LoadError::Parse {
+                    element: None,
+                    // the invalid line has been read
+                    line: Some(reader.lines.max(1)),
+                    offset: None,
+                    message: format!("invalid header, {}", err),
+                }
+            } else {
+                LoadError::Io(err)
+            }
+        })?;
+
+        Ok(Self {
+            reader,
+            parser,
+            encoding: header.encoding,
+            elements: header.elements.into_iter().map(|(_, def)| def).collect(),
+            comments: header.comments,
+            obj_info: header.obj_infos,
+            curr_element: 0,
+            curr_record: 0,
+            line: String::new(),
+        })
+    }
+
+    /// name and number of records of each element, in the order they are stored
+    pub fn elements(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.elements
+            .iter()
+            .map(|def| (def.name.as_str(), def.count))
+    }
+
+    /// index of element `name` in `elements()`
+    pub fn element_index(&self, name: &str) -> Option<usize> {
+        self.elements.iter().position(|def| def.name == name)
+    }
+
+    /// number of records of element `name`, `0` if there is no such element
+    pub fn element_count(&self, name: &str) -> usize {
+        self.element_index(name)
+            .map_or(0, |index| self.elements[index].count)
+    }
+
+    pub(crate) fn element_defs(&self) -> &[ply::ElementDef] {
+        &self.elements
+    }
+
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    pub fn obj_info(&self) -> &[String] {
+        &self.obj_info
+    }
+
+    /// read the next record, return `None` when all elements have been read
+    pub fn next_record(&mut self) -> Result<Option<PlyRecord>, LoadError> {
+        while self.curr_element < self.elements.len()
+            && self.curr_record >= self.elements[self.curr_element].count
+        {
+            self.curr_element += 1;
+            self.curr_record = 0;
+        }
+        if self.curr_element >= self.elements.len() {
+            return Ok(None);
+        }
+
+        let def = &self.elements[self.curr_element];
+        let offset = self.reader.offset;
+        let line = self.reader.lines + 1;
+        let props = match self.encoding {
+            ply::Encoding::Ascii => {
+                self.line.clear();
+                self.reader.read_line(&mut self.line)?;
+                self.parser.read_ascii_element(&self.line, def)
+            }
+            ply::Encoding::BinaryLittleEndian => self
+                .parser
+                .read_little_endian_element(&mut self.reader, def),
+            ply::Encoding::BinaryBigEndian => {
+                self.parser.read_big_endian_element(&mut self.reader, def)
+            }
+        }
+        .map_err(|err| LoadError::Parse {
+            element: Some(def.name.clone()),
+            line: if self.encoding == ply::Encoding::Ascii {
+                Some(line)
+            } else {
+                None
+            },
+            offset: Some(offset),
+            message: format!("failed to read record {}, {}", self.curr_record, err),
+        })?;
+
+        let record = PlyRecord {
+            element: self.curr_element,
+            props,
+        };
+        self.curr_record += 1;
+        Ok(Some(record))
+    }
+}
+
+impl<R: BufRead> Iterator for PlyReader<R> {
+    type Item = Result<PlyRecord, LoadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// count bytes and lines that have been read, to locate errors
+struct CountingReader<R> {
+    inner: R,
+    offset: u64,
+    lines: usize,
+}
+
+impl<R> CountingReader<R> {
+    fn count(&mut self, bytes: &[u8]) {
+        self.offset += bytes.len() as u64;
+        self.lines += bytes.iter().filter(|b| **b == b'\n').count();
+    }
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.count(&buf[..len]);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // buffer is not changed until it's consumed, so it can be filled again to get the bytes
+        let (len, lines) = match self.inner.fill_buf() {
+            Ok(buf) => {
+                let buf = &buf[..amt.min(buf.len())];
+                (buf.len(), buf.iter().filter(|b| **b == b'\n').count())
+            }
+            Err(_) => (amt, 0),
+        };
+        self.offset += len as u64;
+        self.lines += lines;
+        self.inner.consume(amt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// two vertices and a triangle in `format`, little or big endian bytes for binary ones
+    fn file(format: &str) -> Vec<u8> {
+        let mut bytes = format!(
+            "ply\nformat {} 1.0\ncomment test\nelement vertex 2\nproperty float x\n\
+             property float y\nproperty float z\nelement face 1\n\
+             property list uchar int vertex_indices\nend_header\n",
+            format
+        )
+        .into_bytes();
+        let positions = [[0.5f32, 1.0, 2.0], [-1.0, 0.0, 3.0]];
+        let face = [0i32, 1, 1];
+        match format {
+            "ascii" => {
+                for p in &positions {
+                    bytes.extend(format!("{} {} {}\n", p[0], p[1], p[2]).into_bytes());
+                }
+                bytes.extend(b"3 0 1 1\n");
+            }
+            _ => {
+                let little = format == "binary_little_endian";
+                for x in positions.iter().flatten() {
+                    bytes.extend(if little {
+                        x.to_le_bytes()
+                    } else {
+                        x.to_be_bytes()
+                    });
+                }
+                bytes.push(3);
+                for i in &face {
+                    bytes.extend(if little {
+                        i.to_le_bytes()
+                    } else {
+                        i.to_be_bytes()
+                    });
+                }
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn read_records() {
+        for format in ["ascii", "binary_little_endian", "binary_big_endian"] {
+            let bytes = file(format);
+            let mut reader = PlyReader::new(bytes.as_slice()).unwrap();
+            assert_eq!(
+                reader.elements().collect::<Vec<_>>(),
+                vec![("vertex", 2), ("face", 1)]
+            );
+            assert_eq!(reader.element_count("face"), 1);
+            assert_eq!(reader.comments(), ["test".to_string()]);
+
+            let record = reader.next_record().unwrap().unwrap();
+            assert_eq!(record.element, 0);
+            assert_eq!(
+                record.props.get_vec3(["x", "y", "z"]).unwrap(),
+                [0.5, 1.0, 2.0]
+            );
+            let record = reader.next_record().unwrap().unwrap();
+            assert_eq!(record.element, 0);
+            assert_eq!(
+                record.props.get_vec3(["x", "y", "z"]).unwrap(),
+                [-1.0, 0.0, 3.0]
+            );
+            let record = reader.next_record().unwrap().unwrap();
+            assert_eq!(record.element, 1);
+            assert_eq!(
+                record.props.get_list::<u32>("vertex_indices").unwrap(),
+                vec![0, 1, 1]
+            );
+            assert!(reader.next_record().unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        for format in ["ascii", "binary_little_endian", "binary_big_endian"] {
+            let bytes = file(format);
+            // cut inside the face record
+            let bytes = &bytes[..bytes.len() - 3];
+            let records = PlyReader::new(bytes)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>();
+            assert!(matches!(
+                records,
+                Err(LoadError::Parse {
+                    element: Some(ref element),
+                    ..
+                }) if element == "face"
+            ));
+        }
+    }
+}
@@ -1,17 +1,21 @@
-use std::{borrow::Cow, collections::HashMap};
-
-use ply_rs::{
-    parser,
-    ply::{self, Addable},
-    writer,
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io::{BufRead, Write},
 };
 
+use ply_rs::ply;
+
 use crate::{
     io::{
-        ply::{Property, PropertyMap},
+        ply::{
+            ElementData, ExtrasCollector, LoadOptions, LoadReport, PlyExtras, PlyReader,
+            PropertyMap, SaveOptions,
+        },
+        validation::Validator,
         LoadError, SaveError,
     },
-    trimesh::{MeshIndices, TriMesh, VertexAttribute},
+    trimesh::{MeshIndices, PrimitiveTopology, TriMesh, VertexAttribute},
 };
 
 pub fn load_to_trimesh<Path, VertexAttributesInitializer, VertexPropertiesHandler>(
@@ -24,77 +28,185 @@ where
     VertexAttributesInitializer: Fn(usize) -> HashMap<Cow<'static, str>, VertexAttribute>,
     VertexPropertiesHandler: Fn(&mut HashMap<Cow<'static, str>, VertexAttribute>, PropertyMap),
 {
-    let file = std::fs::File::open(path.as_ref()).map_err(|err| LoadError::new(err.to_string()))?;
-    let mut reader = std::io::BufReader::new(file);
+    let file = std::fs::File::open(path.as_ref())?;
+    load_to_trimesh_from_reader(
+        std::io::BufReader::new(file),
+        vertex_attributes_initializer,
+        vertex_properties_handler,
+    )
+}
 
-    let vertex_parser = parser::Parser::<PropertyMap>::new();
-    let face_parser = parser::Parser::<PropertyMap>::new();
+/// vertices are passed to `vertex_properties_handler` as soon as they are read
+pub fn load_to_trimesh_from_reader<Reader, VertexAttributesInitializer, VertexPropertiesHandler>(
+    reader: Reader,
+    vertex_attributes_initializer: VertexAttributesInitializer,
+    vertex_properties_handler: VertexPropertiesHandler,
+) -> Result<TriMesh, LoadError>
+where
+    Reader: BufRead,
+    VertexAttributesInitializer: Fn(usize) -> HashMap<Cow<'static, str>, VertexAttribute>,
+    VertexPropertiesHandler: Fn(&mut HashMap<Cow<'static, str>, VertexAttribute>, PropertyMap),
+{
+    Ok(load_to_trimesh_from_reader_with_options(
+        reader,
+        vertex_attributes_initializer,
+        vertex_properties_handler,
+        &LoadOptions::default(),
+    )?
+    .0)
+}
 
-    let header = vertex_parser
-        .read_header(&mut reader)
-        .map_err(|err| LoadError::new(err.to_string()))?;
+/// also return comments, obj_info and elements other than `vertex` and `face`,
+/// which can be saved again with `SaveOptions::with_extras()`
+pub fn load_to_trimesh_with_extras<Path, VertexAttributesInitializer, VertexPropertiesHandler>(
+    path: Path,
+    vertex_attributes_initializer: VertexAttributesInitializer,
+    vertex_properties_handler: VertexPropertiesHandler,
+) -> Result<(TriMesh, PlyExtras), LoadError>
+where
+    Path: AsRef<std::path::Path>,
+    VertexAttributesInitializer: Fn(usize) -> HashMap<Cow<'static, str>, VertexAttribute>,
+    VertexPropertiesHandler: Fn(&mut HashMap<Cow<'static, str>, VertexAttribute>, PropertyMap),
+{
+    let file = std::fs::File::open(path.as_ref())?;
+    load_to_trimesh_from_reader_with_extras(
+        std::io::BufReader::new(file),
+        vertex_attributes_initializer,
+        vertex_properties_handler,
+    )
+}
 
-    let mut vertices_list = vec![];
-    let mut faces_list = vec![];
+pub fn load_to_trimesh_from_reader_with_extras<
+    Reader,
+    VertexAttributesInitializer,
+    VertexPropertiesHandler,
+>(
+    reader: Reader,
+    vertex_attributes_initializer: VertexAttributesInitializer,
+    vertex_properties_handler: VertexPropertiesHandler,
+) -> Result<(TriMesh, PlyExtras), LoadError>
+where
+    Reader: BufRead,
+    VertexAttributesInitializer: Fn(usize) -> HashMap<Cow<'static, str>, VertexAttribute>,
+    VertexPropertiesHandler: Fn(&mut HashMap<Cow<'static, str>, VertexAttribute>, PropertyMap),
+{
+    let options = LoadOptions {
+        keep_extras: true,
+        ..Default::default()
+    };
+    let (mesh, report) = load_to_trimesh_from_reader_with_options(
+        reader,
+        vertex_attributes_initializer,
+        vertex_properties_handler,
+        &options,
+    )?;
+    Ok((mesh, report.extras))
+}
 
-    for (_, element) in &header.elements {
-        match element.name.as_ref() {
-            "vertex" => {
-                vertices_list = vertex_parser
-                    .read_payload_for_element(&mut reader, &element, &header)
-                    .map_err(|err| LoadError::new(err.to_string()))?
-            }
-            "face" => {
-                faces_list = face_parser
-                    .read_payload_for_element(&mut reader, &element, &header)
-                    .map_err(|err| LoadError::new(err.to_string()))?
-            }
-            _ => {}
-        }
-    }
+pub fn load_to_trimesh_with_options<Path, VertexAttributesInitializer, VertexPropertiesHandler>(
+    path: Path,
+    vertex_attributes_initializer: VertexAttributesInitializer,
+    vertex_properties_handler: VertexPropertiesHandler,
+    options: &LoadOptions,
+) -> Result<(TriMesh, LoadReport), LoadError>
+where
+    Path: AsRef<std::path::Path>,
+    VertexAttributesInitializer: Fn(usize) -> HashMap<Cow<'static, str>, VertexAttribute>,
+    VertexPropertiesHandler: Fn(&mut HashMap<Cow<'static, str>, VertexAttribute>, PropertyMap),
+{
+    let file = std::fs::File::open(path.as_ref())?;
+    load_to_trimesh_from_reader_with_options(
+        std::io::BufReader::new(file),
+        vertex_attributes_initializer,
+        vertex_properties_handler,
+        options,
+    )
+}
 
-    let mut vertex_attributes = vertex_attributes_initializer(vertices_list.len());
-    for vert in vertices_list {
-        vertex_properties_handler(&mut vertex_attributes, vert);
-    }
+/// faces are checked against the number of vertices,
+/// and every vertex attribute must have one value for each vertex after all vertices are handled
+pub fn load_to_trimesh_from_reader_with_options<
+    Reader,
+    VertexAttributesInitializer,
+    VertexPropertiesHandler,
+>(
+    reader: Reader,
+    vertex_attributes_initializer: VertexAttributesInitializer,
+    vertex_properties_handler: VertexPropertiesHandler,
+    options: &LoadOptions,
+) -> Result<(TriMesh, LoadReport), LoadError>
+where
+    Reader: BufRead,
+    VertexAttributesInitializer: Fn(usize) -> HashMap<Cow<'static, str>, VertexAttribute>,
+    VertexPropertiesHandler: Fn(&mut HashMap<Cow<'static, str>, VertexAttribute>, PropertyMap),
+{
+    let reader = PlyReader::new(reader)?;
+    let vertex_element = reader.element_index("vertex");
+    let face_element = reader.element_index("face");
+    // a file with edges but without faces is loaded as a line list
+    let edge_element = match face_element {
+        Some(_) => None,
+        None => reader.element_index("edge"),
+    };
+    let used_elements: &[&str] = match edge_element {
+        Some(_) => &["vertex", "edge"],
+        None => &["vertex", "face"],
+    };
+    let mut extras = ExtrasCollector::new(&reader, used_elements);
+
+    let mut validator = Validator::new(options.validation);
 
-    let mut indices = Vec::with_capacity(faces_list.len() * 3);
-    for mut f in faces_list {
-        let vertex_index = if f.map.contains_key("vertex_index") {
-            f.map.remove("vertex_index".into())
-        } else if f.map.contains_key("vertex_indices") {
-            f.map.remove("vertex_indices".into())
-        } else {
-            None
-        };
-        let vertex_index = if let Some(vertex_index) = vertex_index {
-            if let Property::I32List(vertex_index) = vertex_index {
-                Some(vertex_index.iter().map(|i| *i as usize).collect::<Vec<_>>())
-            } else if let Property::U32List(vertex_index) = vertex_index {
-                Some(vertex_index.iter().map(|i| *i as usize).collect::<Vec<_>>())
-            } else if let Property::I16List(vertex_index) = vertex_index {
-                Some(vertex_index.iter().map(|i| *i as usize).collect::<Vec<_>>())
-            } else if let Property::U16List(vertex_index) = vertex_index {
-                Some(vertex_index.iter().map(|i| *i as usize).collect::<Vec<_>>())
-            } else {
-                None
+    let num_vertices = reader.element_count("vertex");
+    let mut vertex_attributes = vertex_attributes_initializer(num_vertices);
+    let mut indices = Vec::with_capacity(reader.element_count("face") * 3);
+    let mut num_faces = 0;
+    let mut num_edges = 0;
+    for record in reader {
+        let mut record = record?;
+        if Some(record.element) == vertex_element {
+            vertex_properties_handler(&mut vertex_attributes, record.props);
+        } else if Some(record.element) == face_element {
+            let vertex_index = super::take_vertex_index(
+                &mut record.props,
+                num_faces,
+                num_vertices,
+                &mut validator,
+            )?;
+            num_faces += 1;
+            if let Some(vertex_index) = vertex_index {
+                for i in 1..vertex_index.len() - 1 {
+                    indices.push(vertex_index[0] as u32);
+                    indices.push(vertex_index[i] as u32);
+                    indices.push(vertex_index[i + 1] as u32);
+                }
             }
-        } else {
-            None
-        };
-        if let Some(vertex_index) = vertex_index {
-            for i in 1..vertex_index.len() - 1 {
-                indices.push(vertex_index[0] as u32);
-                indices.push(vertex_index[i] as u32);
-                indices.push(vertex_index[i + 1] as u32);
+        } else if Some(record.element) == edge_element {
+            let vertices = super::take_edge_vertices(
+                &mut record.props,
+                num_edges,
+                num_vertices,
+                &mut validator,
+            )?;
+            num_edges += 1;
+            if let Some([v1, v2]) = vertices {
+                indices.push(v1 as u32);
+                indices.push(v2 as u32);
             }
+        } else if options.keep_extras {
+            extras.push(record);
         }
     }
+    validator.check_attributes(&mut vertex_attributes, num_vertices)?;
 
-    Ok(TriMesh::new(
-        vertex_attributes,
-        Some(MeshIndices::U32(indices)),
-    ))
+    let mut mesh = TriMesh::new(vertex_attributes, Some(MeshIndices::U32(indices)));
+    if edge_element.is_some() {
+        mesh.set_topology(PrimitiveTopology::LineList);
+    }
+    let report = LoadReport {
+        extras: extras.extras,
+        issues: validator.issues,
+    };
+    Ok((mesh, report))
 }
 
 pub fn save_trimesh<Path, VertexAttributesConverter>(
@@ -107,70 +219,167 @@ where
     VertexAttributesConverter:
         Fn(&HashMap<Cow<'static, str>, VertexAttribute>, usize) -> PropertyMap,
 {
-    let mut ply = ply::Ply::<ply::DefaultElement>::new();
-    ply.header.encoding = ply::Encoding::Ascii;
+    save_trimesh_with_options(
+        path,
+        mesh,
+        vertex_attributes_converter,
+        &SaveOptions::default(),
+    )
+}
+
+pub fn save_trimesh_with_options<Path, VertexAttributesConverter>(
+    path: Path,
+    mesh: &TriMesh,
+    vertex_attributes_converter: VertexAttributesConverter,
+    options: &SaveOptions,
+) -> Result<(), SaveError>
+where
+    Path: AsRef<std::path::Path>,
+    VertexAttributesConverter:
+        Fn(&HashMap<Cow<'static, str>, VertexAttribute>, usize) -> PropertyMap,
+{
+    save_trimesh_to_writer(
+        super::create_file(path)?,
+        mesh,
+        vertex_attributes_converter,
+        options,
+    )
+}
+
+pub fn save_trimesh_to_writer<Writer, VertexAttributesConverter>(
+    writer: Writer,
+    mesh: &TriMesh,
+    vertex_attributes_converter: VertexAttributesConverter,
+    options: &SaveOptions,
+) -> Result<(), SaveError>
+where
+    Writer: Write,
+    VertexAttributesConverter:
+        Fn(&HashMap<Cow<'static, str>, VertexAttribute>, usize) -> PropertyMap,
+{
+    let mut elements = vec![];
 
     let num_vertices = mesh.num_vertices();
     if num_vertices > 0 {
-        let mut vertex_element = ply::ElementDef::new("vertex".to_owned());
-        let vertex_data = (0..num_vertices)
-            .map(|index| vertex_attributes_converter(&mesh.vertex_attributes, index))
-            .collect::<Vec<_>>();
-        for (k, v) in &vertex_data[0].map {
-            let prop = ply::PropertyDef::new(k.to_string(), super::get_property_type(v));
-            vertex_element.properties.add(prop);
-        }
-        ply.header.elements.add(vertex_element);
+        let mut vertex_element = ElementData::new("vertex");
+        vertex_element.items = (0..num_vertices)
+            .map(|index| {
+                vertex_attributes_converter(&mesh.vertex_attributes, index)
+                    .with_precision(options.float_precision)
+            })
+            .collect();
+        vertex_element.add_properties_of_first();
+        elements.push(vertex_element);
+    }
 
-        let mut vertices = Vec::with_capacity(mesh.num_vertices());
-        for data in vertex_data {
-            let mut vertex = ply::DefaultElement::new();
-            for (k, v) in data.map {
-                vertex.insert(k.to_string(), super::get_ply_property(&v));
-            }
-            vertices.push(vertex);
+    let triangles = mesh.triangles();
+    if !triangles.is_empty() {
+        let mut face_element = ElementData::new("face");
+        face_element.add_property(
+            "vertex_index",
+            ply::PropertyType::List(
+                options.list_count_type.scalar_type(),
+                options.index_type.scalar_type(),
+            ),
+        );
+        for vertex_index in triangles {
+            let mut face = PropertyMap::default();
+            face.map.insert(
+                "vertex_index".into(),
+                options.index_type.index_list(&vertex_index)?,
+            );
+            face_element.items.push(face);
         }
-        ply.payload.insert("vertex".to_owned(), vertices);
+        elements.push(face_element);
     }
 
-    if let Some(num_indcies) = mesh.num_indices() {
-        if num_indcies > 0 {
-            let mut face_element = ply::ElementDef::new("face".to_owned());
-            let prop = ply::PropertyDef::new(
-                "vertex_index".to_owned(),
-                ply::PropertyType::List(ply::ScalarType::UChar, ply::ScalarType::Int),
-            );
-            face_element.properties.add(prop);
-            ply.header.elements.add(face_element);
-
-            let mut faces = Vec::with_capacity(num_indcies / 3);
-            for i in 0..num_indcies / 3 {
-                let i3 = i * 3;
-                let vertex_index = vec![
-                    mesh.indices().unwrap().get(i3) as i32,
-                    mesh.indices().unwrap().get(i3 + 1) as i32,
-                    mesh.indices().unwrap().get(i3 + 2) as i32,
-                ];
-                let mut face = ply::DefaultElement::new();
-                face.insert(
-                    "vertex_index".to_owned(),
-                    ply::Property::ListInt(vertex_index),
-                );
-                faces.push(face);
-            }
-            ply.payload.insert("face".to_owned(), faces);
+    // lines are saved as edges
+    let lines = mesh.lines();
+    if !lines.is_empty() {
+        let mut edge_element = ElementData::new("edge");
+        for [v1, v2] in lines {
+            let mut edge = PropertyMap::default();
+            edge.map
+                .insert("vertex1".into(), options.index_type.index(v1)?);
+            edge.map
+                .insert("vertex2".into(), options.index_type.index(v2)?);
+            edge_element.items.push(edge);
         }
+        edge_element.add_properties_of_first();
+        elements.push(edge_element);
     }
 
-    ply.make_consistent()
-        .map_err(|err| SaveError::new(err.to_string()))?;
+    super::write_ply(writer, elements, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        io::ply::{Encoding, Property},
+        trimesh::TriMeshBuilder,
+    };
+
+    #[test]
+    fn round_trip() {
+        let positions = vec![
+            [0.0f32, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.1, 0.2, 1.5],
+        ];
+        let mesh = TriMeshBuilder::new()
+            .attribute(TriMesh::POSITION, positions.clone())
+            .indices(vec![0, 2, 1, 0, 1, 3, 1, 2, 3, 2, 0, 3])
+            .build()
+            .unwrap();
 
-    let mut file =
-        std::fs::File::create(path.as_ref()).map_err(|err| SaveError::new(err.to_string()))?;
-    let writer = writer::Writer::new();
-    writer
-        .write_ply(&mut file, &mut ply)
-        .map_err(|err| SaveError::new(err.to_string()))?;
+        for encoding in [
+            Encoding::Ascii,
+            Encoding::BinaryLittleEndian,
+            Encoding::BinaryBigEndian,
+        ] {
+            let mut bytes = vec![];
+            let options = SaveOptions {
+                encoding,
+                ..Default::default()
+            };
+            save_trimesh_to_writer(
+                &mut bytes,
+                &mesh,
+                |attributes, i| {
+                    let p = attributes[TriMesh::POSITION].get_float3(i).unwrap();
+                    let mut props = PropertyMap::default();
+                    for (key, value) in ["x", "y", "z"].iter().zip(p) {
+                        props.map.insert((*key).into(), Property::F32(value));
+                    }
+                    props
+                },
+                &options,
+            )
+            .unwrap();
 
-    Ok(())
+            let loaded = load_to_trimesh_from_reader(
+                bytes.as_slice(),
+                |_| {
+                    let mut attributes = HashMap::new();
+                    attributes.insert(TriMesh::POSITION.into(), VertexAttribute::Float3(vec![]));
+                    attributes
+                },
+                |attributes, props| {
+                    let p = props.get_vec3(["x", "y", "z"]).unwrap();
+                    attributes
+                        .get_mut(TriMesh::POSITION)
+                        .unwrap()
+                        .push_float3(p);
+                },
+            )
+            .unwrap();
+            assert_eq!(
+                loaded.attribute_as::<[f32; 3]>(TriMesh::POSITION).unwrap(),
+                positions.as_slice()
+            );
+            assert_eq!(loaded.triangles(), mesh.triangles());
+        }
+    }
 }
@@ -0,0 +1,393 @@
+use std::convert::TryFrom;
+
+use crate::io::ply::{Property, PropertyMap};
+
+#[derive(Debug)]
+pub enum PropertyError {
+    Missing(String),
+    Mismatch {
+        name: String,
+        expected: &'static str,
+        found: String,
+    },
+}
+
+impl std::fmt::Display for PropertyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropertyError::Missing(name) => write!(f, "property '{}' is missing", name),
+            PropertyError::Mismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "property '{}' is expected to be {} but found {}",
+                name, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PropertyError {}
+
+mod sealed {
+    use crate::io::ply::Property;
+
+    /// a number of a scalar property or an element of a list property
+    #[derive(Clone, Copy)]
+    pub enum Number {
+        Int(i64),
+        Float(f64),
+    }
+
+    pub trait Sealed: Sized {
+        fn from_number(number: Number) -> Option<Self>;
+
+        fn to_scalar(self) -> Property;
+
+        fn to_list(list: Vec<Self>) -> Property;
+    }
+}
+
+use sealed::Number;
+
+/// numeric types of ply properties
+///
+/// a property of any numeric type can be converted to any of them, as long as the value can be kept:
+/// integers must be in range, floats must be integral to become integers,
+/// while integers and `f64` become floats with rounding
+pub trait PropertyScalar: Copy + Default + sealed::Sealed {
+    const TYPE_NAME: &'static str;
+    const LIST_TYPE_NAME: &'static str;
+}
+
+macro_rules! impl_property_scalar {
+    ($($ty:ident => $scalar:ident, $list:ident, $from_number:expr;)+) => {
+        $(
+            impl sealed::Sealed for $ty {
+                fn from_number(number: Number) -> Option<Self> {
+                    let from_number: fn(Number) -> Option<Self> = $from_number;
+                    from_number(number)
+                }
+
+                fn to_scalar(self) -> Property {
+                    Property::$scalar(self)
+                }
+
+                fn to_list(list: Vec<Self>) -> Property {
+                    Property::$list(list)
+                }
+            }
+
+            impl PropertyScalar for $ty {
+                const TYPE_NAME: &'static str = stringify!($ty);
+                const LIST_TYPE_NAME: &'static str = concat!("list of ", stringify!($ty));
+            }
+        )+
+    };
+}
+
+macro_rules! integer_from_number {
+    ($ty:ident) => {
+        |number| match number {
+            Number::Int(val) => $ty::try_from(val).ok(),
+            Number::Float(val) if val.is_finite() && val.fract() == 0.0 => {
+                $ty::try_from(val as i64).ok()
+            }
+            Number::Float(_) => None,
+        }
+    };
+}
+
+macro_rules! float_from_number {
+    ($ty:ident) => {
+        |number| match number {
+            Number::Int(val) => Some(val as $ty),
+            Number::Float(val) => Some(val as $ty),
+        }
+    };
+}
+
+impl_property_scalar! {
+    i8 => I8, I8List, integer_from_number!(i8);
+    u8 => U8, U8List, integer_from_number!(u8);
+    i16 => I16, I16List, integer_from_number!(i16);
+    u16 => U16, U16List, integer_from_number!(u16);
+    i32 => I32, I32List, integer_from_number!(i32);
+    u32 => U32, U32List, integer_from_number!(u32);
+    f32 => F32, F32List, float_from_number!(f32);
+    f64 => F64, F64List, float_from_number!(f64);
+}
+
+/// types that can be read from and written to a `Property`,
+/// used by `PropertyMap::get()` and `#[derive(FromPropertyMap, ToPropertyMap)]`
+pub trait PropertyValue: Sized {
+    /// name of the type used in `PropertyError`
+    const TYPE_NAME: &'static str;
+
+    /// read value from `prop`, return `None` if it can't be converted
+    fn from_property(prop: &Property) -> Option<Self>;
+
+    fn to_property(&self) -> Property;
+}
+
+impl<T: PropertyScalar> PropertyValue for T {
+    const TYPE_NAME: &'static str = T::TYPE_NAME;
+
+    fn from_property(prop: &Property) -> Option<Self> {
+        T::from_number(prop.number()?)
+    }
+
+    fn to_property(&self) -> Property {
+        self.to_scalar()
+    }
+}
+
+impl<T: PropertyScalar> PropertyValue for Vec<T> {
+    const TYPE_NAME: &'static str = T::LIST_TYPE_NAME;
+
+    fn from_property(prop: &Property) -> Option<Self> {
+        prop.numbers()?.into_iter().map(T::from_number).collect()
+    }
+
+    fn to_property(&self) -> Property {
+        T::to_list(self.clone())
+    }
+}
+
+impl Property {
+    fn number(&self) -> Option<Number> {
+        match self {
+            Property::I8(val) => Some(Number::Int(*val as i64)),
+            Property::U8(val) => Some(Number::Int(*val as i64)),
+            Property::I16(val) => Some(Number::Int(*val as i64)),
+            Property::U16(val) => Some(Number::Int(*val as i64)),
+            Property::I32(val) => Some(Number::Int(*val as i64)),
+            Property::U32(val) => Some(Number::Int(*val as i64)),
+            Property::F32(val) => Some(Number::Float(*val as f64)),
+            Property::F64(val) => Some(Number::Float(*val)),
+            _ => None,
+        }
+    }
+
+    fn numbers(&self) -> Option<Vec<Number>> {
+        let ints = |list: Vec<i64>| Some(list.into_iter().map(Number::Int).collect());
+        let floats = |list: Vec<f64>| Some(list.into_iter().map(Number::Float).collect());
+        match self {
+            Property::I8List(val) => ints(val.iter().map(|x| *x as i64).collect()),
+            Property::U8List(val) => ints(val.iter().map(|x| *x as i64).collect()),
+            Property::I16List(val) => ints(val.iter().map(|x| *x as i64).collect()),
+            Property::U16List(val) => ints(val.iter().map(|x| *x as i64).collect()),
+            Property::I32List(val) => ints(val.iter().map(|x| *x as i64).collect()),
+            Property::U32List(val) => ints(val.iter().map(|x| *x as i64).collect()),
+            Property::F32List(val) => floats(val.iter().map(|x| *x as f64).collect()),
+            Property::F64List(val) => floats(val.clone()),
+            _ => None,
+        }
+    }
+
+    /// colors of unsigned integer types are normalized to `[0, 1]`
+    fn color_channel(&self) -> Option<f32> {
+        match self {
+            Property::U8(val) => Some(*val as f32 / u8::MAX as f32),
+            Property::U16(val) => Some(*val as f32 / u16::MAX as f32),
+            Property::U32(val) => Some((*val as f64 / u32::MAX as f64) as f32),
+            Property::F32(val) => Some(*val),
+            Property::F64(val) => Some(*val as f32),
+            _ => None,
+        }
+    }
+
+    /// type and value of scalars or type of lists, used in `PropertyError`
+    fn describe(&self) -> String {
+        match self {
+            Property::I8(val) => format!("i8 {}", val),
+            Property::U8(val) => format!("u8 {}", val),
+            Property::I16(val) => format!("i16 {}", val),
+            Property::U16(val) => format!("u16 {}", val),
+            Property::I32(val) => format!("i32 {}", val),
+            Property::U32(val) => format!("u32 {}", val),
+            Property::F32(val) => format!("f32 {}", val),
+            Property::F64(val) => format!("f64 {}", val),
+            Property::I8List(_) => "list of i8".to_owned(),
+            Property::U8List(_) => "list of u8".to_owned(),
+            Property::I16List(_) => "list of i16".to_owned(),
+            Property::U16List(_) => "list of u16".to_owned(),
+            Property::I32List(_) => "list of i32".to_owned(),
+            Property::U32List(_) => "list of u32".to_owned(),
+            Property::F32List(_) => "list of f32".to_owned(),
+            Property::F64List(_) => "list of f64".to_owned(),
+        }
+    }
+}
+
+impl PropertyMap {
+    /// get property `name` converted to `T`
+    pub fn get<T: PropertyValue>(&self, name: &str) -> Result<T, PropertyError> {
+        let prop = self
+            .map
+            .get(name)
+            .ok_or_else(|| PropertyError::Missing(name.to_owned()))?;
+        T::from_property(prop).ok_or_else(|| PropertyError::Mismatch {
+            name: name.to_owned(),
+            expected: T::TYPE_NAME,
+            found: prop.describe(),
+        })
+    }
+
+    pub fn get_f32(&self, name: &str) -> Result<f32, PropertyError> {
+        self.get(name)
+    }
+
+    pub fn get_f64(&self, name: &str) -> Result<f64, PropertyError> {
+        self.get(name)
+    }
+
+    pub fn get_i32(&self, name: &str) -> Result<i32, PropertyError> {
+        self.get(name)
+    }
+
+    pub fn get_u32(&self, name: &str) -> Result<u32, PropertyError> {
+        self.get(name)
+    }
+
+    /// get list property `name`, every element is converted to `T`
+    pub fn get_list<T: PropertyScalar>(&self, name: &str) -> Result<Vec<T>, PropertyError> {
+        self.get(name)
+    }
+
+    /// get properties `names` as an array
+    pub fn get_array<T: PropertyScalar, const N: usize>(
+        &self,
+        names: [&str; N],
+    ) -> Result<[T; N], PropertyError> {
+        let mut values = [T::default(); N];
+        for (value, name) in values.iter_mut().zip(names) {
+            *value = self.get(name)?;
+        }
+        Ok(values)
+    }
+
+    pub fn get_vec2(&self, names: [&str; 2]) -> Result<[f32; 2], PropertyError> {
+        self.get_array(names)
+    }
+
+    pub fn get_vec3(&self, names: [&str; 3]) -> Result<[f32; 3], PropertyError> {
+        self.get_array(names)
+    }
+
+    pub fn get_vec4(&self, names: [&str; 4]) -> Result<[f32; 4], PropertyError> {
+        self.get_array(names)
+    }
+
+    /// get `red`, `green` and `blue`, unsigned integers are normalized to `[0, 1]`
+    pub fn get_color_rgb(&self) -> Result<[f32; 3], PropertyError> {
+        let mut color = [0.0; 3];
+        for (value, name) in color.iter_mut().zip(["red", "green", "blue"]) {
+            *value = self.get_color_channel(name)?;
+        }
+        Ok(color)
+    }
+
+    /// get `red`, `green`, `blue` and `alpha`, unsigned integers are normalized to `[0, 1]`,
+    /// alpha is `1.0` if it's missing
+    pub fn get_color_rgba(&self) -> Result<[f32; 4], PropertyError> {
+        let [r, g, b] = self.get_color_rgb()?;
+        let alpha = match self.get_color_channel("alpha") {
+            Err(PropertyError::Missing(_)) => 1.0,
+            alpha => alpha?,
+        };
+        Ok([r, g, b, alpha])
+    }
+
+    fn get_color_channel(&self, name: &str) -> Result<f32, PropertyError> {
+        let prop = self
+            .map
+            .get(name)
+            .ok_or_else(|| PropertyError::Missing(name.to_owned()))?;
+        prop.color_channel().ok_or_else(|| PropertyError::Mismatch {
+            name: name.to_owned(),
+            expected: "unsigned integer or float",
+            found: prop.describe(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn props(values: &[(&'static str, Property)]) -> PropertyMap {
+        let mut props = PropertyMap::default();
+        for (name, value) in values {
+            props.map.insert((*name).into(), value.clone());
+        }
+        props
+    }
+
+    #[test]
+    fn widening_conversions() {
+        let props = props(&[
+            ("u8", Property::U8(200)),
+            ("i16", Property::I16(-300)),
+            ("f32", Property::F32(1.5)),
+            ("integral", Property::F64(42.0)),
+            ("list", Property::U8List(vec![1, 2, 3])),
+        ]);
+        assert_eq!(props.get_u32("u8").unwrap(), 200);
+        assert_eq!(props.get_i32("u8").unwrap(), 200);
+        assert_eq!(props.get_f32("u8").unwrap(), 200.0);
+        assert_eq!(props.get_i32("i16").unwrap(), -300);
+        assert_eq!(props.get_f64("i16").unwrap(), -300.0);
+        assert_eq!(props.get_f64("f32").unwrap(), 1.5);
+        assert_eq!(props.get_i32("integral").unwrap(), 42);
+        assert_eq!(props.get::<u8>("integral").unwrap(), 42);
+        assert_eq!(props.get_list::<u32>("list").unwrap(), vec![1, 2, 3]);
+        assert_eq!(props.get_list::<f32>("list").unwrap(), vec![1.0, 2.0, 3.0]);
+        assert_eq!(
+            props.get_array::<i32, 2>(["u8", "i16"]).unwrap(),
+            [200, -300]
+        );
+        assert_eq!(props.get_color_rgb().ok(), None);
+
+        let color = self::props(&[
+            ("red", Property::U8(255)),
+            ("green", Property::U16(0)),
+            ("blue", Property::F32(0.5)),
+        ]);
+        assert_eq!(color.get_color_rgba().unwrap(), [1.0, 0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn mismatches_are_errors() {
+        let props = props(&[
+            ("negative", Property::I32(-1)),
+            ("large", Property::U32(300)),
+            ("fraction", Property::F32(2.5)),
+            ("list", Property::F32List(vec![1.5])),
+            ("red", Property::I8(1)),
+        ]);
+        let mismatch = |result: Result<_, PropertyError>, expected_name: &str| matches!(result, Err(PropertyError::Mismatch { name, .. }) if name == expected_name);
+        assert!(mismatch(props.get_u32("negative").map(|_| ()), "negative"));
+        assert!(mismatch(props.get::<u8>("large").map(|_| ()), "large"));
+        assert!(mismatch(props.get_i32("fraction").map(|_| ()), "fraction"));
+        assert!(mismatch(props.get_f32("list").map(|_| ()), "list"));
+        assert!(mismatch(
+            props.get_list::<f32>("large").map(|_| ()),
+            "large"
+        ));
+        assert!(mismatch(props.get_list::<u8>("list").map(|_| ()), "list"));
+        assert!(mismatch(props.get_color_rgb().map(|_| ()), "red"));
+        assert!(matches!(
+            props.get_f32("missing"),
+            Err(PropertyError::Missing(name)) if name == "missing"
+        ));
+
+        let message = props.get::<u8>("large").unwrap_err().to_string();
+        assert_eq!(
+            message,
+            "property 'large' is expected to be u8 but found u32 300"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use crate::{
+    halfedge::{check_manifold, HalfEdgeMesh},
+    io::{
+        ply::{data_from_props, FromPropertyMap, Property, PropertyMap, ToPropertyMap},
+        stl::{StlContent, StlEncoding},
+        LoadError, SaveError,
+    },
+};
+
+/// load an ascii or binary stl file, vertices at the same position are merged and triangles whose
+/// vertices are merged together are dropped
+///
+/// vertex data is created from properties `x`, `y`, `z`, face data is created from properties
+/// `nx`, `ny`, `nz` of the facet normal and `U16` property `attribute`;
+/// an error is returned if the welded triangles don't form a manifold
+pub fn load_to_halfedge<Path, VData, EData, FData>(
+    path: Path,
+) -> Result<HalfEdgeMesh<VData, EData, FData>, LoadError>
+where
+    Path: AsRef<std::path::Path>,
+    VData: Default + FromPropertyMap,
+    EData: Default + FromPropertyMap,
+    FData: Default + FromPropertyMap,
+{
+    let file = std::fs::File::open(path.as_ref())?;
+    load_to_halfedge_from_reader(std::io::BufReader::new(file))
+}
+
+pub fn load_to_halfedge_from_reader<Reader, VData, EData, FData>(
+    reader: Reader,
+) -> Result<HalfEdgeMesh<VData, EData, FData>, LoadError>
+where
+    Reader: Read,
+    VData: Default + FromPropertyMap,
+    EData: Default + FromPropertyMap,
+    FData: Default + FromPropertyMap,
+{
+    let content = StlContent::read(reader)?;
+    let (positions, triangles) = content.weld();
+
+    let mut faces = Vec::with_capacity(triangles.len());
+    let mut faces_data = HashMap::with_capacity(triangles.len());
+    for (i, tri) in triangles.iter().enumerate() {
+        if tri[0] == tri[1] || tri[0] == tri[2] || tri[1] == tri[2] {
+            continue;
+        }
+        let n = content.normals[i];
+        let mut props = PropertyMap::default();
+        props.map.insert("nx".into(), Property::F32(n[0]));
+        props.map.insert("ny".into(), Property::F32(n[1]));
+        props.map.insert("nz".into(), Property::F32(n[2]));
+        props
+            .map
+            .insert("attribute".into(), Property::U16(content.attributes[i]));
+        faces_data.insert(faces.len(), data_from_props::<FData>(props, "facet", i)?);
+        faces.push(tri.to_vec());
+    }
+    check_manifold(&faces).map_err(LoadError::Topology)?;
+
+    let vertices_data = positions
+        .into_iter()
+        .enumerate()
+        .map(|(v, p)| {
+            let mut props = PropertyMap::default();
+            props.map.insert("x".into(), Property::F32(p[0]));
+            props.map.insert("y".into(), Property::F32(p[1]));
+            props.map.insert("z".into(), Property::F32(p[2]));
+            Ok((v, data_from_props::<VData>(props, "vertex", v)?))
+        })
+        .collect::<Result<_, LoadError>>()?;
+
+    Ok(HalfEdgeMesh::new(
+        faces,
+        vertices_data,
+        HashMap::new(),
+        faces_data,
+    ))
+}
+
+/// save a half-edge mesh, positions are given by properties `x`, `y`, `z` of vertex data and
+/// attribute byte count is given by property `attribute` of face data converted to `u16`,
+/// polygons are triangulated as fans and facet normals are computed from positions,
+/// `name` is written to `solid` or the header of binary files (truncated to 80 bytes)
+pub fn save_halfedge<Path, VData, EData, FData>(
+    path: Path,
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+    name: &str,
+    encoding: StlEncoding,
+) -> Result<(), SaveError>
+where
+    Path: AsRef<std::path::Path>,
+    VData: ToPropertyMap,
+    FData: ToPropertyMap,
+{
+    save_halfedge_to_writer(super::create_file(path)?, mesh, name, encoding)
+}
+
+pub fn save_halfedge_to_writer<Writer, VData, EData, FData>(
+    writer: Writer,
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+    name: &str,
+    encoding: StlEncoding,
+) -> Result<(), SaveError>
+where
+    Writer: Write,
+    VData: ToPropertyMap,
+    FData: ToPropertyMap,
+{
+    let positions = mesh
+        .vertices()
+        .map(|vref| {
+            let props = mesh.vertex_data(&vref).to_proprety_map();
+            let value = |key: &str| props.get_f32(key).unwrap_or(0.0);
+            [value("x"), value("y"), value("z")]
+        })
+        .collect::<Vec<_>>();
+
+    let mut triangles = vec![];
+    let mut attributes = vec![];
+    for fref in mesh.faces() {
+        if fref.is_boundary(mesh) {
+            continue;
+        }
+        let attribute = mesh
+            .face_data(&fref)
+            .to_proprety_map()
+            .get::<u16>("attribute")
+            .unwrap_or(0);
+
+        let mut polygon = vec![];
+        let mut he = fref.halfedge(mesh);
+        loop {
+            polygon.push(positions[he.vertex(mesh).id]);
+            he = he.next(mesh);
+            if he == fref.halfedge(mesh) {
+                break;
+            }
+        }
+        for i in 1..polygon.len() - 1 {
+            triangles.push([polygon[0], polygon[i], polygon[i + 1]]);
+            attributes.push(attribute);
+        }
+    }
+
+    super::write_triangles(writer, name, &triangles, &attributes, encoding)
+}
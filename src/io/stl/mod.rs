@@ -0,0 +1,211 @@
+mod halfedge;
+mod trimesh;
+
+pub use halfedge::*;
+pub use trimesh::*;
+
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use super::{create_file, LoadError, SaveError};
+use crate::util::{cross, normalize, sub};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StlEncoding {
+    Ascii,
+    Binary,
+}
+
+/// facets of a stl file
+struct StlContent {
+    /// name of `solid`, or the 80-byte header of binary files
+    name: String,
+    triangles: Vec<[[f32; 3]; 3]>,
+    normals: Vec<[f32; 3]>,
+    attributes: Vec<u16>,
+}
+
+impl StlContent {
+    /// binary files are detected by their size, which is given by the number of triangles
+    fn read<R: Read>(mut reader: R) -> Result<Self, LoadError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+
+        let is_binary = bytes.len() >= 84 && {
+            let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+            bytes.len() == 84 + 50 * count
+        };
+        if is_binary {
+            Ok(Self::parse_binary(&bytes))
+        } else if bytes.trim_ascii_start().starts_with(b"solid") {
+            Self::parse_ascii(&String::from_utf8_lossy(&bytes))
+        } else {
+            Err(LoadError::UnsupportedFormat(
+                "file is neither ascii stl nor binary stl".to_owned(),
+            ))
+        }
+    }
+
+    fn parse_binary(bytes: &[u8]) -> Self {
+        let name = String::from_utf8_lossy(&bytes[..80])
+            .trim_end_matches(['\0', ' '])
+            .to_owned();
+        let count = (bytes.len() - 84) / 50;
+        let float = |offset: usize| {
+            f32::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ])
+        };
+        let vec3 = |offset: usize| [float(offset), float(offset + 4), float(offset + 8)];
+
+        let mut content = Self {
+            name,
+            triangles: Vec::with_capacity(count),
+            normals: Vec::with_capacity(count),
+            attributes: Vec::with_capacity(count),
+        };
+        for i in 0..count {
+            let offset = 84 + 50 * i;
+            content.normals.push(vec3(offset));
+            content
+                .triangles
+                .push([vec3(offset + 12), vec3(offset + 24), vec3(offset + 36)]);
+            content
+                .attributes
+                .push(u16::from_le_bytes([bytes[offset + 48], bytes[offset + 49]]));
+        }
+        content
+    }
+
+    fn parse_ascii(text: &str) -> Result<Self, LoadError> {
+        let mut content = Self {
+            name: String::new(),
+            triangles: vec![],
+            normals: vec![],
+            attributes: vec![],
+        };
+        let mut normal = [0.0; 3];
+        let mut polygon = vec![];
+
+        for (line_number, line) in text.lines().enumerate() {
+            let error = |info: &str| {
+                LoadError::parse_at_line(line_number + 1, format!("{}, '{}'", info, line))
+            };
+            let vec3 = |tokens: std::str::SplitWhitespace| {
+                let values = tokens
+                    .map(|token| token.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| error("invalid number"))?;
+                if values.len() != 3 {
+                    return Err(error("expect 3 numbers"));
+                }
+                Ok([values[0], values[1], values[2]])
+            };
+
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("solid") => content.name = line.trim()["solid".len()..].trim().to_owned(),
+                Some("facet") => {
+                    if tokens.next() != Some("normal") {
+                        return Err(error("expect 'facet normal'"));
+                    }
+                    normal = vec3(tokens)?;
+                    polygon.clear();
+                }
+                Some("vertex") => polygon.push(vec3(tokens)?),
+                Some("endfacet") => {
+                    if polygon.len() < 3 {
+                        return Err(error("facet has less than 3 vertices"));
+                    }
+                    for i in 1..polygon.len() - 1 {
+                        content
+                            .triangles
+                            .push([polygon[0], polygon[i], polygon[i + 1]]);
+                        content.normals.push(normal);
+                        content.attributes.push(0);
+                    }
+                }
+                Some("endsolid") => break,
+                _ => {}
+            }
+        }
+
+        Ok(content)
+    }
+
+    /// merge vertices at the same position, return positions and vertex indices of triangles
+    fn weld(&self) -> (Vec<[f32; 3]>, Vec<[usize; 3]>) {
+        let mut vertices = HashMap::new();
+        let mut positions = vec![];
+        let triangles = self
+            .triangles
+            .iter()
+            .map(|tri| {
+                tri.map(|p| {
+                    // `0.0` and `-0.0` are the same position
+                    let key = p.map(|x| (x + 0.0).to_bits());
+                    *vertices.entry(key).or_insert_with(|| {
+                        positions.push(p);
+                        positions.len() - 1
+                    })
+                })
+            })
+            .collect();
+        (positions, triangles)
+    }
+}
+
+/// write triangles with their facet normals computed from positions
+fn write_triangles<W: Write>(
+    mut writer: W,
+    name: &str,
+    triangles: &[[[f32; 3]; 3]],
+    attributes: &[u16],
+    encoding: StlEncoding,
+) -> Result<(), SaveError> {
+    let mut write = || -> std::io::Result<()> {
+        match encoding {
+            StlEncoding::Ascii => {
+                writeln!(writer, "solid {}", name)?;
+                for tri in triangles {
+                    let n = facet_normal(tri);
+                    writeln!(writer, "facet normal {} {} {}", n[0], n[1], n[2])?;
+                    writeln!(writer, "  outer loop")?;
+                    for p in tri {
+                        writeln!(writer, "    vertex {} {} {}", p[0], p[1], p[2])?;
+                    }
+                    writeln!(writer, "  endloop")?;
+                    writeln!(writer, "endfacet")?;
+                }
+                writeln!(writer, "endsolid {}", name)?;
+            }
+            StlEncoding::Binary => {
+                let mut header = [0u8; 80];
+                let len = name.len().min(80);
+                header[..len].copy_from_slice(&name.as_bytes()[..len]);
+                writer.write_all(&header)?;
+                writer.write_all(&(triangles.len() as u32).to_le_bytes())?;
+                for (i, tri) in triangles.iter().enumerate() {
+                    for v in std::iter::once(facet_normal(tri)).chain(tri.iter().copied()) {
+                        for x in v {
+                            writer.write_all(&x.to_le_bytes())?;
+                        }
+                    }
+                    let attribute = attributes.get(i).copied().unwrap_or(0);
+                    writer.write_all(&attribute.to_le_bytes())?;
+                }
+            }
+        }
+        writer.flush()
+    };
+    write().map_err(SaveError::Io)
+}
+
+fn facet_normal(tri: &[[f32; 3]; 3]) -> [f32; 3] {
+    normalize(cross(sub(tri[1], tri[0]), sub(tri[2], tri[0])))
+}
@@ -0,0 +1,136 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use crate::{
+    io::{
+        stl::{StlContent, StlEncoding},
+        LoadError, SaveError,
+    },
+    trimesh::{MeshIndices, TriMesh, VertexAttribute},
+};
+
+pub struct StlTriMesh {
+    /// has `POSITION` only, vertices at the same position are merged
+    pub mesh: TriMesh,
+    /// name of `solid`, or the header of binary files
+    pub name: String,
+    /// normal stored in the file for each triangle
+    pub facet_normals: Vec<[f32; 3]>,
+    /// attribute byte count of each triangle, always `0` for ascii files
+    pub facet_attributes: Vec<u16>,
+}
+
+/// load an ascii or binary stl file
+pub fn load_to_trimesh<Path: AsRef<std::path::Path>>(path: Path) -> Result<StlTriMesh, LoadError> {
+    let file = std::fs::File::open(path.as_ref())?;
+    load_to_trimesh_from_reader(std::io::BufReader::new(file))
+}
+
+pub fn load_to_trimesh_from_reader<Reader: Read>(reader: Reader) -> Result<StlTriMesh, LoadError> {
+    let content = StlContent::read(reader)?;
+    let (positions, triangles) = content.weld();
+
+    let mut vertex_attributes = HashMap::new();
+    vertex_attributes.insert(TriMesh::POSITION.into(), VertexAttribute::Float3(positions));
+    let indices = triangles
+        .iter()
+        .flatten()
+        .map(|v| *v as u32)
+        .collect::<Vec<_>>();
+
+    Ok(StlTriMesh {
+        mesh: TriMesh::new(vertex_attributes, Some(MeshIndices::U32(indices))),
+        name: content.name,
+        facet_normals: content.normals,
+        facet_attributes: content.attributes,
+    })
+}
+
+/// save `POSITION` of `mesh`, facet normals are computed from positions,
+/// `name` is written to `solid` or the header of binary files (truncated to 80 bytes),
+/// `facet_attributes` are written to binary files and missing ones are `0`
+pub fn save_trimesh<Path: AsRef<std::path::Path>>(
+    path: Path,
+    mesh: &TriMesh,
+    name: &str,
+    facet_attributes: &[u16],
+    encoding: StlEncoding,
+) -> Result<(), SaveError> {
+    save_trimesh_to_writer(
+        super::create_file(path)?,
+        mesh,
+        name,
+        facet_attributes,
+        encoding,
+    )
+}
+
+pub fn save_trimesh_to_writer<Writer: Write>(
+    writer: Writer,
+    mesh: &TriMesh,
+    name: &str,
+    facet_attributes: &[u16],
+    encoding: StlEncoding,
+) -> Result<(), SaveError> {
+    let positions = mesh.attribute_as::<[f32; 3]>(TriMesh::POSITION)?;
+    let triangles = mesh
+        .triangles()
+        .into_iter()
+        .map(|tri| tri.map(|v| positions[v]))
+        .collect::<Vec<_>>();
+    super::write_triangles(writer, name, &triangles, facet_attributes, encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::TriMeshBuilder;
+
+    #[test]
+    fn round_trip() {
+        let positions = vec![
+            [0.0f32, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.1, 0.2, 1.5],
+        ];
+        let mesh = TriMeshBuilder::new()
+            .attribute(TriMesh::POSITION, positions.clone())
+            .indices(vec![0, 2, 1, 0, 1, 3, 1, 2, 3, 2, 0, 3])
+            .build()
+            .unwrap();
+        let facet_attributes = [1, 2, 3, 4];
+
+        for encoding in [StlEncoding::Ascii, StlEncoding::Binary] {
+            let mut bytes = vec![];
+            save_trimesh_to_writer(&mut bytes, &mesh, "part", &facet_attributes, encoding).unwrap();
+            let loaded = load_to_trimesh_from_reader(bytes.as_slice()).unwrap();
+            assert_eq!(loaded.name, "part");
+
+            // vertices are welded in order of first use
+            let loaded_positions = loaded
+                .mesh
+                .attribute_as::<[f32; 3]>(TriMesh::POSITION)
+                .unwrap();
+            assert_eq!(loaded_positions.len(), positions.len());
+            let triangles = |mesh: &TriMesh, positions: &[[f32; 3]]| {
+                mesh.triangles()
+                    .iter()
+                    .map(|tri| tri.map(|v| positions[v]))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(
+                triangles(&loaded.mesh, loaded_positions),
+                triangles(&mesh, &positions)
+            );
+            assert_eq!(loaded.facet_normals[0], [0.0, 0.0, -1.0]);
+            let expected = match encoding {
+                StlEncoding::Ascii => [0; 4],
+                StlEncoding::Binary => facet_attributes,
+            };
+            assert_eq!(loaded.facet_attributes, expected);
+        }
+    }
+}
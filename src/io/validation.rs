@@ -0,0 +1,189 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::{io::LoadError, trimesh::VertexAttribute};
+
+/// how problems of loaded data are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationPolicy {
+    /// fail with `LoadError` at the first problem
+    #[default]
+    Strict,
+    /// skip invalid records, fit vertex attributes to the number of vertices,
+    /// and report every problem as a `ValidationIssue`
+    Lenient,
+}
+
+/// a problem found when loading, `record` is the index of the record in its element
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    /// a property required by the record is missing, the record is skipped
+    MissingProperty {
+        element: String,
+        record: usize,
+        name: String,
+    },
+    /// the record is skipped for other reasons, e.g. a face has less than 3 vertices
+    /// or its indices have an unexpected type
+    InvalidRecord {
+        element: String,
+        record: usize,
+        reason: String,
+    },
+    /// the record refers to a negative index, it is skipped
+    NegativeIndex {
+        element: String,
+        record: usize,
+        index: i64,
+    },
+    /// the record refers to item `index`, but there are only `count` items, it is skipped
+    IndexOutOfRange {
+        element: String,
+        record: usize,
+        index: usize,
+        count: usize,
+    },
+    /// a vertex attribute has `len` values instead of one for each of `expected` vertices,
+    /// it is truncated or padded with zeros
+    AttributeLength {
+        name: Cow<'static, str>,
+        len: usize,
+        expected: usize,
+    },
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::MissingProperty {
+                element,
+                record,
+                name,
+            } => write!(f, "{} {} has no property '{}'", element, record, name),
+            ValidationIssue::InvalidRecord {
+                element,
+                record,
+                reason,
+            } => write!(f, "{} {} is invalid, {}", element, record, reason),
+            ValidationIssue::NegativeIndex {
+                element,
+                record,
+                index,
+            } => write!(f, "{} {} uses negative index {}", element, record, index),
+            ValidationIssue::IndexOutOfRange {
+                element,
+                record,
+                index,
+                count,
+            } => write!(
+                f,
+                "index {} used by {} {} is out of range, there are {} items",
+                index, element, record, count
+            ),
+            ValidationIssue::AttributeLength {
+                name,
+                len,
+                expected,
+            } => write!(
+                f,
+                "vertex attribute '{}' has {} values but there are {} vertices",
+                name, len, expected
+            ),
+        }
+    }
+}
+
+impl From<ValidationIssue> for LoadError {
+    fn from(issue: ValidationIssue) -> Self {
+        match issue {
+            ValidationIssue::MissingProperty { element, name, .. } => {
+                LoadError::MissingProperty { element, name }
+            }
+            ValidationIssue::IndexOutOfRange {
+                element,
+                record,
+                index,
+                count,
+            } => LoadError::IndexOutOfRange {
+                element,
+                record,
+                index,
+                count,
+            },
+            ValidationIssue::InvalidRecord { ref element, .. }
+            | ValidationIssue::NegativeIndex { ref element, .. } => {
+                LoadError::parse_in_element(element, issue.to_string())
+            }
+            ValidationIssue::AttributeLength { .. } => {
+                LoadError::parse_in_element("vertex", issue.to_string())
+            }
+        }
+    }
+}
+
+/// apply a `ValidationPolicy` and collect issues
+pub(crate) struct Validator {
+    policy: ValidationPolicy,
+    pub(crate) issues: Vec<ValidationIssue>,
+}
+
+impl Validator {
+    pub(crate) fn new(policy: ValidationPolicy) -> Self {
+        Self {
+            policy,
+            issues: vec![],
+        }
+    }
+
+    /// fail with `issue` for `Strict` policy, otherwise keep it
+    pub(crate) fn report(&mut self, issue: ValidationIssue) -> Result<(), LoadError> {
+        match self.policy {
+            ValidationPolicy::Strict => Err(issue.into()),
+            ValidationPolicy::Lenient => {
+                self.issues.push(issue);
+                Ok(())
+            }
+        }
+    }
+
+    /// check that indices used by record `record` of `element` are less than `count`,
+    /// return whether the record can be kept
+    pub(crate) fn check_indices(
+        &mut self,
+        element: &str,
+        record: usize,
+        indices: &[usize],
+        count: usize,
+    ) -> Result<bool, LoadError> {
+        match indices.iter().find(|index| **index >= count) {
+            Some(index) => {
+                self.report(ValidationIssue::IndexOutOfRange {
+                    element: element.to_owned(),
+                    record,
+                    index: *index,
+                    count,
+                })?;
+                Ok(false)
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// check that every attribute has `num_vertices` values, fit them if the policy is `Lenient`
+    pub(crate) fn check_attributes(
+        &mut self,
+        vertex_attributes: &mut HashMap<Cow<'static, str>, VertexAttribute>,
+        num_vertices: usize,
+    ) -> Result<(), LoadError> {
+        for (name, attribute) in vertex_attributes {
+            if attribute.len() != num_vertices {
+                self.report(ValidationIssue::AttributeLength {
+                    name: name.clone(),
+                    len: attribute.len(),
+                    expected: num_vertices,
+                })?;
+                attribute.resize(num_vertices);
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
 pub mod halfedge;
 pub mod io;
 pub mod trimesh;
+mod util;
+pub mod uv;
@@ -1,38 +1,258 @@
+use std::borrow::Cow;
+
+pub use half::f16;
+
+/// formats of `VertexAttribute`, named in the same way as its variants
+///
+/// `Unorm` and `Snorm` formats are integers normalized to `[0, 1]` and `[-1, 1]`,
+/// 8-bit, 16-bit and half float formats only have 2 or 4 components like GPU vertex formats
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum VertexAttributeFormat {
     Float,
     Float2,
     Float3,
     Float4,
+    Double,
+    Double2,
+    Double3,
+    Double4,
+    Half2,
+    Half4,
     Int,
     Int2,
     Int3,
     Int4,
+    Uint,
+    Uint2,
+    Uint3,
+    Uint4,
+    Uint8x2,
+    Uint8x4,
+    Uint16x2,
+    Uint16x4,
+    Unorm8x2,
+    Unorm8x4,
+    Snorm8x2,
+    Snorm8x4,
+    Unorm16x2,
+    Unorm16x4,
+    Snorm16x2,
+    Snorm16x4,
+}
+
+/// how components of a format are interpreted
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ComponentKind {
+    Float,
+    Int,
+    Unorm,
+    Snorm,
+}
+
+impl VertexAttributeFormat {
+    /// number of components of a value
+    pub fn components(self) -> usize {
+        use VertexAttributeFormat::*;
+        match self {
+            Float | Double | Int | Uint => 1,
+            Float2 | Double2 | Half2 | Int2 | Uint2 | Uint8x2 | Uint16x2 | Unorm8x2 | Snorm8x2
+            | Unorm16x2 | Snorm16x2 => 2,
+            Float3 | Double3 | Int3 | Uint3 => 3,
+            Float4 | Double4 | Half4 | Int4 | Uint4 | Uint8x4 | Uint16x4 | Unorm8x4 | Snorm8x4
+            | Unorm16x4 | Snorm16x4 => 4,
+        }
+    }
+
+    /// size of a value in bytes
+    pub fn size(self) -> usize {
+        self.component_size() * self.components()
+    }
+
+    /// size of a component in bytes
+    pub fn component_size(self) -> usize {
+        use VertexAttributeFormat::*;
+        match self {
+            Uint8x2 | Uint8x4 | Unorm8x2 | Unorm8x4 | Snorm8x2 | Snorm8x4 => 1,
+            Half2 | Half4 | Uint16x2 | Uint16x4 | Unorm16x2 | Unorm16x4 | Snorm16x2 | Snorm16x4 => {
+                2
+            }
+            Float | Float2 | Float3 | Float4 | Int | Int2 | Int3 | Int4 | Uint | Uint2 | Uint3
+            | Uint4 => 4,
+            Double | Double2 | Double3 | Double4 => 8,
+        }
+    }
+
+    /// whether values are floats or normalized integers
+    pub fn is_float(self) -> bool {
+        self.kind() != ComponentKind::Int
+    }
+
+    pub fn is_normalized(self) -> bool {
+        matches!(self.kind(), ComponentKind::Unorm | ComponentKind::Snorm)
+    }
+
+    fn kind(self) -> ComponentKind {
+        use VertexAttributeFormat::*;
+        match self {
+            Float | Float2 | Float3 | Float4 | Double | Double2 | Double3 | Double4 | Half2
+            | Half4 => ComponentKind::Float,
+            Int | Int2 | Int3 | Int4 | Uint | Uint2 | Uint3 | Uint4 | Uint8x2 | Uint8x4
+            | Uint16x2 | Uint16x4 => ComponentKind::Int,
+            Unorm8x2 | Unorm8x4 | Unorm16x2 | Unorm16x4 => ComponentKind::Unorm,
+            Snorm8x2 | Snorm8x4 | Snorm16x2 | Snorm16x4 => ComponentKind::Snorm,
+        }
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeError {
+    Missing(Cow<'static, str>),
+    FormatMismatch {
+        name: Cow<'static, str>,
+        expected: VertexAttributeFormat,
+        found: VertexAttributeFormat,
+    },
+    /// the attribute doesn't have a value for each vertex
+    Length {
+        name: Cow<'static, str>,
+        len: usize,
+        expected: usize,
+    },
+}
+
+impl std::fmt::Display for AttributeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttributeError::Missing(name) => write!(f, "vertex attribute '{}' is missing", name),
+            AttributeError::FormatMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "vertex attribute '{}' is expected to be {:?} but found {:?}",
+                name, expected, found
+            ),
+            AttributeError::Length {
+                name,
+                len,
+                expected,
+            } => write!(
+                f,
+                "vertex attribute '{}' has {} values instead of {}",
+                name, len, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AttributeError {}
+
+#[derive(Clone)]
 pub enum VertexAttribute {
     Float(Vec<f32>),
     Float2(Vec<[f32; 2]>),
     Float3(Vec<[f32; 3]>),
     Float4(Vec<[f32; 4]>),
+    Double(Vec<f64>),
+    Double2(Vec<[f64; 2]>),
+    Double3(Vec<[f64; 3]>),
+    Double4(Vec<[f64; 4]>),
+    Half2(Vec<[f16; 2]>),
+    Half4(Vec<[f16; 4]>),
     Int(Vec<i32>),
     Int2(Vec<[i32; 2]>),
     Int3(Vec<[i32; 3]>),
     Int4(Vec<[i32; 4]>),
+    Uint(Vec<u32>),
+    Uint2(Vec<[u32; 2]>),
+    Uint3(Vec<[u32; 3]>),
+    Uint4(Vec<[u32; 4]>),
+    Uint8x2(Vec<[u8; 2]>),
+    Uint8x4(Vec<[u8; 4]>),
+    Uint16x2(Vec<[u16; 2]>),
+    Uint16x4(Vec<[u16; 4]>),
+    /// `u8` normalized to `[0, 1]`
+    Unorm8x2(Vec<[u8; 2]>),
+    Unorm8x4(Vec<[u8; 4]>),
+    /// `i8` normalized to `[-1, 1]`, `-128` is clamped to `-1` as well as `-127`
+    Snorm8x2(Vec<[i8; 2]>),
+    Snorm8x4(Vec<[i8; 4]>),
+    Unorm16x2(Vec<[u16; 2]>),
+    Unorm16x4(Vec<[u16; 4]>),
+    Snorm16x2(Vec<[i16; 2]>),
+    Snorm16x4(Vec<[i16; 4]>),
+}
+
+/// call `$callback!(@arms $args; variants...)` with all variants of `VertexAttribute`,
+/// which are also the variants of `VertexAttributeFormat`
+macro_rules! for_each_variant {
+    ( $callback:ident ! ( $( $args:tt )* ) ) => {
+        $callback!(
+            @arms $( $args )*;
+            Float, Float2, Float3, Float4, Double, Double2, Double3, Double4, Half2, Half4,
+            Int, Int2, Int3, Int4, Uint, Uint2, Uint3, Uint4, Uint8x2, Uint8x4, Uint16x2, Uint16x4,
+            Unorm8x2, Unorm8x4, Snorm8x2, Snorm8x4, Unorm16x2, Unorm16x4, Snorm16x2, Snorm16x4
+        )
+    };
+}
+
+/// create the variant of `VertexAttribute` for `$format`, `$values` is evaluated in the matched arm,
+/// so that it can be generic over the type of values
+macro_rules! attribute_of_format {
+    ( $format:expr, $values:expr ) => {
+        for_each_variant!(attribute_of_format!($format, $values))
+    };
+    ( @arms $format:expr, $values:expr; $( $variant:ident ),+ ) => {
+        match $format {
+            $( VertexAttributeFormat::$variant => VertexAttribute::$variant($values), )+
+        }
+    };
+}
+
+/// match all variants of a `VertexAttribute`, binding the values to `$val`
+macro_rules! match_attribute {
+    ( $attribute:expr, $val:ident => $body:expr ) => {
+        for_each_variant!(match_attribute!($attribute, $val => $body))
+    };
+    ( @arms $attribute:expr, $val:ident => $body:expr; $( $variant:ident ),+ ) => {
+        match $attribute {
+            $( VertexAttribute::$variant($val) => $body, )+
+        }
+    };
+}
+
+/// map values of a `VertexAttribute` to an attribute of the same variant
+macro_rules! map_attribute {
+    ( $attribute:expr, $val:ident => $body:expr ) => {
+        for_each_variant!(map_attribute!($attribute, $val => $body))
+    };
+    ( @arms $attribute:expr, $val:ident => $body:expr; $( $variant:ident ),+ ) => {
+        match $attribute {
+            $( VertexAttribute::$variant($val) => VertexAttribute::$variant($body), )+
+        }
+    };
+}
+
+/// match two `VertexAttribute`s of the same variant, or evaluate `$otherwise`
+macro_rules! match_attribute_pair {
+    ( $first:expr, $second:expr, $a:ident, $b:ident => $body:expr, _ => $otherwise:expr ) => {
+        for_each_variant!(match_attribute_pair!($first, $second, $a, $b => $body, _ => $otherwise))
+    };
+    (
+        @arms $first:expr, $second:expr, $a:ident, $b:ident => $body:expr, _ => $otherwise:expr;
+        $( $variant:ident ),+
+    ) => {
+        match ($first, $second) {
+            $( (VertexAttribute::$variant($a), VertexAttribute::$variant($b)) => $body, )+
+            _ => $otherwise,
+        }
+    };
 }
 
 impl VertexAttribute {
     pub fn len(&self) -> usize {
-        match self {
-            VertexAttribute::Float(val) => val.len(),
-            VertexAttribute::Float2(val) => val.len(),
-            VertexAttribute::Float3(val) => val.len(),
-            VertexAttribute::Float4(val) => val.len(),
-            VertexAttribute::Int(val) => val.len(),
-            VertexAttribute::Int2(val) => val.len(),
-            VertexAttribute::Int3(val) => val.len(),
-            VertexAttribute::Int4(val) => val.len(),
-        }
+        match_attribute!(self, val => val.len())
     }
 
     pub fn is_empty(&self) -> bool {
@@ -40,33 +260,360 @@ impl VertexAttribute {
     }
 
     pub fn format(&self) -> VertexAttributeFormat {
+        use VertexAttributeFormat as F;
         match self {
-            VertexAttribute::Float(_) => VertexAttributeFormat::Float,
-            VertexAttribute::Float2(_) => VertexAttributeFormat::Float2,
-            VertexAttribute::Float3(_) => VertexAttributeFormat::Float3,
-            VertexAttribute::Float4(_) => VertexAttributeFormat::Float4,
-            VertexAttribute::Int(_) => VertexAttributeFormat::Int,
-            VertexAttribute::Int2(_) => VertexAttributeFormat::Int2,
-            VertexAttribute::Int3(_) => VertexAttributeFormat::Int3,
-            VertexAttribute::Int4(_) => VertexAttributeFormat::Int4,
+            VertexAttribute::Float(_) => F::Float,
+            VertexAttribute::Float2(_) => F::Float2,
+            VertexAttribute::Float3(_) => F::Float3,
+            VertexAttribute::Float4(_) => F::Float4,
+            VertexAttribute::Double(_) => F::Double,
+            VertexAttribute::Double2(_) => F::Double2,
+            VertexAttribute::Double3(_) => F::Double3,
+            VertexAttribute::Double4(_) => F::Double4,
+            VertexAttribute::Half2(_) => F::Half2,
+            VertexAttribute::Half4(_) => F::Half4,
+            VertexAttribute::Int(_) => F::Int,
+            VertexAttribute::Int2(_) => F::Int2,
+            VertexAttribute::Int3(_) => F::Int3,
+            VertexAttribute::Int4(_) => F::Int4,
+            VertexAttribute::Uint(_) => F::Uint,
+            VertexAttribute::Uint2(_) => F::Uint2,
+            VertexAttribute::Uint3(_) => F::Uint3,
+            VertexAttribute::Uint4(_) => F::Uint4,
+            VertexAttribute::Uint8x2(_) => F::Uint8x2,
+            VertexAttribute::Uint8x4(_) => F::Uint8x4,
+            VertexAttribute::Uint16x2(_) => F::Uint16x2,
+            VertexAttribute::Uint16x4(_) => F::Uint16x4,
+            VertexAttribute::Unorm8x2(_) => F::Unorm8x2,
+            VertexAttribute::Unorm8x4(_) => F::Unorm8x4,
+            VertexAttribute::Snorm8x2(_) => F::Snorm8x2,
+            VertexAttribute::Snorm8x4(_) => F::Snorm8x4,
+            VertexAttribute::Unorm16x2(_) => F::Unorm16x2,
+            VertexAttribute::Unorm16x4(_) => F::Unorm16x4,
+            VertexAttribute::Snorm16x2(_) => F::Snorm16x2,
+            VertexAttribute::Snorm16x4(_) => F::Snorm16x4,
         }
     }
 
+    /// convert values to `format`
+    ///
+    /// normalized integers are converted to and from floats in `[0, 1]` or `[-1, 1]`,
+    /// floats are rounded and clamped to the range of integer formats,
+    /// extra components are dropped and missing ones are filled with `(0, 0, 0, 1)`
+    pub fn to_format(&self, format: VertexAttributeFormat) -> VertexAttribute {
+        let components = self.format().components();
+        let values = self.to_f64_values();
+        let kind = format.kind();
+        attribute_of_format!(format, encode(&values, components, kind))
+    }
+
+    /// flattened components, normalized integers are converted to floats
+    pub(crate) fn to_f64_values(&self) -> Vec<f64> {
+        let kind = self.format().kind();
+        match_attribute!(self, val => decode(val, kind))
+    }
+
+    /// read `count` values of `format` from `bytes` in native endian,
+    /// value `i` starts at `offset + i * stride`
+    pub(crate) fn from_bytes(
+        format: VertexAttributeFormat,
+        bytes: &[u8],
+        offset: usize,
+        stride: usize,
+        count: usize,
+    ) -> VertexAttribute {
+        attribute_of_format!(format, read_values(bytes, offset, stride, count))
+    }
+
+    /// write value `index` to the beginning of `bytes` in native endian
+    pub(crate) fn write_bytes(&self, index: usize, bytes: &mut [u8]) {
+        match_attribute!(self, val => write_value(&val[index], bytes))
+    }
+
+    /// values at `sources`, in the same format
+    pub(crate) fn gather(&self, sources: &[usize]) -> VertexAttribute {
+        map_attribute!(self, val => sources.iter().map(|index| val[*index]).collect())
+    }
+
+    /// append values of `other`, return `false` if it has a different format
+    pub(crate) fn append(&mut self, other: &VertexAttribute) -> bool {
+        match_attribute_pair!(self, other, val, other_val => {
+            val.extend_from_slice(other_val);
+            true
+        }, _ => false)
+    }
+
+    /// append copies of the values at `sources`
+    pub(crate) fn duplicate(&mut self, sources: &[usize]) {
+        match_attribute!(self, val => {
+            for index in sources {
+                let value = val[*index];
+                val.push(value);
+            }
+        })
+    }
+
+    /// truncate values or pad them with zeros
+    pub(crate) fn resize(&mut self, len: usize) {
+        match_attribute!(self, val => val.resize(len, Default::default()))
+    }
+
     #[cfg(feature = "bytemuck")]
     pub fn as_bytes(&self) -> &[u8] {
-        match self {
-            VertexAttribute::Float(val) => bytemuck::cast_slice(&val),
-            VertexAttribute::Float2(val) => bytemuck::cast_slice(&val),
-            VertexAttribute::Float3(val) => bytemuck::cast_slice(&val),
-            VertexAttribute::Float4(val) => bytemuck::cast_slice(&val),
-            VertexAttribute::Int(val) => bytemuck::cast_slice(&val),
-            VertexAttribute::Int2(val) => bytemuck::cast_slice(&val),
-            VertexAttribute::Int3(val) => bytemuck::cast_slice(&val),
-            VertexAttribute::Int4(val) => bytemuck::cast_slice(&val),
+        match_attribute!(self, val => bytemuck::cast_slice(val))
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// rust types of values of `VertexAttribute`, used by `TriMesh::attribute_as()`
+///
+/// `[u8; N]` and `[u16; N]` also access values of `Unorm` formats, which are stored as the same integers,
+/// `[i8; N]` and `[i16; N]` are values of `Snorm` formats
+pub trait AttributeValue: Copy + sealed::Sealed {
+    /// format of attributes created from values of this type
+    const FORMAT: VertexAttributeFormat;
+
+    fn values(attribute: &VertexAttribute) -> Option<&[Self]>;
+
+    fn values_mut(attribute: &mut VertexAttribute) -> Option<&mut [Self]>;
+
+    fn into_attribute(values: Vec<Self>) -> VertexAttribute;
+}
+
+macro_rules! impl_attribute_value {
+    ( $( $type:ty => $enum:ident $( | $alias:ident )? ),+ $(,)? ) => {
+        $(
+            impl sealed::Sealed for $type {}
+
+            impl AttributeValue for $type {
+                const FORMAT: VertexAttributeFormat = VertexAttributeFormat::$enum;
+
+                fn values(attribute: &VertexAttribute) -> Option<&[Self]> {
+                    match attribute {
+                        VertexAttribute::$enum(val) $( | VertexAttribute::$alias(val) )? => Some(val),
+                        _ => None,
+                    }
+                }
+
+                fn values_mut(attribute: &mut VertexAttribute) -> Option<&mut [Self]> {
+                    match attribute {
+                        VertexAttribute::$enum(val) $( | VertexAttribute::$alias(val) )? => Some(val),
+                        _ => None,
+                    }
+                }
+
+                fn into_attribute(values: Vec<Self>) -> VertexAttribute {
+                    VertexAttribute::$enum(values)
+                }
+            }
+        )+
+    };
+}
+
+impl_attribute_value! {
+    f32 => Float,
+    [f32; 2] => Float2,
+    [f32; 3] => Float3,
+    [f32; 4] => Float4,
+    f64 => Double,
+    [f64; 2] => Double2,
+    [f64; 3] => Double3,
+    [f64; 4] => Double4,
+    [f16; 2] => Half2,
+    [f16; 4] => Half4,
+    i32 => Int,
+    [i32; 2] => Int2,
+    [i32; 3] => Int3,
+    [i32; 4] => Int4,
+    u32 => Uint,
+    [u32; 2] => Uint2,
+    [u32; 3] => Uint3,
+    [u32; 4] => Uint4,
+    [u8; 2] => Uint8x2 | Unorm8x2,
+    [u8; 4] => Uint8x4 | Unorm8x4,
+    [u16; 2] => Uint16x2 | Unorm16x2,
+    [u16; 4] => Uint16x4 | Unorm16x4,
+    [i8; 2] => Snorm8x2,
+    [i8; 4] => Snorm8x4,
+    [i16; 2] => Snorm16x2,
+    [i16; 4] => Snorm16x4,
+}
+
+impl<T: AttributeValue> From<Vec<T>> for VertexAttribute {
+    fn from(values: Vec<T>) -> Self {
+        T::into_attribute(values)
+    }
+}
+
+/// a component of a value of `VertexAttribute`
+trait Component: Copy {
+    /// maximum of integers, used for normalization
+    const MAX: f64;
+
+    fn to_f64(self) -> f64;
+
+    /// integers are saturated
+    fn from_f64(value: f64) -> Self;
+
+    fn read_ne(bytes: &[u8]) -> Self;
+
+    fn write_ne(self, bytes: &mut [u8]);
+}
+
+macro_rules! impl_component {
+    ( $( $ty:ty ),+ $(,)? ) => {
+        $(
+            impl Component for $ty {
+                const MAX: f64 = <$ty>::MAX as f64;
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn from_f64(value: f64) -> Self {
+                    value as $ty
+                }
+
+                fn read_ne(bytes: &[u8]) -> Self {
+                    const SIZE: usize = std::mem::size_of::<$ty>();
+                    let mut array = [0; SIZE];
+                    array.copy_from_slice(&bytes[..SIZE]);
+                    <$ty>::from_ne_bytes(array)
+                }
+
+                fn write_ne(self, bytes: &mut [u8]) {
+                    let array = self.to_ne_bytes();
+                    bytes[..array.len()].copy_from_slice(&array);
+                }
+            }
+        )+
+    };
+}
+
+impl_component!(f32, f64, i8, u8, i16, u16, i32, u32);
+
+impl Component for f16 {
+    const MAX: f64 = 1.0;
+
+    fn to_f64(self) -> f64 {
+        f16::to_f64(self)
+    }
+
+    fn from_f64(value: f64) -> Self {
+        f16::from_f64(value)
+    }
+
+    fn read_ne(bytes: &[u8]) -> Self {
+        f16::from_ne_bytes([bytes[0], bytes[1]])
+    }
+
+    fn write_ne(self, bytes: &mut [u8]) {
+        bytes[..2].copy_from_slice(&self.to_ne_bytes());
+    }
+}
+
+/// a value of `VertexAttribute`, a single component or an array of components
+trait Element: Copy {
+    type Component: Component;
+    const COMPONENTS: usize;
+
+    fn component(&self, index: usize) -> Self::Component;
+
+    fn from_components(f: impl FnMut(usize) -> Self::Component) -> Self;
+}
+
+macro_rules! impl_scalar_element {
+    ( $( $ty:ty ),+ $(,)? ) => {
+        $(
+            impl Element for $ty {
+                type Component = $ty;
+                const COMPONENTS: usize = 1;
+
+                fn component(&self, _: usize) -> $ty {
+                    *self
+                }
+
+                fn from_components(mut f: impl FnMut(usize) -> $ty) -> Self {
+                    f(0)
+                }
+            }
+        )+
+    };
+}
+
+impl_scalar_element!(f32, f64, i32, u32);
+
+impl<T: Component, const N: usize> Element for [T; N] {
+    type Component = T;
+    const COMPONENTS: usize = N;
+
+    fn component(&self, index: usize) -> T {
+        self[index]
+    }
+
+    fn from_components(f: impl FnMut(usize) -> T) -> Self {
+        std::array::from_fn(f)
+    }
+}
+
+fn decode<E: Element>(values: &[E], kind: ComponentKind) -> Vec<f64> {
+    let decode = |component: E::Component| {
+        let value = component.to_f64();
+        match kind {
+            ComponentKind::Float | ComponentKind::Int => value,
+            ComponentKind::Unorm => value / E::Component::MAX,
+            ComponentKind::Snorm => (value / E::Component::MAX).max(-1.0),
         }
+    };
+    values
+        .iter()
+        .flat_map(|value| (0..E::COMPONENTS).map(move |i| decode(value.component(i))))
+        .collect()
+}
+
+fn read_values<E: Element>(bytes: &[u8], offset: usize, stride: usize, count: usize) -> Vec<E> {
+    let size = std::mem::size_of::<E::Component>();
+    (0..count)
+        .map(|i| {
+            let start = offset + i * stride;
+            E::from_components(|c| E::Component::read_ne(&bytes[start + c * size..]))
+        })
+        .collect()
+}
+
+fn write_value<E: Element>(value: &E, bytes: &mut [u8]) {
+    let size = std::mem::size_of::<E::Component>();
+    for c in 0..E::COMPONENTS {
+        value.component(c).write_ne(&mut bytes[c * size..]);
     }
 }
 
+/// `values` are flattened with `components` for each value
+fn encode<E: Element>(values: &[f64], components: usize, kind: ComponentKind) -> Vec<E> {
+    let encode = |value: f64| {
+        let value = match kind {
+            ComponentKind::Float => value,
+            ComponentKind::Int => value.round(),
+            ComponentKind::Unorm => (value.clamp(0.0, 1.0) * E::Component::MAX).round(),
+            ComponentKind::Snorm => (value.clamp(-1.0, 1.0) * E::Component::MAX).round(),
+        };
+        E::Component::from_f64(value)
+    };
+    values
+        .chunks(components)
+        .map(|value| {
+            E::from_components(|i| match value.get(i) {
+                Some(component) => encode(*component),
+                None if i == 3 => encode(1.0),
+                None => encode(0.0),
+            })
+        })
+        .collect()
+}
+
 macro_rules! impl_vertex_attributes_ctor {
     ( $( ( $name:expr, $enum:ident ) ),+ $(,)? ) => {
         $(
@@ -85,10 +632,32 @@ impl VertexAttribute {
         (float2, Float2),
         (float3, Float3),
         (float4, Float4),
+        (double, Double),
+        (double2, Double2),
+        (double3, Double3),
+        (double4, Double4),
+        (half2, Half2),
+        (half4, Half4),
         (int, Int),
         (int2, Int2),
         (int3, Int3),
         (int4, Int4),
+        (uint, Uint),
+        (uint2, Uint2),
+        (uint3, Uint3),
+        (uint4, Uint4),
+        (uint8x2, Uint8x2),
+        (uint8x4, Uint8x4),
+        (uint16x2, Uint16x2),
+        (uint16x4, Uint16x4),
+        (unorm8x2, Unorm8x2),
+        (unorm8x4, Unorm8x4),
+        (snorm8x2, Snorm8x2),
+        (snorm8x4, Snorm8x4),
+        (unorm16x2, Unorm16x2),
+        (unorm16x4, Unorm16x4),
+        (snorm16x2, Snorm16x2),
+        (snorm16x4, Snorm16x4),
     }
 }
 
@@ -126,9 +695,31 @@ impl VertexAttribute {
         (float2, [f32; 2], Float2),
         (float3, [f32; 3], Float3),
         (float4, [f32; 4], Float4),
+        (double, f64, Double),
+        (double2, [f64; 2], Double2),
+        (double3, [f64; 3], Double3),
+        (double4, [f64; 4], Double4),
+        (half2, [f16; 2], Half2),
+        (half4, [f16; 4], Half4),
         (int, i32, Int),
         (int2, [i32; 2], Int2),
         (int3, [i32; 3], Int3),
         (int4, [i32; 4], Int4),
+        (uint, u32, Uint),
+        (uint2, [u32; 2], Uint2),
+        (uint3, [u32; 3], Uint3),
+        (uint4, [u32; 4], Uint4),
+        (uint8x2, [u8; 2], Uint8x2),
+        (uint8x4, [u8; 4], Uint8x4),
+        (uint16x2, [u16; 2], Uint16x2),
+        (uint16x4, [u16; 4], Uint16x4),
+        (unorm8x2, [u8; 2], Unorm8x2),
+        (unorm8x4, [u8; 4], Unorm8x4),
+        (snorm8x2, [i8; 2], Snorm8x2),
+        (snorm8x4, [i8; 4], Snorm8x4),
+        (unorm16x2, [u16; 2], Unorm16x2),
+        (unorm16x4, [u16; 4], Unorm16x4),
+        (snorm16x2, [i16; 2], Snorm16x2),
+        (snorm16x4, [i16; 4], Snorm16x4),
     }
 }
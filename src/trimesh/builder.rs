@@ -0,0 +1,315 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use super::{AttributeError, MeshIndices, PrimitiveTopology, TriMesh, VertexAttribute};
+
+/// problems of a `TriMesh` found by `TriMeshBuilder::build()` or `TriMesh::validate()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriMeshError {
+    /// attribute `name` has `len` values, but the mesh has `expected` vertices
+    AttributeLength {
+        name: Cow<'static, str>,
+        len: usize,
+        expected: usize,
+    },
+    /// `indices[position]` is `index`, which is not less than the number of vertices
+    /// and isn't the restart index of strips and fans
+    IndexOutOfRange {
+        position: usize,
+        index: usize,
+        num_vertices: usize,
+    },
+    /// number of indices is not a multiple of the primitive size of a list topology
+    IndexCount {
+        count: usize,
+        topology: PrimitiveTopology,
+    },
+    /// the mesh has no indices and the number of vertices is not a multiple of the primitive size
+    VertexCount {
+        count: usize,
+        topology: PrimitiveTopology,
+    },
+    /// a `VertexLayout` doesn't match the data given to `TriMesh::deinterleave()`
+    InvalidLayout(String),
+    /// `TriMesh::convert_topology()` can't convert primitives of `from` to `to`
+    UnsupportedConversion {
+        from: PrimitiveTopology,
+        to: PrimitiveTopology,
+    },
+    /// `submeshes[submesh]` ends at `end`, beyond the `len` indices (or vertices without indices),
+    /// or its count doesn't fit the topology
+    SubmeshRange {
+        submesh: usize,
+        end: usize,
+        len: usize,
+    },
+    /// meshes given to `TriMesh::merge()` have different topologies or attributes
+    MergeMismatch(String),
+    /// a vertex attribute needed by an operation is missing or has another format
+    Attribute(AttributeError),
+}
+
+impl std::fmt::Display for TriMeshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TriMeshError::AttributeLength {
+                name,
+                len,
+                expected,
+            } => write!(
+                f,
+                "vertex attribute '{}' has {} values but there are {} vertices",
+                name, len, expected
+            ),
+            TriMeshError::IndexOutOfRange {
+                position,
+                index,
+                num_vertices,
+            } => write!(
+                f,
+                "index {} at position {} is out of range, there are {} vertices",
+                index, position, num_vertices
+            ),
+            TriMeshError::IndexCount { count, topology } => write!(
+                f,
+                "number of indices {} doesn't fit topology {:?}",
+                count, topology
+            ),
+            TriMeshError::VertexCount { count, topology } => write!(
+                f,
+                "mesh has no indices and number of vertices {} doesn't fit topology {:?}",
+                count, topology
+            ),
+            TriMeshError::InvalidLayout(message) => write!(f, "invalid vertex layout, {}", message),
+            TriMeshError::UnsupportedConversion { from, to } => {
+                write!(f, "can't convert topology {:?} to {:?}", from, to)
+            }
+            TriMeshError::SubmeshRange { submesh, end, len } => write!(
+                f,
+                "submesh {} ending at {} doesn't fit {} indices or the topology",
+                submesh, end, len
+            ),
+            TriMeshError::MergeMismatch(message) => write!(f, "can't merge meshes, {}", message),
+            TriMeshError::Attribute(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for TriMeshError {}
+
+impl From<AttributeError> for TriMeshError {
+    fn from(err: AttributeError) -> Self {
+        TriMeshError::Attribute(err)
+    }
+}
+
+/// build a `TriMesh` whose attributes and indices are checked
+///
+/// indices are stored as `u16` if all vertices can be indexed with it, unless `u32_indices()` is called
+#[derive(Default)]
+pub struct TriMeshBuilder {
+    vertex_attributes: HashMap<Cow<'static, str>, VertexAttribute>,
+    indices: Option<Vec<u32>>,
+    topology: PrimitiveTopology,
+    u32_indices: bool,
+}
+
+impl TriMeshBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn attribute<Str, Values>(mut self, name: Str, values: Values) -> Self
+    where
+        Str: Into<Cow<'static, str>>,
+        Values: Into<VertexAttribute>,
+    {
+        self.vertex_attributes.insert(name.into(), values.into());
+        self
+    }
+
+    pub fn indices<Indices: IntoIterator<Item = u32>>(mut self, indices: Indices) -> Self {
+        self.indices = Some(indices.into_iter().collect());
+        self
+    }
+
+    /// `TriangleList` by default, restart index of strips and fans is `u32::MAX`
+    pub fn topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// keep indices as `u32` even if `u16` is enough
+    pub fn u32_indices(mut self) -> Self {
+        self.u32_indices = true;
+        self
+    }
+
+    pub fn build(self) -> Result<TriMesh, TriMeshError> {
+        let indices = self.indices.map(MeshIndices::U32);
+        let mut mesh = TriMesh::new(self.vertex_attributes, indices);
+        mesh.topology = self.topology;
+        // indices are checked before they are converted to `u16`
+        mesh.validate()?;
+        // `u16::MAX` is the restart index of `u16` indices
+        let max_vertices = u16::MAX as usize + !self.topology.has_restart() as usize;
+        if !self.u32_indices && mesh.num_vertices() <= max_vertices {
+            if let Some(MeshIndices::U32(indices)) = &mesh.indices {
+                let indices = indices.iter().map(|i| *i as u16).collect();
+                mesh.indices = Some(MeshIndices::U16(indices));
+            }
+        }
+        Ok(mesh)
+    }
+}
+
+impl TriMesh {
+    /// check that all attributes have the same length, and that indices and submeshes are in range
+    /// and fit the topology
+    ///
+    /// the number of vertices is the length of `TriMesh::POSITION`,
+    /// or of the first attribute by name if there is no position
+    pub fn validate(&self) -> Result<(), TriMeshError> {
+        let num_vertices = self.num_vertices();
+        let mut names = self.vertex_attributes.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            let len = self.vertex_attributes[name].len();
+            if len != num_vertices {
+                return Err(TriMeshError::AttributeLength {
+                    name: name.clone(),
+                    len,
+                    expected: num_vertices,
+                });
+            }
+        }
+
+        let topology = self.topology;
+        let list_size = topology.list_size().unwrap_or(1);
+        match &self.indices {
+            Some(indices) => {
+                let restart = topology.has_restart().then(|| indices.restart_index());
+                if let Some(position) = (0..indices.len()).find(|i| {
+                    let index = indices.get(*i);
+                    index >= num_vertices && Some(index) != restart
+                }) {
+                    return Err(TriMeshError::IndexOutOfRange {
+                        position,
+                        index: indices.get(position),
+                        num_vertices,
+                    });
+                }
+                if !indices.len().is_multiple_of(list_size) {
+                    return Err(TriMeshError::IndexCount {
+                        count: indices.len(),
+                        topology,
+                    });
+                }
+            }
+            None if !num_vertices.is_multiple_of(list_size) => {
+                return Err(TriMeshError::VertexCount {
+                    count: num_vertices,
+                    topology,
+                })
+            }
+            None => {}
+        }
+
+        self.validate_submeshes()
+    }
+
+    /// the part of `validate()` that checks submesh ranges, for operations that index by them
+    pub(crate) fn validate_submeshes(&self) -> Result<(), TriMeshError> {
+        let list_size = self.topology.list_size().unwrap_or(1);
+        let len = self
+            .indices
+            .as_ref()
+            .map_or_else(|| self.num_vertices(), MeshIndices::len);
+        for (i, submesh) in self.submeshes.iter().enumerate() {
+            let end = submesh.start.saturating_add(submesh.count);
+            if end > len || !submesh.count.is_multiple_of(list_size) {
+                return Err(TriMeshError::SubmeshRange {
+                    submesh: i,
+                    end,
+                    len,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::{PrimitiveTopology, Submesh};
+
+    fn triangle() -> TriMesh {
+        TriMeshBuilder::new()
+            .attribute(
+                TriMesh::POSITION,
+                vec![[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            )
+            .indices(vec![0, 1, 2])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn validate_rejects_invalid_meshes() {
+        assert_eq!(triangle().validate(), Ok(()));
+
+        let mut mesh = triangle();
+        mesh.set_attribute(TriMesh::TEXCOORD, vec![[0.0f32, 0.0]]);
+        assert!(matches!(
+            mesh.validate(),
+            Err(TriMeshError::AttributeLength {
+                len: 1,
+                expected: 3,
+                ..
+            })
+        ));
+
+        let mut mesh = triangle();
+        mesh.set_indices(Some(MeshIndices::U32(vec![0, 1, 3])));
+        assert_eq!(
+            mesh.validate(),
+            Err(TriMeshError::IndexOutOfRange {
+                position: 2,
+                index: 3,
+                num_vertices: 3,
+            })
+        );
+
+        let mut mesh = triangle();
+        mesh.set_indices(Some(MeshIndices::U32(vec![0, 1, 2, 0])));
+        assert_eq!(
+            mesh.validate(),
+            Err(TriMeshError::IndexCount {
+                count: 4,
+                topology: PrimitiveTopology::TriangleList,
+            })
+        );
+
+        let mut mesh = triangle();
+        mesh.set_indices(None);
+        mesh.set_topology(PrimitiveTopology::LineList);
+        assert_eq!(
+            mesh.validate(),
+            Err(TriMeshError::VertexCount {
+                count: 3,
+                topology: PrimitiveTopology::LineList,
+            })
+        );
+
+        let mut mesh = triangle();
+        mesh.set_submeshes(vec![Submesh::new(0, 3, None), Submesh::new(3, 3, None)]);
+        assert_eq!(
+            mesh.validate(),
+            Err(TriMeshError::SubmeshRange {
+                submesh: 1,
+                end: 6,
+                len: 3,
+            })
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use std::borrow::Cow;
+
+use super::{
+    AttributeError, MeshIndices, TriMesh, TriMeshError, VertexAttribute, VertexAttributeFormat,
+};
+
+/// an attribute in an interleaved vertex buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexLayoutAttribute {
+    pub name: Cow<'static, str>,
+    pub format: VertexAttributeFormat,
+    /// offset in bytes from the beginning of a vertex
+    pub offset: usize,
+}
+
+/// layout of an interleaved vertex buffer, which maps to vertex buffer layouts of wgpu or Vulkan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexLayout {
+    /// size of a vertex in bytes
+    pub stride: usize,
+    pub attributes: Vec<VertexLayoutAttribute>,
+}
+
+impl VertexLayout {
+    /// place attributes in the given order
+    ///
+    /// each offset is a multiple of the component size and of `min(4, size)` of the format,
+    /// and the stride is a multiple of 4 and of all these alignments
+    pub fn new<Str: Into<Cow<'static, str>>>(
+        attributes: impl IntoIterator<Item = (Str, VertexAttributeFormat)>,
+    ) -> Self {
+        let mut stride = 0;
+        let mut max_alignment = 4;
+        let attributes = attributes
+            .into_iter()
+            .map(|(name, format)| {
+                let alignment = format.component_size().max(format.size().min(4));
+                max_alignment = max_alignment.max(alignment);
+                let offset = align(stride, alignment);
+                stride = offset + format.size();
+                VertexLayoutAttribute {
+                    name: name.into(),
+                    format,
+                    offset,
+                }
+            })
+            .collect();
+        Self {
+            stride: align(stride, max_alignment),
+            attributes,
+        }
+    }
+}
+
+/// vertices of a `TriMesh` in a single buffer, described by `layout`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterleavedVertices {
+    /// vertex data in native endian, padding bytes are zeros
+    pub data: Vec<u8>,
+    pub layout: VertexLayout,
+}
+
+impl TriMesh {
+    /// write attributes `names` of all vertices into a single buffer in the given order,
+    /// see `VertexLayout::new()` for alignment
+    pub fn interleave(&self, names: &[&str]) -> Result<InterleavedVertices, AttributeError> {
+        let attributes = names
+            .iter()
+            .map(|name| {
+                self.vertex_attributes
+                    .get(*name)
+                    .ok_or_else(|| AttributeError::Missing(name.to_string().into()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let num_vertices = self.num_vertices();
+        if let Some((name, attribute)) = names
+            .iter()
+            .zip(&attributes)
+            .find(|(_, attribute)| attribute.len() != num_vertices)
+        {
+            return Err(AttributeError::Length {
+                name: name.to_string().into(),
+                len: attribute.len(),
+                expected: num_vertices,
+            });
+        }
+        let layout = VertexLayout::new(
+            names
+                .iter()
+                .zip(&attributes)
+                .map(|(name, attribute)| (name.to_string(), attribute.format())),
+        );
+
+        let mut data = vec![0; layout.stride * num_vertices];
+        for (attribute, layout_attribute) in attributes.iter().zip(&layout.attributes) {
+            for vertex in 0..num_vertices {
+                let start = vertex * layout.stride + layout_attribute.offset;
+                attribute.write_bytes(vertex, &mut data[start..]);
+            }
+        }
+        Ok(InterleavedVertices { data, layout })
+    }
+
+    /// inverse of `interleave()`, read all attributes of `layout` from `data`,
+    /// the mesh is checked by `validate()`
+    pub fn deinterleave(
+        data: &[u8],
+        layout: &VertexLayout,
+        indices: Option<MeshIndices>,
+    ) -> Result<TriMesh, TriMeshError> {
+        if layout.stride == 0 || !data.len().is_multiple_of(layout.stride) {
+            return Err(TriMeshError::InvalidLayout(format!(
+                "data of {} bytes is not a multiple of stride {}",
+                data.len(),
+                layout.stride
+            )));
+        }
+        if let Some(attribute) = layout
+            .attributes
+            .iter()
+            .find(|attribute| attribute.offset + attribute.format.size() > layout.stride)
+        {
+            return Err(TriMeshError::InvalidLayout(format!(
+                "attribute '{}' exceeds stride {}",
+                attribute.name, layout.stride
+            )));
+        }
+
+        let num_vertices = data.len() / layout.stride;
+        let vertex_attributes = layout
+            .attributes
+            .iter()
+            .map(|attribute| {
+                let values = VertexAttribute::from_bytes(
+                    attribute.format,
+                    data,
+                    attribute.offset,
+                    layout.stride,
+                    num_vertices,
+                );
+                (attribute.name.clone(), values)
+            })
+            .collect();
+        let mesh = TriMesh::new(vertex_attributes, indices);
+        mesh.validate()?;
+        Ok(mesh)
+    }
+}
+
+fn align(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::TriMeshBuilder;
+
+    fn mesh() -> TriMesh {
+        TriMeshBuilder::new()
+            .attribute(
+                TriMesh::POSITION,
+                vec![[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            )
+            .attribute(
+                TriMesh::TEXCOORD,
+                vec![[0.0f32, 0.0], [1.0, 0.0], [0.0, 1.0]],
+            )
+            .indices(vec![0, 1, 2])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mesh = mesh();
+        let vertices = mesh
+            .interleave(&[TriMesh::POSITION, TriMesh::TEXCOORD])
+            .unwrap();
+        assert_eq!(vertices.layout.stride, 20);
+        assert_eq!(vertices.layout.attributes[1].offset, 12);
+        let read = TriMesh::deinterleave(&vertices.data, &vertices.layout, mesh.indices().cloned())
+            .unwrap();
+        assert_eq!(
+            read.attribute_as::<[f32; 3]>(TriMesh::POSITION).unwrap(),
+            mesh.attribute_as::<[f32; 3]>(TriMesh::POSITION).unwrap()
+        );
+        assert_eq!(
+            read.attribute_as::<[f32; 2]>(TriMesh::TEXCOORD).unwrap(),
+            mesh.attribute_as::<[f32; 2]>(TriMesh::TEXCOORD).unwrap()
+        );
+    }
+
+    #[test]
+    fn short_attribute_is_an_error() {
+        let mut mesh = mesh();
+        mesh.set_attribute(TriMesh::TEXCOORD, vec![[0.0f32, 0.0]]);
+        assert!(matches!(
+            mesh.interleave(&[TriMesh::POSITION, TriMesh::TEXCOORD]),
+            Err(AttributeError::Length {
+                len: 1,
+                expected: 3,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,358 @@
+use std::ops::Range;
+
+use super::{triangles_of, TriMesh, TriMeshError};
+use crate::util::{add, cross, dot, length, normalize, scale, sub};
+
+/// meshlets of a `TriMesh` for mesh shaders, built by `TriMesh::build_meshlets()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Meshlets {
+    pub meshlets: Vec<Meshlet>,
+    /// vertex of the mesh of each local vertex, ranges of them are given by `Meshlet::vertices`
+    pub vertices: Vec<u32>,
+    /// local vertices of each triangle, ranges of them are given by `Meshlet::triangles`
+    pub triangles: Vec<[u8; 3]>,
+}
+
+/// a cluster of triangles using at most `max_vertices` vertices
+#[derive(Debug, Clone, PartialEq)]
+pub struct Meshlet {
+    /// range of `Meshlets::vertices`
+    pub vertices: Range<usize>,
+    /// range of `Meshlets::triangles`
+    pub triangles: Range<usize>,
+    /// index of the submesh whose triangles are in this meshlet, `None` if the mesh has no submeshes
+    pub submesh: Option<usize>,
+    pub bounds: MeshletBounds,
+}
+
+/// culling data of a meshlet
+///
+/// a meshlet can be culled by the view frustum with its bounding sphere, and is back-facing for
+/// a camera at `eye` if `dot(normalize(cone_apex - eye), cone_axis) >= cone_cutoff`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshletBounds {
+    pub center: [f32; 3],
+    pub radius: f32,
+    pub cone_apex: [f32; 3],
+    pub cone_axis: [f32; 3],
+    /// sine of the half angle of the normal cone, `1` if the cone can't be used for culling
+    pub cone_cutoff: f32,
+}
+
+/// number of triangles after the first triangle that isn't used yet that are searched
+/// when a meshlet has no more adjacent triangles
+const SEARCH_WINDOW: usize = 64;
+
+impl TriMesh {
+    /// partition triangles into meshlets with at most `max_vertices` vertices and `max_triangles`
+    /// triangles, meshlets don't cross submeshes
+    ///
+    /// triangles sharing vertices with a meshlet are added greedily, preferring those that add
+    /// fewer vertices and are closer to the meshlet; `cone_weight` in `[0, 1]` prefers triangles
+    /// facing the same way for tighter normal cones, e.g. `0.25`
+    ///
+    /// return an error if `POSITION` is missing or submeshes are out of range
+    ///
+    /// # Panics
+    ///
+    /// if `max_vertices` is not in `3..=256` or `max_triangles` is `0`
+    pub fn build_meshlets(
+        &self,
+        max_vertices: usize,
+        max_triangles: usize,
+        cone_weight: f32,
+    ) -> Result<Meshlets, TriMeshError> {
+        assert!(
+            (3..=256).contains(&max_vertices),
+            "max_vertices must be in 3..=256"
+        );
+        assert!(max_triangles > 0, "max_triangles must be positive");
+
+        self.validate_submeshes()?;
+        let positions = self.attribute_as::<[f32; 3]>(Self::POSITION)?;
+        let mut builder = MeshletBuilder {
+            positions,
+            max_vertices,
+            max_triangles,
+            cone_weight: cone_weight.clamp(0.0, 1.0),
+            local: vec![None; positions.len()],
+            result: Meshlets::default(),
+        };
+        if self.submeshes.is_empty() {
+            builder.build(&self.triangles(), None);
+        } else {
+            let index_list = self.index_list();
+            for (i, submesh) in self.submeshes.iter().enumerate() {
+                let part = &index_list[submesh.range()];
+                builder.build(&triangles_of(part, self.topology), Some(i));
+            }
+        }
+        Ok(builder.result)
+    }
+}
+
+struct MeshletBuilder<'a> {
+    positions: &'a [[f32; 3]],
+    max_vertices: usize,
+    max_triangles: usize,
+    cone_weight: f32,
+    /// local vertex of each vertex in the current meshlet
+    local: Vec<Option<u8>>,
+    result: Meshlets,
+}
+
+impl MeshletBuilder<'_> {
+    fn build(&mut self, triangles: &[[usize; 3]], submesh: Option<usize>) {
+        let num_triangles = triangles.len();
+        let mut adjacent = vec![vec![]; self.positions.len()];
+        for (t, tri) in triangles.iter().enumerate() {
+            for v in tri {
+                adjacent[*v].push(t);
+            }
+        }
+
+        let geometry = triangles
+            .iter()
+            .map(|tri| {
+                let p = tri.map(|v| self.positions[v]);
+                let normal = cross(sub(p[1], p[0]), sub(p[2], p[0]));
+                let centroid = scale(1.0 / 3.0, add(add(p[0], p[1]), p[2]));
+                (centroid, normalize(normal), 0.5 * length(normal))
+            })
+            .collect::<Vec<_>>();
+        // expected radius of a full meshlet, to balance distance against normal spread
+        let average_area = geometry.iter().map(|g| g.2).sum::<f32>() / num_triangles.max(1) as f32;
+        let expected_radius = (average_area * self.max_triangles as f32).sqrt() * 0.5;
+
+        let mut emitted = vec![false; num_triangles];
+        let mut cursor = 0;
+        let mut meshlet = vec![];
+        let mut candidates: Vec<usize> = vec![];
+        let mut centroid_sum = [0.0; 3];
+        let mut normal_sum = [0.0; 3];
+        loop {
+            while cursor < num_triangles && emitted[cursor] {
+                cursor += 1;
+            }
+            if cursor == num_triangles {
+                break;
+            }
+
+            // number of vertices not in the meshlet yet, and cost of adding a triangle
+            let count = meshlet.len().max(1) as f32;
+            let center = scale(1.0 / count, centroid_sum);
+            let axis = normalize(normal_sum);
+            let cost = |t: usize| {
+                let extra = triangles[t]
+                    .iter()
+                    .filter(|v| self.local[**v].is_none())
+                    .count();
+                let (centroid, normal, _) = geometry[t];
+                let distance = length(sub(centroid, center));
+                let spread = 1.0 - dot(normal, axis) * self.cone_weight;
+                let cost = (1.0
+                    + distance / expected_radius.max(f32::EPSILON) * (1.0 - self.cone_weight))
+                    * spread.max(1e-3);
+                (extra, cost)
+            };
+            let num_vertices = self.result.vertices.len() - self.vertex_start();
+            let fits = |(extra, _): (usize, f32)| num_vertices + extra <= self.max_vertices;
+            let best = if meshlet.is_empty() {
+                Some(cursor)
+            } else {
+                candidates.retain(|t| !emitted[*t]);
+                let best = min_cost(candidates.iter().copied(), cost, fits);
+                // continue with nearby triangles when there are no adjacent ones
+                best.or_else(|| {
+                    let window = (cursor..num_triangles)
+                        .filter(|t| !emitted[*t])
+                        .take(SEARCH_WINDOW);
+                    candidates
+                        .is_empty()
+                        .then(|| min_cost(window, cost, fits))?
+                })
+            };
+
+            let t = match best {
+                Some(t) if meshlet.len() < self.max_triangles => t,
+                _ => {
+                    self.finish(&meshlet, triangles, submesh);
+                    meshlet.clear();
+                    candidates.clear();
+                    centroid_sum = [0.0; 3];
+                    normal_sum = [0.0; 3];
+                    continue;
+                }
+            };
+
+            emitted[t] = true;
+            meshlet.push(t);
+            centroid_sum = add(centroid_sum, geometry[t].0);
+            normal_sum = add(normal_sum, geometry[t].1);
+            for &v in &triangles[t] {
+                if self.local[v].is_none() {
+                    let local = self.result.vertices.len() - self.vertex_start();
+                    self.local[v] = Some(local as u8);
+                    self.result.vertices.push(v as u32);
+                    candidates.extend(adjacent[v].iter().filter(|t| !emitted[**t]));
+                }
+            }
+        }
+        if !meshlet.is_empty() {
+            self.finish(&meshlet, triangles, submesh);
+        }
+    }
+
+    /// start of the vertices of the current meshlet
+    fn vertex_start(&self) -> usize {
+        self.result.meshlets.last().map_or(0, |m| m.vertices.end)
+    }
+
+    fn finish(&mut self, meshlet: &[usize], triangles: &[[usize; 3]], submesh: Option<usize>) {
+        let vertices = self.vertex_start()..self.result.vertices.len();
+        let triangle_start = self.result.triangles.len();
+        for &t in meshlet {
+            let tri = triangles[t].map(|v| self.local[v].unwrap());
+            self.result.triangles.push(tri);
+        }
+        for &v in &self.result.vertices[vertices.clone()] {
+            self.local[v as usize] = None;
+        }
+
+        let bounds = self.bounds(meshlet.iter().map(|t| triangles[*t]));
+        self.result.meshlets.push(Meshlet {
+            vertices,
+            triangles: triangle_start..self.result.triangles.len(),
+            submesh,
+            bounds,
+        });
+    }
+
+    fn bounds<Triangles: Iterator<Item = [usize; 3]> + Clone>(
+        &self,
+        triangles: Triangles,
+    ) -> MeshletBounds {
+        // center of the bounding box, which is close to the minimal sphere of small clusters
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for p in triangles.clone().flatten().map(|v| self.positions[v]) {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        let center = scale(0.5, add(min, max));
+        let radius = triangles
+            .clone()
+            .flatten()
+            .map(|v| length(sub(self.positions[v], center)))
+            .fold(0.0, f32::max);
+
+        let faces = triangles
+            .map(|tri| {
+                let p = tri.map(|v| self.positions[v]);
+                (p[0], normalize(cross(sub(p[1], p[0]), sub(p[2], p[0]))))
+            })
+            .filter(|(_, normal)| *normal != [0.0; 3])
+            .collect::<Vec<_>>();
+        let axis = normalize(faces.iter().fold([0.0; 3], |sum, f| add(sum, f.1)));
+        let min_dot = faces
+            .iter()
+            .map(|(_, normal)| dot(*normal, axis))
+            .fold(1.0, f32::min);
+        // cones wider than a hemisphere would never cull
+        if faces.is_empty() || min_dot <= 0.1 {
+            return MeshletBounds {
+                center,
+                radius,
+                cone_apex: center,
+                cone_axis: axis,
+                cone_cutoff: 1.0,
+            };
+        }
+
+        // move the apex back along the axis until it is behind all triangle planes
+        let max_t = faces
+            .iter()
+            .map(|(p, normal)| dot(sub(*p, center), *normal) / dot(axis, *normal))
+            .fold(0.0, f32::max);
+        MeshletBounds {
+            center,
+            radius,
+            cone_apex: sub(center, scale(max_t, axis)),
+            cone_axis: axis,
+            cone_cutoff: (1.0 - min_dot * min_dot).sqrt(),
+        }
+    }
+}
+
+/// triangle with the fewest new vertices and then the lowest cost among those that fit
+fn min_cost<Cost, Fits>(
+    triangles: impl Iterator<Item = usize>,
+    cost: Cost,
+    fits: Fits,
+) -> Option<usize>
+where
+    Cost: Fn(usize) -> (usize, f32),
+    Fits: Fn((usize, f32)) -> bool,
+{
+    triangles
+        .map(|t| (t, cost(t)))
+        .filter(|(_, cost)| fits(*cost))
+        .min_by(|a, b| a.1 .0.cmp(&b.1 .0).then(a.1 .1.total_cmp(&b.1 .1)))
+        .map(|(t, _)| t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::{Submesh, TriMeshBuilder};
+
+    /// `n` by `n` quads split into triangles
+    fn grid(n: u32) -> TriMesh {
+        let positions = (0..=n)
+            .flat_map(|j| (0..=n).map(move |i| [i as f32, j as f32, 0.0]))
+            .collect::<Vec<_>>();
+        let mut indices = vec![];
+        for j in 0..n {
+            for i in 0..n {
+                let v = j * (n + 1) + i;
+                indices.extend([v, v + 1, v + n + 2, v, v + n + 2, v + n + 1]);
+            }
+        }
+        TriMeshBuilder::new()
+            .attribute(TriMesh::POSITION, positions)
+            .indices(indices)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn meshlets_respect_limits() {
+        let mut mesh = grid(10);
+        mesh.set_submeshes(vec![
+            Submesh::new(0, 90, Some(0)),
+            Submesh::new(90, 510, Some(1)),
+        ]);
+        let meshlets = mesh.build_meshlets(16, 10, 0.25).unwrap();
+
+        let mut triangles = vec![];
+        for meshlet in &meshlets.meshlets {
+            assert!(meshlet.vertices.len() <= 16);
+            assert!(!meshlet.triangles.is_empty() && meshlet.triangles.len() <= 10);
+            let vertices = &meshlets.vertices[meshlet.vertices.clone()];
+            for tri in &meshlets.triangles[meshlet.triangles.clone()] {
+                let tri = tri.map(|v| vertices[v as usize] as usize);
+                // meshlets don't cross submeshes
+                let t = mesh.triangles().iter().position(|t| *t == tri).unwrap();
+                let submesh = if t < 30 { 0 } else { 1 };
+                assert_eq!(meshlet.submesh, Some(submesh));
+                triangles.push(tri);
+            }
+        }
+        let mut expected = mesh.triangles();
+        expected.sort_unstable();
+        triangles.sort_unstable();
+        assert_eq!(triangles, expected);
+    }
+}
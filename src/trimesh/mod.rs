@@ -1,5 +1,21 @@
 mod attribute;
+mod builder;
+mod interleave;
+mod meshlet;
+mod normal;
+mod optimize;
+mod submesh;
+mod tangent;
+mod topology;
+#[allow(clippy::module_inception)]
 mod trimesh;
 
 pub use attribute::*;
+pub use builder::*;
+pub use interleave::*;
+pub use meshlet::*;
+pub use normal::*;
+pub use optimize::*;
+pub use submesh::*;
+pub use topology::*;
 pub use trimesh::*;
@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+
+use super::{AttributeError, PrimitiveTopology, TriMesh, VertexAttribute};
+use crate::util::{add, cross, dot, find, length, normalize, scale, sub, union};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// each face contributes equally
+    Uniform,
+    /// weighted by area of the face
+    Area,
+    /// weighted by angle of the face at the vertex
+    Angle,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalOptions {
+    pub weighting: NormalWeighting,
+    /// faces whose normals differ by more than this angle (in radians) are not smoothed together,
+    /// `None` to smooth across all edges
+    pub crease_angle: Option<f32>,
+    /// smoothing group bitmask of each triangle, two faces are smoothed together only when their masks
+    /// share a bit, so that `0` gives a flat face
+    pub smoothing_groups: Option<Vec<u32>>,
+}
+
+impl Default for NormalOptions {
+    fn default() -> Self {
+        Self {
+            weighting: NormalWeighting::Angle,
+            crease_angle: None,
+            smoothing_groups: None,
+        }
+    }
+}
+
+/// problems found by `TriMesh::compute_normals()`
+#[derive(Debug)]
+pub enum NormalError {
+    Attribute(AttributeError),
+    /// `NormalOptions::smoothing_groups` has `len` masks, but the mesh has `triangles` triangles
+    SmoothingGroups {
+        len: usize,
+        triangles: usize,
+    },
+    /// normals can only be computed for triangles
+    Topology(PrimitiveTopology),
+}
+
+impl std::fmt::Display for NormalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NormalError::Attribute(err) => write!(f, "{}", err),
+            NormalError::SmoothingGroups { len, triangles } => write!(
+                f,
+                "{} smoothing groups are given but there are {} triangles",
+                len, triangles
+            ),
+            NormalError::Topology(topology) => {
+                write!(f, "can't compute normals of topology {:?}", topology)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NormalError {}
+
+impl From<AttributeError> for NormalError {
+    fn from(err: AttributeError) -> Self {
+        NormalError::Attribute(err)
+    }
+}
+
+impl TriMesh {
+    /// compute `TriMesh::NORMAL` from `POSITION`
+    ///
+    /// corners at the same position are smoothed together when they are connected by edges that are
+    /// neither creases nor between different smoothing groups, vertices are split where normals differ
+    pub fn compute_normals(&mut self, options: &NormalOptions) -> Result<(), NormalError> {
+        if !self.topology.is_triangles() {
+            return Err(NormalError::Topology(self.topology));
+        }
+        let normals = {
+            let positions = self.attribute_as::<[f32; 3]>(Self::POSITION)?;
+            let triangles = self.triangles();
+            if let Some(groups) = &options.smoothing_groups {
+                if groups.len() != triangles.len() {
+                    return Err(NormalError::SmoothingGroups {
+                        len: groups.len(),
+                        triangles: triangles.len(),
+                    });
+                }
+            }
+            corner_normals(positions, &triangles, options)
+        };
+
+        let keys = normals
+            .iter()
+            .map(|normal| normal.map(f32::to_bits))
+            .collect::<Vec<_>>();
+        let corners = self.split_vertices(&keys);
+        let mut result = vec![[0.0, 0.0, 1.0]; self.num_vertices()];
+        for (vertex, normal) in corners.into_iter().zip(normals) {
+            result[vertex] = normal;
+        }
+        self.set_attribute(Self::NORMAL, VertexAttribute::Float3(result));
+
+        Ok(())
+    }
+}
+
+fn corner_normals(
+    positions: &[[f32; 3]],
+    triangles: &[[usize; 3]],
+    options: &NormalOptions,
+) -> Vec<[f32; 3]> {
+    // vertices at the same position are treated as one
+    let mut first = HashMap::new();
+    let welded = positions
+        .iter()
+        .enumerate()
+        .map(|(v, p)| *first.entry(p.map(|x| (x + 0.0).to_bits())).or_insert(v))
+        .collect::<Vec<_>>();
+
+    let face_normals = triangles
+        .iter()
+        .map(|tri| {
+            let n = cross(
+                sub(positions[tri[1]], positions[tri[0]]),
+                sub(positions[tri[2]], positions[tri[0]]),
+            );
+            // length of the cross product is twice the area
+            (normalize(n), 0.5 * length(n))
+        })
+        .collect::<Vec<_>>();
+
+    let min_cos = options.crease_angle.map(f32::cos);
+    let smooth = |a: usize, b: usize| {
+        let same_group = options
+            .smoothing_groups
+            .as_ref()
+            .is_none_or(|groups| groups[a] & groups[b] != 0);
+        let below_crease =
+            min_cos.is_none_or(|min_cos| dot(face_normals[a].0, face_normals[b].0) >= min_cos);
+        same_group && below_crease
+    };
+
+    // join corners of two faces at both ends of their shared edge
+    let mut edges: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+    for (t, tri) in triangles.iter().enumerate() {
+        for i in 0..3 {
+            let a = welded[tri[i]];
+            let b = welded[tri[(i + 1) % 3]];
+            if a != b {
+                edges.entry((a.min(b), a.max(b))).or_default().push((t, i));
+            }
+        }
+    }
+    let mut parents = (0..3 * triangles.len()).collect::<Vec<_>>();
+    for faces in edges.values() {
+        for (k, &(t0, i0)) in faces.iter().enumerate() {
+            for &(t1, _) in &faces[k + 1..] {
+                if t0 == t1 || !smooth(t0, t1) {
+                    continue;
+                }
+                for c0 in [i0, (i0 + 1) % 3] {
+                    let c1 = (0..3)
+                        .find(|c| welded[triangles[t1][*c]] == welded[triangles[t0][c0]])
+                        .unwrap();
+                    union(&mut parents, 3 * t0 + c0, 3 * t1 + c1);
+                }
+            }
+        }
+    }
+
+    let mut sums = vec![[0.0f32; 3]; 3 * triangles.len()];
+    for (t, tri) in triangles.iter().enumerate() {
+        let (normal, area) = face_normals[t];
+        for i in 0..3 {
+            let weight = match options.weighting {
+                NormalWeighting::Uniform => 1.0,
+                NormalWeighting::Area => area,
+                NormalWeighting::Angle => {
+                    let p = positions[tri[i]];
+                    let e0 = normalize(sub(positions[tri[(i + 1) % 3]], p));
+                    let e1 = normalize(sub(positions[tri[(i + 2) % 3]], p));
+                    dot(e0, e1).clamp(-1.0, 1.0).acos()
+                }
+            };
+            let root = find(&mut parents, 3 * t + i);
+            sums[root] = add(sums[root], scale(weight, normal));
+        }
+    }
+
+    (0..3 * triangles.len())
+        .map(|corner| {
+            let normal = normalize(sums[find(&mut parents, corner)]);
+            if normal == [0.0; 3] {
+                // isolated degenerate faces
+                face_normals[corner / 3].0
+            } else {
+                normal
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::TriMeshBuilder;
+
+    fn quad() -> TriMesh {
+        TriMeshBuilder::new()
+            .attribute(
+                TriMesh::POSITION,
+                vec![
+                    [0.0f32, 0.0, 0.0],
+                    [1.0, 0.0, 0.0],
+                    [1.0, 1.0, 0.0],
+                    [0.0, 1.0, 0.0],
+                ],
+            )
+            .indices(vec![0, 1, 2, 0, 2, 3])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn flat_quad() {
+        let mut mesh = quad();
+        mesh.compute_normals(&NormalOptions::default()).unwrap();
+        assert_eq!(mesh.num_vertices(), 4);
+        let normals = mesh.attribute_as::<[f32; 3]>(TriMesh::NORMAL).unwrap();
+        assert!(normals.iter().all(|n| *n == [0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn invalid_input() {
+        let options = NormalOptions {
+            smoothing_groups: Some(vec![1]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            quad().compute_normals(&options),
+            Err(NormalError::SmoothingGroups {
+                len: 1,
+                triangles: 2
+            })
+        ));
+
+        let mut lines = quad();
+        lines.set_topology(PrimitiveTopology::LineList);
+        assert!(matches!(
+            lines.compute_normals(&NormalOptions::default()),
+            Err(NormalError::Topology(PrimitiveTopology::LineList))
+        ));
+    }
+
+    fn cube() -> TriMesh {
+        let positions = (0..8)
+            .map(|v| [(v & 1) as f32, ((v >> 1) & 1) as f32, ((v >> 2) & 1) as f32])
+            .collect::<Vec<_>>();
+        #[rustfmt::skip]
+        let indices = vec![
+            0, 2, 1, 1, 2, 3, // -z
+            4, 5, 6, 5, 7, 6, // +z
+            0, 1, 4, 1, 5, 4, // -y
+            2, 6, 3, 3, 6, 7, // +y
+            0, 4, 2, 2, 4, 6, // -x
+            1, 3, 5, 3, 7, 5, // +x
+        ];
+        TriMeshBuilder::new()
+            .attribute(TriMesh::POSITION, positions)
+            .indices(indices)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn cube_creases_and_smoothing_groups() {
+        let mut mesh = cube();
+        let options = NormalOptions {
+            crease_angle: Some(60f32.to_radians()),
+            ..Default::default()
+        };
+        mesh.compute_normals(&options).unwrap();
+        assert_eq!(mesh.num_vertices(), 24);
+        let positions = mesh.attribute_as::<[f32; 3]>(TriMesh::POSITION).unwrap();
+        let normals = mesh.attribute_as::<[f32; 3]>(TriMesh::NORMAL).unwrap();
+        for tri in mesh.triangles() {
+            let n = normalize(cross(
+                sub(positions[tri[1]], positions[tri[0]]),
+                sub(positions[tri[2]], positions[tri[0]]),
+            ));
+            // outward axis normal of a flat cube face
+            assert!(tri.iter().all(|v| normals[*v] == n));
+            assert_eq!(n.iter().filter(|x| **x != 0.0).count(), 1);
+        }
+
+        let mut mesh = cube();
+        let options = NormalOptions {
+            smoothing_groups: Some(vec![1; 12]),
+            ..Default::default()
+        };
+        mesh.compute_normals(&options).unwrap();
+        assert_eq!(mesh.num_vertices(), 8);
+        let positions = mesh.attribute_as::<[f32; 3]>(TriMesh::POSITION).unwrap();
+        let normals = mesh.attribute_as::<[f32; 3]>(TriMesh::NORMAL).unwrap();
+        for (p, n) in positions.iter().zip(normals) {
+            // diagonal pointing away from the center
+            let expected = normalize(p.map(|x| x - 0.5));
+            assert!((0..3).all(|i| (n[i] - expected[i]).abs() < 1e-5));
+        }
+    }
+}
@@ -0,0 +1,555 @@
+use std::ops::Range;
+
+use super::{MeshIndices, PrimitiveTopology, Submesh, TriMesh, TriMeshError, RESTART};
+use crate::util::{add, cross, dot, length, normalize, scale, sub};
+
+/// algorithm of `TriMesh::optimize_vertex_cache()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VertexCacheOptimizer {
+    /// Tom Forsyth's linear-speed vertex cache optimisation, tuned for an LRU cache of 32 vertices
+    /// and good for most GPUs
+    Forsyth,
+    /// Tipsify of Sander et al., faster than `Forsyth` and tuned for a FIFO cache of `cache_size`
+    Tipsify { cache_size: usize },
+}
+
+/// vertex transforms of drawing the triangles of a mesh with a FIFO post-transform cache
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VertexCacheStatistics {
+    pub vertices_transformed: usize,
+    /// average cache miss ratio, transformed vertices per triangle, between 0.5 and 3
+    pub acmr: f32,
+    /// average transform to vertex ratio, transformed vertices per vertex, 1 is the best
+    pub atvr: f32,
+}
+
+/// cache size used to find clusters of `TriMesh::optimize_overdraw()`
+const OVERDRAW_CACHE_SIZE: usize = 16;
+
+impl TriMesh {
+    /// reorder triangles of each submesh so that vertices are reused while they are
+    /// in the post-transform cache
+    ///
+    /// strips and fans are converted to triangle lists, points and lines are not changed,
+    /// indices are generated if there are none, submeshes must be in range
+    pub fn optimize_vertex_cache(
+        &mut self,
+        optimizer: VertexCacheOptimizer,
+    ) -> Result<(), TriMeshError> {
+        self.reorder_triangles(|part| {
+            let (local, num_vertices) = local_vertices(part);
+            match optimizer {
+                VertexCacheOptimizer::Forsyth => forsyth_order(&local, num_vertices),
+                VertexCacheOptimizer::Tipsify { cache_size } => {
+                    tipsify_order(&local, num_vertices, cache_size)
+                }
+            }
+        })
+    }
+
+    /// reorder clusters of triangles of each submesh so that triangles facing outwards are drawn
+    /// first, which reduces overdraw with depth testing
+    ///
+    /// triangles should be optimized with `optimize_vertex_cache()` first, clusters are split
+    /// while their ACMR is at most `threshold` times the ACMR of larger clusters, so that `1.05`
+    /// allows 5% more cache misses
+    pub fn optimize_overdraw(&mut self, threshold: f32) -> Result<(), TriMeshError> {
+        let positions = self.attribute_as::<[f32; 3]>(Self::POSITION)?.to_vec();
+        self.reorder_triangles(|part| overdraw_order(part, &positions, threshold))
+    }
+
+    /// reorder vertices in order of their first use by indices and remove unused vertices,
+    /// all vertex attributes are remapped
+    ///
+    /// return the new vertex of each old vertex, `None` for removed vertices,
+    /// meshes without indices are not changed
+    pub fn optimize_vertex_fetch(&mut self) -> Vec<Option<usize>> {
+        let num_vertices = self.num_vertices();
+        if self.indices.is_none() {
+            return (0..num_vertices).map(Some).collect();
+        }
+
+        let mut indices = self.index_list();
+        let mut remap = vec![None; num_vertices];
+        let mut sources = vec![];
+        for index in indices.iter_mut().filter(|index| **index != RESTART) {
+            *index = *remap[*index].get_or_insert_with(|| {
+                sources.push(*index);
+                sources.len() - 1
+            });
+        }
+
+        for attribute in self.vertex_attributes.values_mut() {
+            *attribute = attribute.gather(&sources);
+        }
+        let prefer_u16 = !matches!(self.indices, Some(MeshIndices::U32(_)));
+        self.set_index_list(&indices, self.topology, prefer_u16);
+        remap
+    }
+
+    /// simulate drawing `triangles()` with a FIFO cache of `cache_size` vertices
+    pub fn vertex_cache_statistics(&self, cache_size: usize) -> VertexCacheStatistics {
+        let triangles = self.triangles();
+        let num_vertices = self.num_vertices();
+        let mut cache = FifoCache::new(num_vertices, cache_size);
+        let vertices_transformed = triangles.iter().map(|tri| cache.add(tri)).sum::<usize>();
+        let ratio = |count: usize| {
+            if count == 0 {
+                0.0
+            } else {
+                vertices_transformed as f32 / count as f32
+            }
+        };
+        VertexCacheStatistics {
+            vertices_transformed,
+            acmr: ratio(triangles.len()),
+            atvr: ratio(num_vertices),
+        }
+    }
+
+    /// replace the triangles of each submesh (or the whole mesh) with the triangles at positions
+    /// given by `order`
+    fn reorder_triangles<Order: FnMut(&[usize]) -> Vec<usize>>(
+        &mut self,
+        mut order: Order,
+    ) -> Result<(), TriMeshError> {
+        if !self.topology.is_triangles() {
+            return Ok(());
+        }
+        self.validate_submeshes()?;
+        self.convert_topology(PrimitiveTopology::TriangleList)?;
+
+        let mut indices = self.index_list();
+        let parts: Vec<Range<usize>> = if self.submeshes.is_empty() {
+            std::iter::once(0..indices.len()).collect()
+        } else {
+            self.submeshes.iter().map(Submesh::range).collect()
+        };
+        for range in parts {
+            let part = &indices[range.clone()];
+            let reordered = order(part)
+                .into_iter()
+                .flat_map(|t| part[3 * t..3 * t + 3].to_vec())
+                .collect::<Vec<_>>();
+            indices[range].copy_from_slice(&reordered);
+        }
+
+        let prefer_u16 = !matches!(self.indices, Some(MeshIndices::U32(_)));
+        self.set_index_list(&indices, self.topology, prefer_u16);
+        Ok(())
+    }
+}
+
+/// renumber vertices of `indices` from 0, return new indices and number of vertices
+fn local_vertices(indices: &[usize]) -> (Vec<usize>, usize) {
+    let mut local = std::collections::HashMap::new();
+    let indices = indices
+        .iter()
+        .map(|index| {
+            let next = local.len();
+            *local.entry(*index).or_insert(next)
+        })
+        .collect();
+    (indices, local.len())
+}
+
+/// triangles using each vertex
+fn vertex_triangles(indices: &[usize], num_vertices: usize) -> Vec<Vec<usize>> {
+    let mut triangles = vec![vec![]; num_vertices];
+    for (t, tri) in indices.chunks_exact(3).enumerate() {
+        for v in tri {
+            triangles[*v].push(t);
+        }
+    }
+    triangles
+}
+
+const FORSYTH_CACHE_SIZE: usize = 32;
+
+fn forsyth_score(cache_position: Option<usize>, remaining: usize) -> f32 {
+    if remaining == 0 {
+        return -1.0;
+    }
+    let cache_score = match cache_position {
+        // vertices of the last triangle get a fixed score, so that it isn't reused immediately
+        Some(position) if position < 3 => 0.75,
+        Some(position) => {
+            let scaled = (position - 3) as f32 / (FORSYTH_CACHE_SIZE - 3) as f32;
+            (1.0 - scaled).powf(1.5)
+        }
+        None => 0.0,
+    };
+    // vertices with few remaining triangles are preferred to avoid leaving them alone
+    cache_score + 2.0 * (remaining as f32).powf(-0.5)
+}
+
+fn forsyth_order(indices: &[usize], num_vertices: usize) -> Vec<usize> {
+    let num_triangles = indices.len() / 3;
+    let mut remaining = vertex_triangles(indices, num_vertices);
+    let mut cache_position = vec![None; num_vertices];
+    let mut vertex_scores = remaining
+        .iter()
+        .map(|triangles| forsyth_score(None, triangles.len()))
+        .collect::<Vec<_>>();
+    let triangle_score = |t: usize, vertex_scores: &[f32]| {
+        indices[3 * t..3 * t + 3]
+            .iter()
+            .map(|v| vertex_scores[*v])
+            .sum::<f32>()
+    };
+
+    let mut emitted = vec![false; num_triangles];
+    let mut order = Vec::with_capacity(num_triangles);
+    let mut cache: Vec<usize> = vec![];
+    let mut cursor = 0;
+    let mut best = (0..num_triangles).max_by(|a, b| {
+        let a = triangle_score(*a, &vertex_scores);
+        let b = triangle_score(*b, &vertex_scores);
+        a.total_cmp(&b)
+    });
+    while let Some(t) = best {
+        emitted[t] = true;
+        order.push(t);
+        let tri = &indices[3 * t..3 * t + 3];
+        for v in tri {
+            remaining[*v].retain(|other| *other != t);
+        }
+
+        // vertices of the triangle move to the front of the LRU cache
+        let mut new_cache = Vec::with_capacity(cache.len() + 3);
+        for v in tri.iter().chain(&cache) {
+            if !new_cache.contains(v) {
+                new_cache.push(*v);
+            }
+        }
+        for v in new_cache.drain(FORSYTH_CACHE_SIZE.min(new_cache.len())..) {
+            cache_position[v] = None;
+            vertex_scores[v] = forsyth_score(None, remaining[v].len());
+        }
+        for (position, v) in new_cache.iter().enumerate() {
+            cache_position[*v] = Some(position);
+            vertex_scores[*v] = forsyth_score(Some(position), remaining[*v].len());
+        }
+        cache = new_cache;
+
+        best = cache
+            .iter()
+            .flat_map(|v| remaining[*v].iter().copied())
+            .map(|t| (t, triangle_score(t, &vertex_scores)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(t, _)| t);
+        if best.is_none() {
+            // dead end, continue with the next triangle that isn't drawn
+            while cursor < num_triangles && emitted[cursor] {
+                cursor += 1;
+            }
+            best = (cursor < num_triangles).then_some(cursor);
+        }
+    }
+    order
+}
+
+fn tipsify_order(indices: &[usize], num_vertices: usize, cache_size: usize) -> Vec<usize> {
+    let num_triangles = indices.len() / 3;
+    let adjacent = vertex_triangles(indices, num_vertices);
+    let mut live = adjacent.iter().map(Vec::len).collect::<Vec<_>>();
+    let mut cache_time = vec![0; num_vertices];
+    let mut time = cache_size + 1;
+    let mut dead_end = vec![];
+    let mut emitted = vec![false; num_triangles];
+    let mut order = Vec::with_capacity(num_triangles);
+    let mut cursor = 0;
+
+    let mut fanning = (num_vertices > 0).then_some(0);
+    while let Some(f) = fanning {
+        let mut candidates = vec![];
+        for &t in &adjacent[f] {
+            if emitted[t] {
+                continue;
+            }
+            emitted[t] = true;
+            order.push(t);
+            for &v in &indices[3 * t..3 * t + 3] {
+                dead_end.push(v);
+                candidates.push(v);
+                live[v] -= 1;
+                if time - cache_time[v] > cache_size {
+                    cache_time[v] = time;
+                    time += 1;
+                }
+            }
+        }
+
+        // the candidate that stays in the cache longest after drawing its remaining triangles
+        let mut best = None;
+        let mut best_priority = -1;
+        for v in candidates.into_iter().filter(|v| live[*v] > 0) {
+            let age = (time - cache_time[v]) as isize;
+            let priority = if age + 2 * live[v] as isize <= cache_size as isize {
+                age
+            } else {
+                0
+            };
+            if priority > best_priority {
+                best = Some(v);
+                best_priority = priority;
+            }
+        }
+        fanning = best.or_else(|| {
+            while let Some(v) = dead_end.pop() {
+                if live[v] > 0 {
+                    return Some(v);
+                }
+            }
+            while cursor < num_vertices {
+                cursor += 1;
+                if live[cursor - 1] > 0 {
+                    return Some(cursor - 1);
+                }
+            }
+            None
+        });
+    }
+    order
+}
+
+/// triangle order that draws clusters facing outwards first, see `TriMesh::optimize_overdraw()`
+fn overdraw_order(indices: &[usize], positions: &[[f32; 3]], threshold: f32) -> Vec<usize> {
+    let num_triangles = indices.len() / 3;
+    let (local, num_vertices) = local_vertices(indices);
+    let starts = cluster_starts(&local, num_vertices, threshold);
+
+    let triangles = (0..num_triangles)
+        .map(|t| {
+            let p = [0, 1, 2].map(|i| positions[indices[3 * t + i]]);
+            let normal = cross(sub(p[1], p[0]), sub(p[2], p[0]));
+            let centroid = scale(1.0 / 3.0, add(add(p[0], p[1]), p[2]));
+            // length of the normal is twice the area
+            (centroid, normal, 0.5 * length(normal))
+        })
+        .collect::<Vec<_>>();
+    let weighted_centroid = |range: Range<usize>| {
+        let area = triangles[range.clone()].iter().map(|t| t.2).sum::<f32>();
+        let sum = triangles[range.clone()]
+            .iter()
+            .fold([0.0; 3], |sum, t| add(sum, scale(t.2, t.0)));
+        if area > 0.0 {
+            scale(1.0 / area, sum)
+        } else {
+            let sum = triangles[range.clone()]
+                .iter()
+                .fold([0.0; 3], |sum, t| add(sum, t.0));
+            scale(1.0 / range.len().max(1) as f32, sum)
+        }
+    };
+    let mesh_centroid = weighted_centroid(0..num_triangles);
+
+    let mut clusters = starts
+        .iter()
+        .enumerate()
+        .map(|(i, start)| {
+            let range = *start..starts.get(i + 1).copied().unwrap_or(num_triangles);
+            let normal = triangles[range.clone()]
+                .iter()
+                .fold([0.0; 3], |sum, t| add(sum, t.1));
+            let key = dot(
+                sub(weighted_centroid(range.clone()), mesh_centroid),
+                normalize(normal),
+            );
+            (range, key)
+        })
+        .collect::<Vec<_>>();
+    clusters.sort_by(|a, b| b.1.total_cmp(&a.1));
+    clusters.into_iter().flat_map(|(range, _)| range).collect()
+}
+
+/// first triangle of each cluster, clusters start where all three vertices miss the cache and
+/// are split where the ACMR up to a triangle is within `threshold` of the ACMR of the cluster
+fn cluster_starts(indices: &[usize], num_vertices: usize, threshold: f32) -> Vec<usize> {
+    let num_triangles = indices.len() / 3;
+    let triangle = |t: usize| &indices[3 * t..3 * t + 3];
+    let mut cache = FifoCache::new(num_vertices, OVERDRAW_CACHE_SIZE);
+    let hard = (0..num_triangles)
+        .filter(|t| cache.add(triangle(*t)) == 3 || *t == 0)
+        .collect::<Vec<_>>();
+
+    let mut starts = vec![];
+    for (i, &start) in hard.iter().enumerate() {
+        let end = hard.get(i + 1).copied().unwrap_or(num_triangles);
+        cache.reset();
+        let misses = (start..end).map(|t| cache.add(triangle(t))).sum::<usize>();
+        let acmr = misses as f32 / (end - start) as f32;
+
+        cache.reset();
+        starts.push(start);
+        let mut cluster_start = start;
+        let mut cluster_misses = 0;
+        for t in start..end - 1 {
+            cluster_misses += cache.add(triangle(t));
+            let cluster_acmr = cluster_misses as f32 / (t + 1 - cluster_start) as f32;
+            if cluster_acmr <= threshold * acmr {
+                starts.push(t + 1);
+                cluster_start = t + 1;
+                cluster_misses = 0;
+                cache.reset();
+            }
+        }
+    }
+    starts
+}
+
+/// FIFO post-transform cache, a vertex is cached if it missed at most `size` misses ago
+struct FifoCache {
+    timestamps: Vec<usize>,
+    time: usize,
+    size: usize,
+}
+
+impl FifoCache {
+    fn new(num_vertices: usize, size: usize) -> Self {
+        Self {
+            timestamps: vec![0; num_vertices],
+            time: size + 1,
+            size,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.time += self.size + 1;
+    }
+
+    /// add vertices and return the number of misses
+    fn add(&mut self, vertices: &[usize]) -> usize {
+        let mut misses = 0;
+        for v in vertices {
+            if self.time - self.timestamps[*v] > self.size {
+                self.timestamps[*v] = self.time;
+                self.time += 1;
+                misses += 1;
+            }
+        }
+        misses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::TriMeshBuilder;
+
+    /// `n` by `n` quads split into triangles, rows of quads are drawn in a scrambled order
+    fn grid(n: u32) -> TriMesh {
+        let positions = (0..=n)
+            .flat_map(|j| (0..=n).map(move |i| [i as f32, j as f32, 0.0]))
+            .collect::<Vec<_>>();
+        let mut indices = vec![];
+        for j in (0..n).map(|j| j * 7 % n) {
+            for i in 0..n {
+                let v = j * (n + 1) + i;
+                indices.extend([v, v + 1, v + n + 2, v, v + n + 2, v + n + 1]);
+            }
+        }
+        TriMeshBuilder::new()
+            .attribute(TriMesh::POSITION, positions)
+            .indices(indices)
+            .build()
+            .unwrap()
+    }
+
+    type Optimize = fn(&mut TriMesh) -> Result<(), TriMeshError>;
+
+    /// sorted triangles of each submesh
+    fn triangles_per_submesh(mesh: &TriMesh) -> Vec<Vec<[usize; 3]>> {
+        let indices = mesh.index_list();
+        mesh.submeshes()
+            .iter()
+            .map(|submesh| {
+                let mut triangles = indices[submesh.range()]
+                    .chunks(3)
+                    .map(|t| [t[0], t[1], t[2]])
+                    .collect::<Vec<_>>();
+                triangles.sort_unstable();
+                triangles
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reordering_permutes_triangles_of_each_submesh() {
+        let mut original = grid(8);
+        original.set_submeshes(vec![
+            Submesh::new(0, 150, Some(0)),
+            Submesh::new(150, 234, Some(1)),
+        ]);
+        let expected = triangles_per_submesh(&original);
+
+        let optimizers: [Optimize; 3] = [
+            |mesh| mesh.optimize_vertex_cache(VertexCacheOptimizer::Forsyth),
+            |mesh| mesh.optimize_vertex_cache(VertexCacheOptimizer::Tipsify { cache_size: 16 }),
+            |mesh| mesh.optimize_overdraw(1.05),
+        ];
+        for optimize in optimizers.iter() {
+            let mut mesh = original.clone();
+            optimize(&mut mesh).unwrap();
+            assert_eq!(mesh.num_vertices(), original.num_vertices());
+            assert_eq!(mesh.submeshes(), original.submeshes());
+            assert_eq!(triangles_per_submesh(&mesh), expected);
+        }
+    }
+
+    #[test]
+    fn vertex_cache_acmr_does_not_get_worse() {
+        let original = grid(16);
+        let before = original.vertex_cache_statistics(16);
+        for optimizer in [
+            VertexCacheOptimizer::Forsyth,
+            VertexCacheOptimizer::Tipsify { cache_size: 16 },
+        ]
+        .iter()
+        {
+            let mut mesh = original.clone();
+            mesh.optimize_vertex_cache(*optimizer).unwrap();
+            let after = mesh.vertex_cache_statistics(16);
+            assert!(
+                after.acmr <= before.acmr,
+                "{:?}: {} > {}",
+                optimizer,
+                after.acmr,
+                before.acmr
+            );
+            assert!(after.acmr >= 0.5 && after.atvr >= 1.0);
+        }
+    }
+
+    #[test]
+    fn vertex_fetch_removes_unused_vertices() {
+        // vertex 0 and 4 are unused, the others are used in the order 3, 1, 2, 5
+        let positions = (0..6).map(|i| [i as f32, 0.0, 0.0]).collect::<Vec<_>>();
+        let colors = (0..6)
+            .map(|i| [i as f32 / 10.0, 0.0, 0.0, 1.0])
+            .collect::<Vec<_>>();
+        let mut mesh = TriMeshBuilder::new()
+            .attribute(TriMesh::POSITION, positions.clone())
+            .attribute(TriMesh::COLOR, colors.clone())
+            .indices(vec![3, 1, 2, 2, 1, 5])
+            .build()
+            .unwrap();
+
+        let remap = mesh.optimize_vertex_fetch();
+        assert_eq!(remap, vec![None, Some(1), Some(2), Some(0), None, Some(3)]);
+        assert_eq!(mesh.num_vertices(), 4);
+        assert_eq!(mesh.index_list(), vec![0, 1, 2, 2, 1, 3]);
+        for (old, new) in remap.iter().enumerate() {
+            if let Some(new) = *new {
+                assert_eq!(
+                    mesh.attribute_as::<[f32; 3]>(TriMesh::POSITION).unwrap()[new],
+                    positions[old]
+                );
+                assert_eq!(
+                    mesh.attribute_as::<[f32; 4]>(TriMesh::COLOR).unwrap()[new],
+                    colors[old]
+                );
+            }
+        }
+    }
+}
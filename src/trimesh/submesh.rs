@@ -0,0 +1,259 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+};
+
+use super::{MeshIndices, TriMesh, TriMeshError, RESTART};
+
+/// a range of indices drawn with one material, all submeshes of a `TriMesh` share its vertices
+///
+/// ranges are in vertices if the mesh has no indices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Submesh {
+    pub start: usize,
+    pub count: usize,
+    pub material_id: Option<usize>,
+}
+
+impl Submesh {
+    pub fn new(start: usize, count: usize, material_id: Option<usize>) -> Self {
+        Self {
+            start,
+            count,
+            material_id,
+        }
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.start + self.count
+    }
+}
+
+impl TriMesh {
+    /// empty if the whole mesh is drawn with one unspecified material
+    pub fn submeshes(&self) -> &[Submesh] {
+        &self.submeshes
+    }
+
+    pub fn set_submeshes(&mut self, submeshes: Vec<Submesh>) {
+        self.submeshes = submeshes;
+    }
+
+    /// reorder indices so that each material is drawn by one submesh, in order of material id
+    /// with `None` first
+    ///
+    /// indices are generated if there are none, indices outside of submeshes are dropped,
+    /// submeshes must be in range
+    pub fn sort_by_material(&mut self) -> Result<(), TriMeshError> {
+        if self.submeshes.is_empty() {
+            return Ok(());
+        }
+        self.validate_submeshes()?;
+
+        let index_list = self.index_list();
+        let mut groups = BTreeMap::<_, Vec<_>>::new();
+        for submesh in &self.submeshes {
+            groups
+                .entry(submesh.material_id)
+                .or_default()
+                .push(&index_list[submesh.range()]);
+        }
+
+        let mut indices = vec![];
+        let mut submeshes = Vec::with_capacity(groups.len());
+        for (material_id, parts) in groups {
+            let start = indices.len();
+            for part in parts {
+                if self.topology.has_restart() && indices.len() > start {
+                    indices.push(RESTART);
+                }
+                indices.extend_from_slice(part);
+            }
+            submeshes.push(Submesh::new(start, indices.len() - start, material_id));
+        }
+
+        let prefer_u16 = !matches!(self.indices, Some(MeshIndices::U32(_)));
+        self.set_index_list(&indices, self.topology, prefer_u16);
+        self.submeshes = submeshes;
+        Ok(())
+    }
+
+    /// one mesh for each submesh that only has the vertices used by it,
+    /// each result has a single submesh covering all of its indices, empty if there are no submeshes
+    pub fn split_submeshes(&self) -> Result<Vec<TriMesh>, TriMeshError> {
+        self.validate()?;
+
+        let index_list = self.index_list();
+        let prefer_u16 = !matches!(self.indices, Some(MeshIndices::U32(_)));
+        let meshes = self
+            .submeshes
+            .iter()
+            .map(|submesh| {
+                let mut new_vertex = HashMap::new();
+                let mut sources = vec![];
+                let indices = index_list[submesh.range()]
+                    .iter()
+                    .map(|index| match *index {
+                        RESTART => RESTART,
+                        index => *new_vertex.entry(index).or_insert_with(|| {
+                            sources.push(index);
+                            sources.len() - 1
+                        }),
+                    })
+                    .collect::<Vec<_>>();
+
+                let vertex_attributes = self
+                    .vertex_attributes
+                    .iter()
+                    .map(|(name, attribute)| (name.clone(), attribute.gather(&sources)))
+                    .collect();
+                let mut mesh = TriMesh::new(vertex_attributes, None);
+                mesh.set_index_list(&indices, self.topology, prefer_u16);
+                mesh.submeshes = vec![Submesh::new(0, indices.len(), submesh.material_id)];
+                mesh
+            })
+            .collect();
+        Ok(meshes)
+    }
+
+    /// concatenate vertices and indices of `meshes`, which must have the same topology and attributes
+    ///
+    /// submeshes are kept, a mesh without submeshes becomes one submesh without material,
+    /// strips and fans of different meshes are separated by restart indices
+    pub fn merge(meshes: &[&TriMesh]) -> Result<TriMesh, TriMeshError> {
+        let first = match meshes.first() {
+            Some(first) => first,
+            None => return Ok(TriMesh::new(HashMap::new(), None)),
+        };
+        let topology = first.topology;
+
+        let mut vertex_attributes = first
+            .vertex_attributes
+            .iter()
+            .map(|(name, attribute)| (name.clone(), attribute.gather(&[])))
+            .collect::<HashMap<_, _>>();
+        let mut indices = vec![];
+        let mut submeshes = vec![];
+        let mut num_vertices = 0;
+        for (i, mesh) in meshes.iter().enumerate() {
+            mesh.validate()?;
+            if mesh.topology != topology {
+                return Err(TriMeshError::MergeMismatch(format!(
+                    "mesh {} has topology {:?} instead of {:?}",
+                    i, mesh.topology, topology
+                )));
+            }
+            if mesh.vertex_attributes.len() != vertex_attributes.len() {
+                return Err(TriMeshError::MergeMismatch(format!(
+                    "mesh {} has {} attributes instead of {}",
+                    i,
+                    mesh.vertex_attributes.len(),
+                    vertex_attributes.len()
+                )));
+            }
+            for (name, attribute) in &mesh.vertex_attributes {
+                let merged = vertex_attributes.get_mut(name).ok_or_else(|| {
+                    TriMeshError::MergeMismatch(format!(
+                        "mesh {} has extra attribute '{}'",
+                        i, name
+                    ))
+                })?;
+                if !merged.append(attribute) {
+                    return Err(TriMeshError::MergeMismatch(format!(
+                        "attribute '{}' of mesh {} has format {:?} instead of {:?}",
+                        name,
+                        i,
+                        attribute.format(),
+                        merged.format()
+                    )));
+                }
+            }
+
+            if topology.has_restart() && !indices.is_empty() {
+                indices.push(RESTART);
+            }
+            let start = indices.len();
+            indices.extend(mesh.index_list().into_iter().map(|index| match index {
+                RESTART => RESTART,
+                index => index + num_vertices,
+            }));
+            if mesh.submeshes.is_empty() {
+                submeshes.push(Submesh::new(start, indices.len() - start, None));
+            } else {
+                submeshes.extend(mesh.submeshes.iter().map(|submesh| Submesh {
+                    start: start + submesh.start,
+                    ..*submesh
+                }));
+            }
+            num_vertices += mesh.num_vertices();
+        }
+
+        let prefer_u16 = meshes
+            .iter()
+            .all(|mesh| !matches!(mesh.indices, Some(MeshIndices::U32(_))));
+        let mut mesh = TriMesh::new(vertex_attributes, None);
+        mesh.set_index_list(&indices, topology, prefer_u16);
+        mesh.submeshes = submeshes;
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::{PrimitiveTopology, TriMeshBuilder, VertexCacheOptimizer};
+
+    fn quad() -> TriMesh {
+        TriMeshBuilder::new()
+            .attribute(
+                TriMesh::POSITION,
+                vec![
+                    [0.0f32, 0.0, 0.0],
+                    [1.0, 0.0, 0.0],
+                    [1.0, 1.0, 0.0],
+                    [0.0, 1.0, 0.0],
+                ],
+            )
+            .indices(vec![0, 1, 2, 0, 2, 3])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn sort_by_material() {
+        let mut mesh = quad();
+        mesh.set_submeshes(vec![
+            Submesh::new(0, 3, Some(1)),
+            Submesh::new(3, 3, Some(0)),
+        ]);
+        mesh.sort_by_material().unwrap();
+        assert_eq!(
+            mesh.submeshes(),
+            [Submesh::new(0, 3, Some(0)), Submesh::new(3, 3, Some(1))]
+        );
+        assert_eq!(mesh.triangles(), [[0, 2, 3], [0, 1, 2]]);
+    }
+
+    #[test]
+    fn out_of_range_submeshes_are_errors() {
+        let mut mesh = quad();
+        mesh.set_submeshes(vec![Submesh::new(0, 3, None), Submesh::new(3, 6, None)]);
+        let error = TriMeshError::SubmeshRange {
+            submesh: 1,
+            end: 9,
+            len: 6,
+        };
+
+        assert_eq!(mesh.clone().sort_by_material(), Err(error.clone()));
+        assert_eq!(
+            mesh.clone().convert_topology(PrimitiveTopology::LineList),
+            Err(error.clone())
+        );
+        assert_eq!(
+            mesh.clone()
+                .optimize_vertex_cache(VertexCacheOptimizer::Forsyth),
+            Err(error.clone())
+        );
+        assert_eq!(mesh.build_meshlets(64, 126, 0.0), Err(error));
+    }
+}
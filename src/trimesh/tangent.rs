@@ -0,0 +1,512 @@
+use std::collections::HashMap;
+
+use super::{AttributeError, TriMesh, VertexAttribute};
+use crate::util::{add, dot, length, scale, sub};
+
+const GROUP_WITH_ANY: u32 = 1 << 2;
+const ORIENT_PRESERVING: u32 = 1 << 3;
+
+impl TriMesh {
+    /// compute `TriMesh::TANGENT` from `POSITION`, `NORMAL` and `TEXCOORD` in the same way as MikkTSpace
+    ///
+    /// tangent is stored as `Float4`, whose `w` is the handedness, so that
+    /// `bitangent = w * cross(normal, tangent)`; vertices are split where tangents of their corners differ
+    pub fn compute_tangents(&mut self) -> Result<(), AttributeError> {
+        let tangents = {
+            let positions = self.attribute_as::<[f32; 3]>(Self::POSITION)?;
+            let normals = self.attribute_as::<[f32; 3]>(Self::NORMAL)?;
+            let texcoords = self.attribute_as::<[f32; 2]>(Self::TEXCOORD)?;
+            let triangles = self.triangles();
+            MikkTSpace::new(positions, normals, texcoords, &triangles).generate()
+        };
+
+        let keys = tangents
+            .iter()
+            .map(|tangent| tangent.map(f32::to_bits))
+            .collect::<Vec<_>>();
+        let corners = self.split_vertices(&keys);
+        let mut result = vec![[1.0, 0.0, 0.0, 1.0]; self.num_vertices()];
+        for (vertex, tangent) in corners.into_iter().zip(tangents) {
+            result[vertex] = tangent;
+        }
+        self.set_attribute(Self::TANGENT, VertexAttribute::Float4(result));
+
+        Ok(())
+    }
+}
+
+struct TriInfo {
+    /// neighbor triangle across edge `(i, i + 1)`
+    neighbors: [Option<usize>; 3],
+    groups: [Option<usize>; 3],
+    os: [f32; 3],
+    ot: [f32; 3],
+    flags: u32,
+}
+
+struct Group {
+    vertex: usize,
+    orient_preserving: bool,
+    faces: Vec<usize>,
+}
+
+#[derive(Clone, Copy)]
+struct TSpace {
+    os: [f32; 3],
+    orient: bool,
+}
+
+/// port of the reference MikkTSpace implementation, restricted to triangles
+struct MikkTSpace<'a> {
+    positions: &'a [[f32; 3]],
+    normals: &'a [[f32; 3]],
+    texcoords: &'a [[f32; 2]],
+    triangles: &'a [[usize; 3]],
+    /// shared index of each corner, corners with identical position, normal and texcoord share an index
+    shared: Vec<usize>,
+}
+
+impl<'a> MikkTSpace<'a> {
+    fn new(
+        positions: &'a [[f32; 3]],
+        normals: &'a [[f32; 3]],
+        texcoords: &'a [[f32; 2]],
+        triangles: &'a [[usize; 3]],
+    ) -> Self {
+        // `0.0` and `-0.0` are the same vertex
+        let bits = |x: f32| (x + 0.0).to_bits();
+        let mut first = HashMap::new();
+        let shared = triangles
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(corner, v)| {
+                let key = (
+                    positions[*v].map(bits),
+                    normals[*v].map(bits),
+                    texcoords[*v].map(bits),
+                );
+                *first.entry(key).or_insert(corner)
+            })
+            .collect();
+
+        Self {
+            positions,
+            normals,
+            texcoords,
+            triangles,
+            shared,
+        }
+    }
+
+    fn position(&self, index: usize) -> [f32; 3] {
+        self.positions[self.triangles[index / 3][index % 3]]
+    }
+
+    fn normal(&self, index: usize) -> [f32; 3] {
+        self.normals[self.triangles[index / 3][index % 3]]
+    }
+
+    fn texcoord(&self, index: usize) -> [f32; 2] {
+        self.texcoords[self.triangles[index / 3][index % 3]]
+    }
+
+    /// tangent and handedness of each corner
+    fn generate(&self) -> Vec<[f32; 4]> {
+        let num_triangles = self.triangles.len();
+        let tri = |t: usize| {
+            [
+                self.shared[3 * t],
+                self.shared[3 * t + 1],
+                self.shared[3 * t + 2],
+            ]
+        };
+
+        // good triangles keep their order, degenerate ones are handled at last
+        let (good, degenerate): (Vec<_>, Vec<_>) = (0..num_triangles).partition(|t| {
+            let [i0, i1, i2] = tri(*t);
+            i0 != i1 && i0 != i2 && i1 != i2
+        });
+        let list = good.iter().map(|t| tri(*t)).collect::<Vec<_>>();
+
+        let mut infos = list
+            .iter()
+            .map(|indices| self.init_tri_info(indices))
+            .collect::<Vec<_>>();
+        build_neighbors(&mut infos, &list);
+        let groups = build_groups(&mut infos, &list);
+
+        let default = TSpace {
+            os: [1.0, 0.0, 0.0],
+            orient: false,
+        };
+        let mut tspaces = vec![default; 3 * num_triangles];
+        self.generate_tspaces(&infos, &groups, &list, &good, &mut tspaces);
+
+        // corners of degenerate triangles take tangent of the first good corner with the same index
+        for t in degenerate {
+            for i in 0..3 {
+                let index = self.shared[3 * t + i];
+                if let Some(j) = list.iter().flatten().position(|other| *other == index) {
+                    tspaces[3 * t + i] = tspaces[3 * good[j / 3] + j % 3];
+                }
+            }
+        }
+
+        tspaces
+            .into_iter()
+            .map(|tspace| {
+                let sign = if tspace.orient { 1.0 } else { -1.0 };
+                [tspace.os[0], tspace.os[1], tspace.os[2], sign]
+            })
+            .collect()
+    }
+
+    fn init_tri_info(&self, indices: &[usize; 3]) -> TriInfo {
+        let mut info = TriInfo {
+            neighbors: [None; 3],
+            groups: [None; 3],
+            os: [0.0; 3],
+            ot: [0.0; 3],
+            flags: GROUP_WITH_ANY,
+        };
+
+        let v1 = self.position(indices[0]);
+        let v2 = self.position(indices[1]);
+        let v3 = self.position(indices[2]);
+        let t1 = self.texcoord(indices[0]);
+        let t2 = self.texcoord(indices[1]);
+        let t3 = self.texcoord(indices[2]);
+
+        let t21x = t2[0] - t1[0];
+        let t21y = t2[1] - t1[1];
+        let t31x = t3[0] - t1[0];
+        let t31y = t3[1] - t1[1];
+        let d1 = sub(v2, v1);
+        let d2 = sub(v3, v1);
+
+        let signed_area_x2 = t21x * t31y - t21y * t31x;
+        let os = sub(scale(t31y, d1), scale(t21y, d2));
+        let ot = add(scale(-t31x, d1), scale(t21x, d2));
+        if signed_area_x2 > 0.0 {
+            info.flags |= ORIENT_PRESERVING;
+        }
+
+        if not_zero(signed_area_x2) {
+            let abs_area = signed_area_x2.abs();
+            let len_os = length(os);
+            let len_ot = length(ot);
+            let sign = if info.flags & ORIENT_PRESERVING == 0 {
+                -1.0
+            } else {
+                1.0
+            };
+            if not_zero(len_os) {
+                info.os = scale(sign / len_os, os);
+            }
+            if not_zero(len_ot) {
+                info.ot = scale(sign / len_ot, ot);
+            }
+            let mag_s = len_os / abs_area;
+            let mag_t = len_ot / abs_area;
+            if not_zero(mag_s) && not_zero(mag_t) {
+                info.flags &= !GROUP_WITH_ANY;
+            }
+        }
+
+        info
+    }
+
+    fn generate_tspaces(
+        &self,
+        infos: &[TriInfo],
+        groups: &[Group],
+        list: &[[usize; 3]],
+        good: &[usize],
+        tspaces: &mut [TSpace],
+    ) {
+        // angular threshold of the reference implementation is 180 degrees
+        let threshold_cos = -1.0;
+        let project = |n: [f32; 3], v: [f32; 3]| {
+            let projected = sub(v, scale(dot(n, v), n));
+            if v_not_zero(projected) {
+                normalize(projected)
+            } else {
+                projected
+            }
+        };
+
+        for (g, group) in groups.iter().enumerate() {
+            let mut sub_groups: Vec<(Vec<usize>, TSpace)> = vec![];
+            for &f in &group.faces {
+                let index = (0..3).find(|i| infos[f].groups[*i] == Some(g)).unwrap();
+                let n = self.normal(list[f][index]);
+                let os = project(n, infos[f].os);
+                let ot = project(n, infos[f].ot);
+
+                let mut members = vec![];
+                for &t in &group.faces {
+                    let os2 = project(n, infos[t].os);
+                    let ot2 = project(n, infos[t].ot);
+                    let any = (infos[f].flags | infos[t].flags) & GROUP_WITH_ANY != 0;
+                    let same_face = f == t;
+                    if any
+                        || same_face
+                        || (dot(os, os2) > threshold_cos && dot(ot, ot2) > threshold_cos)
+                    {
+                        members.push(t);
+                    }
+                }
+                members.sort_unstable();
+
+                let tspace = match sub_groups.iter().find(|(other, _)| *other == members) {
+                    Some((_, tspace)) => *tspace,
+                    None => {
+                        let tspace = TSpace {
+                            os: self.eval_tspace(&members, infos, list, group.vertex),
+                            orient: group.orient_preserving,
+                        };
+                        sub_groups.push((members, tspace));
+                        tspace
+                    }
+                };
+                tspaces[3 * good[f] + index] = tspace;
+            }
+        }
+    }
+
+    fn eval_tspace(
+        &self,
+        faces: &[usize],
+        infos: &[TriInfo],
+        list: &[[usize; 3]],
+        vertex: usize,
+    ) -> [f32; 3] {
+        let mut os = [0.0; 3];
+        for &f in faces {
+            if infos[f].flags & GROUP_WITH_ANY != 0 {
+                continue;
+            }
+            let i = (0..3).find(|i| list[f][*i] == vertex).unwrap();
+
+            let n = self.normal(list[f][i]);
+            let mut vos = sub(infos[f].os, scale(dot(n, infos[f].os), n));
+            if v_not_zero(vos) {
+                vos = normalize(vos);
+            }
+
+            let i2 = list[f][if i < 2 { i + 1 } else { 0 }];
+            let i1 = list[f][i];
+            let i0 = list[f][if i > 0 { i - 1 } else { 2 }];
+            let p0 = self.position(i0);
+            let p1 = self.position(i1);
+            let p2 = self.position(i2);
+            let mut v1 = sub(p0, p1);
+            let mut v2 = sub(p2, p1);
+            v1 = sub(v1, scale(dot(n, v1), n));
+            if v_not_zero(v1) {
+                v1 = normalize(v1);
+            }
+            v2 = sub(v2, scale(dot(n, v2), n));
+            if v_not_zero(v2) {
+                v2 = normalize(v2);
+            }
+
+            // weight contribution by the angle between the two edge vectors
+            let cos = dot(v1, v2).clamp(-1.0, 1.0);
+            let angle = (cos as f64).acos() as f32;
+            os = add(os, scale(angle, vos));
+        }
+
+        if v_not_zero(os) {
+            os = normalize(os);
+        }
+        os
+    }
+}
+
+fn build_neighbors(infos: &mut [TriInfo], list: &[[usize; 3]]) {
+    // `(min index, max index, triangle, edge)`
+    let mut edges = Vec::with_capacity(3 * list.len());
+    for (t, indices) in list.iter().enumerate() {
+        for i in 0..3 {
+            let i0 = indices[i];
+            let i1 = indices[(i + 1) % 3];
+            edges.push((i0.min(i1), i0.max(i1), t, i));
+        }
+    }
+    edges.sort_unstable();
+
+    for a in 0..edges.len() {
+        let (min, max, f, edge_a) = edges[a];
+        if infos[f].neighbors[edge_a].is_some() {
+            continue;
+        }
+        let i0_a = list[f][edge_a];
+        let i1_a = list[f][(edge_a + 1) % 3];
+
+        let mut b = a + 1;
+        while b < edges.len() && edges[b].0 == min && edges[b].1 == max {
+            let (_, _, t, edge_b) = edges[b];
+            let i0_b = list[t][(edge_b + 1) % 3];
+            let i1_b = list[t][edge_b];
+            if i0_a == i0_b && i1_a == i1_b && infos[t].neighbors[edge_b].is_none() {
+                infos[f].neighbors[edge_a] = Some(t);
+                infos[t].neighbors[edge_b] = Some(f);
+                break;
+            }
+            b += 1;
+        }
+    }
+}
+
+/// group faces around each vertex that are connected by edges and have the same orientation
+fn build_groups(infos: &mut [TriInfo], list: &[[usize; 3]]) -> Vec<Group> {
+    let mut groups = vec![];
+    for f in 0..list.len() {
+        // triangles that can be grouped with anything only join groups of their neighbors,
+        // corners that are left alone keep the default tangent space
+        if infos[f].flags & GROUP_WITH_ANY != 0 {
+            continue;
+        }
+        for i in 0..3 {
+            if infos[f].groups[i].is_some() {
+                continue;
+            }
+            let g = groups.len();
+            infos[f].groups[i] = Some(g);
+            groups.push(Group {
+                vertex: list[f][i],
+                orient_preserving: infos[f].flags & ORIENT_PRESERVING != 0,
+                faces: vec![f],
+            });
+
+            let left = infos[f].neighbors[i];
+            let right = infos[f].neighbors[if i > 0 { i - 1 } else { 2 }];
+            if let Some(left) = left {
+                assign_recur(infos, list, &mut groups, left, g);
+            }
+            if let Some(right) = right {
+                assign_recur(infos, list, &mut groups, right, g);
+            }
+        }
+    }
+    groups
+}
+
+fn assign_recur(
+    infos: &mut [TriInfo],
+    list: &[[usize; 3]],
+    groups: &mut [Group],
+    t: usize,
+    g: usize,
+) -> bool {
+    let i = (0..3).find(|i| list[t][*i] == groups[g].vertex).unwrap();
+    let info = &mut infos[t];
+
+    if info.groups[i] == Some(g) {
+        return true;
+    } else if info.groups[i].is_some() {
+        return false;
+    }
+    if info.flags & GROUP_WITH_ANY != 0 && info.groups.iter().all(Option::is_none) {
+        // the first group decides orientation of a triangle that can be grouped with anything
+        info.flags &= !ORIENT_PRESERVING;
+        if groups[g].orient_preserving {
+            info.flags |= ORIENT_PRESERVING;
+        }
+    }
+    if (info.flags & ORIENT_PRESERVING != 0) != groups[g].orient_preserving {
+        return false;
+    }
+
+    groups[g].faces.push(t);
+    info.groups[i] = Some(g);
+
+    let left = info.neighbors[i];
+    let right = info.neighbors[if i > 0 { i - 1 } else { 2 }];
+    if let Some(left) = left {
+        assign_recur(infos, list, groups, left, g);
+    }
+    if let Some(right) = right {
+        assign_recur(infos, list, groups, right, g);
+    }
+    true
+}
+
+fn not_zero(x: f32) -> bool {
+    x.abs() > f32::MIN_POSITIVE
+}
+
+fn v_not_zero(v: [f32; 3]) -> bool {
+    not_zero(v[0]) || not_zero(v[1]) || not_zero(v[2])
+}
+
+/// unlike `util::normalize()`, callers check `v_not_zero()` first as the reference implementation does
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    scale(1.0 / length(v), v)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::*;
+    use crate::trimesh::TriMeshBuilder;
+
+    /// flat quad whose triangle `(0, 2, 3)` has zero uv area
+    fn quad(indices: Vec<u32>) -> TriMesh {
+        TriMeshBuilder::new()
+            .attribute(
+                TriMesh::POSITION,
+                vec![
+                    [0.0f32, 0.0, 0.0],
+                    [1.0, 0.0, 0.0],
+                    [1.0, 1.0, 0.0],
+                    [0.0, 1.0, 0.0],
+                ],
+            )
+            .attribute(TriMesh::NORMAL, vec![[0.0f32, 0.0, 1.0]; 4])
+            .attribute(
+                TriMesh::TEXCOORD,
+                vec![[0.0f32, 0.0], [1.0, 0.0], [1.0, 1.0], [0.5, 0.5]],
+            )
+            .indices(indices)
+            .build()
+            .unwrap()
+    }
+
+    fn tangents_by_position(mesh: &TriMesh) -> Vec<([f32; 3], [f32; 4])> {
+        let positions = mesh.attribute_as::<[f32; 3]>(TriMesh::POSITION).unwrap();
+        let tangents = mesh.attribute_as::<[f32; 4]>(TriMesh::TANGENT).unwrap();
+        let mut result = positions
+            .iter()
+            .copied()
+            .zip(tangents.iter().copied())
+            .collect::<Vec<_>>();
+        result.sort_by(|a, b| {
+            (0..3)
+                .map(|i| a.0[i].total_cmp(&b.0[i]))
+                .fold(Ordering::Equal, Ordering::then)
+        });
+        result
+    }
+
+    #[test]
+    fn uv_degenerate_triangle_matches_mikktspace() {
+        // reference MikkTSpace output: corners grouped with the good triangle take its tangent,
+        // the corner left alone keeps the default `(1, 0, 0)` with negative handedness
+        let expected = vec![
+            ([0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 1.0]),
+            ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0, -1.0]),
+            ([1.0, 0.0, 0.0], [1.0, 0.0, 0.0, 1.0]),
+            ([1.0, 1.0, 0.0], [1.0, 0.0, 0.0, 1.0]),
+        ];
+        for indices in [vec![0, 1, 2, 0, 2, 3], vec![0, 2, 3, 0, 1, 2]] {
+            let mut mesh = quad(indices);
+            mesh.compute_tangents().unwrap();
+            assert_eq!(mesh.num_vertices(), 4);
+            assert_eq!(tangents_by_position(&mesh), expected);
+        }
+    }
+}
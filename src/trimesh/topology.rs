@@ -0,0 +1,423 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{MeshIndices, Submesh, TriMesh, TriMeshError};
+
+/// how vertices are assembled into primitives, vertices are taken from indices if there are any
+///
+/// strips and fans are restarted by `MeshIndices::restart_index()` as with primitive restart of
+/// wgpu or Vulkan, other topologies don't have restart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PrimitiveTopology {
+    PointList,
+    LineList,
+    LineStrip,
+    #[default]
+    TriangleList,
+    /// triangle `i` is `(i, i + 1, i + 2)`, the first two vertices are swapped for odd `i`
+    /// so that all triangles have the same winding
+    TriangleStrip,
+    /// triangle `i` is `(i + 1, i + 2, 0)`
+    TriangleFan,
+}
+
+impl PrimitiveTopology {
+    pub fn is_triangles(self) -> bool {
+        matches!(
+            self,
+            PrimitiveTopology::TriangleList
+                | PrimitiveTopology::TriangleStrip
+                | PrimitiveTopology::TriangleFan
+        )
+    }
+
+    pub fn is_lines(self) -> bool {
+        matches!(
+            self,
+            PrimitiveTopology::LineList | PrimitiveTopology::LineStrip
+        )
+    }
+
+    /// whether indices can be restarted
+    pub fn has_restart(self) -> bool {
+        matches!(
+            self,
+            PrimitiveTopology::LineStrip
+                | PrimitiveTopology::TriangleStrip
+                | PrimitiveTopology::TriangleFan
+        )
+    }
+
+    /// number of vertices of each primitive of a list topology
+    pub(crate) fn list_size(self) -> Option<usize> {
+        match self {
+            PrimitiveTopology::PointList => Some(1),
+            PrimitiveTopology::LineList => Some(2),
+            PrimitiveTopology::TriangleList => Some(3),
+            _ => None,
+        }
+    }
+}
+
+/// marks a restart in index lists built by conversions, it becomes the restart index of `MeshIndices`
+pub(crate) const RESTART: usize = usize::MAX;
+
+impl TriMesh {
+    pub fn topology(&self) -> PrimitiveTopology {
+        self.topology
+    }
+
+    /// change how vertices and indices are interpreted, they are not modified,
+    /// use `convert_topology()` to keep the primitives
+    pub fn set_topology(&mut self, topology: PrimitiveTopology) {
+        self.topology = topology;
+    }
+
+    /// rebuild indices to draw the same primitives with `topology`
+    ///
+    /// * triangles can be converted to any topology except `TriangleFan`,
+    ///   lines are the edges of triangles and strips are generated greedily
+    /// * lines can be converted to lines or points
+    /// * points of any topology are the used vertices in order of their first use
+    ///
+    /// each submesh is converted on its own and indices outside of submeshes are dropped,
+    /// submeshes must be in range
+    ///
+    /// indices are `u16` if the mesh has `u16` indices or no indices and all vertices can be indexed
+    pub fn convert_topology(&mut self, topology: PrimitiveTopology) -> Result<(), TriMeshError> {
+        if topology == self.topology {
+            return Ok(());
+        }
+
+        self.validate_submeshes()?;
+
+        let from = self.topology;
+        let index_list = self.index_list();
+        let mut indices = vec![];
+        let mut submeshes = Vec::with_capacity(self.submeshes.len());
+        for submesh in &self.submeshes {
+            let part = convert_indices(&index_list[submesh.range()], from, topology)?;
+            if topology.has_restart() && !indices.is_empty() && !part.is_empty() {
+                indices.push(RESTART);
+            }
+            submeshes.push(Submesh {
+                start: indices.len(),
+                count: part.len(),
+                ..*submesh
+            });
+            indices.extend(part);
+        }
+        if self.submeshes.is_empty() {
+            indices = convert_indices(&index_list, from, topology)?;
+        }
+
+        let prefer_u16 = !matches!(self.indices, Some(MeshIndices::U32(_)));
+        self.set_index_list(&indices, topology, prefer_u16);
+        self.submeshes = submeshes;
+        Ok(())
+    }
+
+    /// indices, or all vertices in order if there are no indices, restart indices become `RESTART`
+    pub(crate) fn index_list(&self) -> Vec<usize> {
+        match &self.indices {
+            Some(indices) => {
+                let restart = self.topology.has_restart().then(|| indices.restart_index());
+                (0..indices.len())
+                    .map(|i| indices.get(i))
+                    .map(|index| {
+                        if Some(index) == restart {
+                            RESTART
+                        } else {
+                            index
+                        }
+                    })
+                    .collect()
+            }
+            None => (0..self.num_vertices()).collect(),
+        }
+    }
+
+    /// set indices drawn with `topology` from a list that may contain `RESTART`
+    pub(crate) fn set_index_list(
+        &mut self,
+        indices: &[usize],
+        topology: PrimitiveTopology,
+        prefer_u16: bool,
+    ) {
+        // the restart index can't be a vertex of strips and fans
+        let max_vertices = self.num_vertices() + topology.has_restart() as usize;
+        self.indices = Some(MeshIndices::from_vec(indices, max_vertices, prefer_u16));
+        self.topology = topology;
+    }
+
+    /// vertices split at restart indices, restart is only used by strips and fans
+    pub(crate) fn vertex_runs(&self) -> Vec<Vec<usize>> {
+        self.index_list()
+            .split(|index| *index == RESTART)
+            .map(<[usize]>::to_vec)
+            .collect()
+    }
+
+    /// vertex indices of each triangle, empty for points and lines
+    pub(crate) fn triangles(&self) -> Vec<[usize; 3]> {
+        triangles_of(&self.index_list(), self.topology)
+    }
+
+    /// vertex indices of each line, empty for points and triangles
+    pub(crate) fn lines(&self) -> Vec<[usize; 2]> {
+        lines_of(&self.index_list(), self.topology)
+    }
+}
+
+/// indices of `from` that draw the same primitives with `to`, see `TriMesh::convert_topology()`
+fn convert_indices(
+    indices: &[usize],
+    from: PrimitiveTopology,
+    to: PrimitiveTopology,
+) -> Result<Vec<usize>, TriMeshError> {
+    let indices = match to {
+        _ if to == from => indices.to_vec(),
+        PrimitiveTopology::PointList => {
+            let mut used = HashSet::new();
+            indices
+                .iter()
+                .copied()
+                .filter(|vertex| *vertex != RESTART && used.insert(*vertex))
+                .collect()
+        }
+        PrimitiveTopology::TriangleList if from.is_triangles() => {
+            triangles_of(indices, from).concat()
+        }
+        PrimitiveTopology::TriangleStrip if from.is_triangles() => {
+            triangle_strips(&triangles_of(indices, from))
+        }
+        PrimitiveTopology::LineList if from.is_triangles() => {
+            triangle_edges(&triangles_of(indices, from)).concat()
+        }
+        PrimitiveTopology::LineStrip if from.is_triangles() => {
+            line_strips(&triangle_edges(&triangles_of(indices, from)))
+        }
+        PrimitiveTopology::LineList if from.is_lines() => lines_of(indices, from).concat(),
+        PrimitiveTopology::LineStrip if from.is_lines() => line_strips(&lines_of(indices, from)),
+        _ => return Err(TriMeshError::UnsupportedConversion { from, to }),
+    };
+    Ok(indices)
+}
+
+/// triangles of `indices` drawn with `topology`
+pub(crate) fn triangles_of(indices: &[usize], topology: PrimitiveTopology) -> Vec<[usize; 3]> {
+    let mut triangles = vec![];
+    for run in indices.split(|index| *index == RESTART) {
+        match topology {
+            PrimitiveTopology::TriangleList => {
+                triangles.extend(run.chunks_exact(3).map(|tri| [tri[0], tri[1], tri[2]]))
+            }
+            PrimitiveTopology::TriangleStrip => {
+                triangles.extend(run.windows(3).enumerate().map(|(i, tri)| {
+                    if i % 2 == 0 {
+                        [tri[0], tri[1], tri[2]]
+                    } else {
+                        [tri[1], tri[0], tri[2]]
+                    }
+                }))
+            }
+            PrimitiveTopology::TriangleFan => triangles.extend(
+                run.windows(2)
+                    .skip(1)
+                    .map(|pair| [pair[0], pair[1], run[0]]),
+            ),
+            _ => {}
+        }
+    }
+    triangles
+}
+
+/// lines of `indices` drawn with `topology`
+pub(crate) fn lines_of(indices: &[usize], topology: PrimitiveTopology) -> Vec<[usize; 2]> {
+    let mut lines = vec![];
+    for run in indices.split(|index| *index == RESTART) {
+        match topology {
+            PrimitiveTopology::LineList => {
+                lines.extend(run.chunks_exact(2).map(|line| [line[0], line[1]]))
+            }
+            PrimitiveTopology::LineStrip => {
+                lines.extend(run.windows(2).map(|line| [line[0], line[1]]))
+            }
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// unique edges of triangles, in order of their first use
+fn triangle_edges(triangles: &[[usize; 3]]) -> Vec<[usize; 2]> {
+    let mut used = HashSet::new();
+    triangles
+        .iter()
+        .flat_map(|tri| [[tri[0], tri[1]], [tri[1], tri[2]], [tri[2], tri[0]]])
+        .filter(|[a, b]| used.insert((*a.min(b), *a.max(b))))
+        .collect()
+}
+
+/// chain lines that continue the previous one, restart otherwise
+fn line_strips(lines: &[[usize; 2]]) -> Vec<usize> {
+    let mut strips: Vec<usize> = vec![];
+    for &[a, b] in lines {
+        if strips.last() != Some(&a) {
+            if !strips.is_empty() {
+                strips.push(RESTART);
+            }
+            strips.push(a);
+        }
+        strips.push(b);
+    }
+    strips
+}
+
+/// greedy triangle strips, each strip starts from the first unused triangle with the rotation
+/// that gives the longest strip, and strips are separated by restart
+fn triangle_strips(triangles: &[[usize; 3]]) -> Vec<usize> {
+    // triangles containing each directed edge
+    let mut by_edge = HashMap::<(usize, usize), Vec<usize>>::new();
+    for (i, tri) in triangles.iter().enumerate() {
+        for k in 0..3 {
+            by_edge
+                .entry((tri[k], tri[(k + 1) % 3]))
+                .or_default()
+                .push(i);
+        }
+    }
+
+    let mut used = vec![false; triangles.len()];
+    let mut strips = vec![];
+    for start in 0..triangles.len() {
+        if used[start] {
+            continue;
+        }
+        let (strip, members) = (0..3)
+            .map(|r| {
+                let tri = triangles[start];
+                let first = [tri[r], tri[(r + 1) % 3], tri[(r + 2) % 3]];
+                extend_strip(first, start, triangles, &by_edge, &used)
+            })
+            .max_by_key(|(strip, _)| strip.len())
+            .unwrap();
+        for triangle in members {
+            used[triangle] = true;
+        }
+        if !strips.is_empty() {
+            strips.push(RESTART);
+        }
+        strips.extend(strip);
+    }
+    strips
+}
+
+/// extend a strip starting with triangle `first` while an unused triangle continues it with the same winding,
+/// return the strip and its triangles
+fn extend_strip(
+    first: [usize; 3],
+    first_index: usize,
+    triangles: &[[usize; 3]],
+    by_edge: &HashMap<(usize, usize), Vec<usize>>,
+    used: &[bool],
+) -> (Vec<usize>, Vec<usize>) {
+    let mut strip = first.to_vec();
+    let mut members = vec![first_index];
+    loop {
+        let (a, b) = (strip[strip.len() - 2], strip[strip.len() - 1]);
+        // the next triangle is `(a, b, c)` if its index in the strip is even, otherwise `(b, a, c)`
+        let edge = if strip.len().is_multiple_of(2) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let next = by_edge.get(&edge).and_then(|candidates| {
+            candidates
+                .iter()
+                .find(|t| !used[**t] && !members.contains(*t))
+        });
+        match next {
+            Some(&t) => {
+                let tri = triangles[t];
+                let k = (0..3)
+                    .find(|k| (tri[*k], tri[(k + 1) % 3]) == edge)
+                    .unwrap();
+                strip.push(tri[(k + 2) % 3]);
+                members.push(t);
+            }
+            None => return (strip, members),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::TriMeshBuilder;
+
+    fn triangle_list(num_vertices: usize, indices: Vec<u32>) -> TriMesh {
+        TriMeshBuilder::new()
+            .attribute(TriMesh::POSITION, vec![[0.0f32; 3]; num_vertices])
+            .indices(indices)
+            .build()
+            .unwrap()
+    }
+
+    /// triangles rotated to start at their smallest vertex, which keeps their winding
+    fn rotated(triangles: Vec<[usize; 3]>) -> Vec<[usize; 3]> {
+        triangles
+            .into_iter()
+            .map(|mut tri| {
+                let min = (0..3).min_by_key(|i| tri[*i]).unwrap();
+                tri.rotate_left(min);
+                tri
+            })
+            .collect()
+    }
+
+    #[test]
+    fn strip_round_trip() {
+        // two quads sharing an edge and a separate triangle
+        let indices = vec![0, 1, 2, 2, 1, 3, 2, 3, 4, 4, 3, 5, 6, 7, 8];
+        let mut mesh = triangle_list(9, indices);
+        let triangles = rotated(mesh.triangles());
+        for topology in [
+            PrimitiveTopology::TriangleStrip,
+            PrimitiveTopology::TriangleList,
+        ] {
+            mesh.convert_topology(topology).unwrap();
+            assert_eq!(mesh.topology(), topology);
+            assert_eq!(rotated(mesh.triangles()), triangles);
+            mesh.validate().unwrap();
+        }
+    }
+
+    #[test]
+    fn lines_of_triangles() {
+        let mut mesh = triangle_list(4, vec![0, 1, 2, 2, 1, 3]);
+        mesh.convert_topology(PrimitiveTopology::LineList).unwrap();
+        assert_eq!(mesh.lines(), vec![[0, 1], [1, 2], [2, 0], [1, 3], [3, 2]]);
+        mesh.convert_topology(PrimitiveTopology::LineStrip).unwrap();
+        assert_eq!(mesh.index_list(), vec![0, 1, 2, 0, RESTART, 1, 3, 2]);
+        assert_eq!(
+            mesh.convert_topology(PrimitiveTopology::TriangleList),
+            Err(TriMeshError::UnsupportedConversion {
+                from: PrimitiveTopology::LineStrip,
+                to: PrimitiveTopology::TriangleList,
+            })
+        );
+        mesh.convert_topology(PrimitiveTopology::PointList).unwrap();
+        assert_eq!(mesh.index_list(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn last_u16_vertex_of_strip_is_not_restart() {
+        let num_vertices = u16::MAX as usize + 1;
+        let mut mesh = triangle_list(num_vertices, vec![0, 65534, 65535]);
+        assert!(matches!(mesh.indices(), Some(MeshIndices::U16(_))));
+        mesh.convert_topology(PrimitiveTopology::TriangleStrip)
+            .unwrap();
+        assert!(matches!(mesh.indices(), Some(MeshIndices::U32(_))));
+        assert_eq!(rotated(mesh.triangles()), vec![[0, 65534, 65535]]);
+    }
+}
@@ -1,15 +1,19 @@
-use std::{borrow::Cow, collections::HashMap};
+use std::{borrow::Cow, collections::HashMap, hash::Hash};
 
-use super::VertexAttribute;
+use super::{AttributeError, AttributeValue, PrimitiveTopology, Submesh, VertexAttribute};
 
+#[derive(Clone)]
 pub enum MeshIndices {
     U16(Vec<u16>),
     U32(Vec<u32>),
 }
 
+#[derive(Clone)]
 pub struct TriMesh {
     pub(crate) vertex_attributes: HashMap<Cow<'static, str>, VertexAttribute>,
     pub(crate) indices: Option<MeshIndices>,
+    pub(crate) topology: PrimitiveTopology,
+    pub(crate) submeshes: Vec<Submesh>,
 }
 
 impl TriMesh {
@@ -27,6 +31,8 @@ impl TriMesh {
         Self {
             vertex_attributes,
             indices,
+            topology: PrimitiveTopology::TriangleList,
+            submeshes: vec![],
         }
     }
 
@@ -41,11 +47,49 @@ impl TriMesh {
         self.vertex_attributes.get_mut(&name.into())
     }
 
+    /// values of attribute `name` as a slice of `T`, e.g. `attribute_as::<[f32; 3]>(TriMesh::POSITION)`
+    pub fn attribute_as<T: AttributeValue>(
+        &self,
+        name: impl Into<Cow<'static, str>>,
+    ) -> Result<&[T], AttributeError> {
+        let name = name.into();
+        match self.vertex_attributes.get(&name) {
+            Some(attribute) => T::values(attribute).ok_or_else(|| AttributeError::FormatMismatch {
+                expected: T::FORMAT,
+                found: attribute.format(),
+                name,
+            }),
+            None => Err(AttributeError::Missing(name)),
+        }
+    }
+
+    pub fn attribute_as_mut<T: AttributeValue>(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+    ) -> Result<&mut [T], AttributeError> {
+        let name = name.into();
+        match self.vertex_attributes.get_mut(&name) {
+            Some(attribute) => {
+                let found = attribute.format();
+                T::values_mut(attribute).ok_or(AttributeError::FormatMismatch {
+                    expected: T::FORMAT,
+                    found,
+                    name,
+                })
+            }
+            None => Err(AttributeError::Missing(name)),
+        }
+    }
+
+    /// length of `TriMesh::POSITION`, or of the first attribute by name if there is no position
     pub fn num_vertices(&self) -> usize {
+        if let Some(positions) = self.vertex_attributes.get(Self::POSITION) {
+            return positions.len();
+        }
         self.vertex_attributes
-            .values()
-            .next()
-            .map_or(0, |attrib| attrib.len())
+            .iter()
+            .min_by_key(|(name, _)| *name)
+            .map_or(0, |(_, attrib)| attrib.len())
     }
 
     pub fn indices(&self) -> Option<&MeshIndices> {
@@ -71,9 +115,67 @@ impl TriMesh {
     pub fn set_indices(&mut self, indices: Option<MeshIndices>) {
         self.indices = indices;
     }
+
+    /// duplicate vertices so that corners sharing a vertex get their own copy when their `keys` differ,
+    /// `keys` has one entry for each corner of `triangles()`
+    ///
+    /// return the vertex of each corner after splitting, copies are appended after existing vertices
+    ///
+    /// strips and fans are converted to triangle lists first
+    pub(crate) fn split_vertices<Key: Eq + Hash>(&mut self, keys: &[Key]) -> Vec<usize> {
+        if self.topology.is_triangles() {
+            // converting between triangle topologies can't fail
+            let _ = self.convert_topology(PrimitiveTopology::TriangleList);
+        }
+        let corners = self.triangles().concat();
+        if self.indices.is_none() {
+            return corners;
+        }
+
+        let num_vertices = self.num_vertices();
+        let mut first_key = vec![None; num_vertices];
+        let mut copies = HashMap::new();
+        let mut sources = vec![];
+        let mut result = Vec::with_capacity(corners.len());
+        for (vertex, key) in corners.into_iter().zip(keys) {
+            let target = match first_key[vertex] {
+                None => {
+                    first_key[vertex] = Some(key);
+                    vertex
+                }
+                Some(first) if first == key => vertex,
+                Some(_) => *copies.entry((vertex, key)).or_insert_with(|| {
+                    sources.push(vertex);
+                    num_vertices + sources.len() - 1
+                }),
+            };
+            result.push(target);
+        }
+
+        if !sources.is_empty() {
+            for attribute in self.vertex_attributes.values_mut() {
+                attribute.duplicate(&sources);
+            }
+            self.indices = Some(MeshIndices::from_vec(
+                &result,
+                num_vertices + sources.len(),
+                matches!(self.indices, Some(MeshIndices::U16(_))),
+            ));
+        }
+        result
+    }
 }
 
 impl MeshIndices {
+    /// use `u16` when `prefer_u16` is set and all vertices can be indexed with it
+    pub(crate) fn from_vec(indices: &[usize], num_vertices: usize, prefer_u16: bool) -> Self {
+        if prefer_u16 && num_vertices <= u16::MAX as usize + 1 {
+            MeshIndices::U16(indices.iter().map(|i| *i as u16).collect())
+        } else {
+            MeshIndices::U32(indices.iter().map(|i| *i as u32).collect())
+        }
+    }
+
     pub fn len(&self) -> usize {
         match self {
             MeshIndices::U16(val) => val.len(),
@@ -81,6 +183,18 @@ impl MeshIndices {
         }
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// index that restarts strips and fans, the maximum value of the index type
+    pub fn restart_index(&self) -> usize {
+        match self {
+            MeshIndices::U16(_) => u16::MAX as usize,
+            MeshIndices::U32(_) => u32::MAX as usize,
+        }
+    }
+
     pub fn get(&self, index: usize) -> usize {
         match self {
             MeshIndices::U16(val) => val[index] as usize,
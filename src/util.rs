@@ -0,0 +1,90 @@
+//! small helpers shared by the mesh algorithms
+
+use std::ops::{Add, Div, Mul, Sub};
+
+/// `f32` or `f64`
+pub(crate) trait Real:
+    Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn sqrt(self) -> Self;
+}
+
+impl Real for f32 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    fn sqrt(self) -> Self {
+        self.sqrt()
+    }
+}
+
+impl Real for f64 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    fn sqrt(self) -> Self {
+        self.sqrt()
+    }
+}
+
+pub(crate) fn add<T: Real>(a: [T; 3], b: [T; 3]) -> [T; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub(crate) fn sub<T: Real>(a: [T; 3], b: [T; 3]) -> [T; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn scale<T: Real>(s: T, v: [T; 3]) -> [T; 3] {
+    [s * v[0], s * v[1], s * v[2]]
+}
+
+pub(crate) fn dot<T: Real>(a: [T; 3], b: [T; 3]) -> T {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross<T: Real>(a: [T; 3], b: [T; 3]) -> [T; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(crate) fn length<T: Real>(v: [T; 3]) -> T {
+    dot(v, v).sqrt()
+}
+
+/// `v` itself if it has zero length
+pub(crate) fn normalize<T: Real>(v: [T; 3]) -> [T; 3] {
+    let len = length(v);
+    if len > T::ZERO {
+        scale(T::ONE / len, v)
+    } else {
+        v
+    }
+}
+
+/// root of the set of `x` in a union-find forest, halving paths on the way
+pub(crate) fn find(parents: &mut [usize], mut x: usize) -> usize {
+    while parents[x] != x {
+        parents[x] = parents[parents[x]];
+        x = parents[x];
+    }
+    x
+}
+
+/// merge the sets of `a` and `b`
+pub(crate) fn union(parents: &mut [usize], a: usize, b: usize) {
+    let a = find(parents, a);
+    let b = find(parents, b);
+    parents[a] = b;
+}
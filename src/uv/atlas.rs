@@ -0,0 +1,410 @@
+use crate::trimesh::{AttributeError, TriMesh};
+use crate::util::{find, union};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasOptions {
+    /// width and height of the target texture in texels
+    pub resolution: u32,
+    /// gap between two charts and between a chart and the border, in texels
+    pub padding: u32,
+    /// allow charts to be rotated to reduce their bounding boxes
+    pub allow_rotation: bool,
+}
+
+impl Default for AtlasOptions {
+    fn default() -> Self {
+        Self {
+            resolution: 1024,
+            padding: 2,
+            allow_rotation: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AtlasStats {
+    pub num_charts: usize,
+    /// area covered by charts divided by area of the texture
+    pub efficiency: f32,
+    /// texels per unit of the original UVs, shared by all charts
+    pub texels_per_unit: f32,
+}
+
+/// UV indices and triangles of a chart
+pub(crate) struct ChartGeometry {
+    pub(crate) vertices: Vec<usize>,
+    pub(crate) triangles: Vec<[usize; 3]>,
+}
+
+/// pack `TriMesh::TEXCOORD` of `mesh` into the unit square
+///
+/// charts are the connected components of triangles, so vertices on UV seams must already be split
+pub fn pack_trimesh(
+    mesh: &mut TriMesh,
+    options: AtlasOptions,
+) -> Result<AtlasStats, AttributeError> {
+    let triangles = mesh.triangles();
+    let uvs = mesh.attribute_as_mut::<[f32; 2]>(TriMesh::TEXCOORD)?;
+
+    let mut parents = (0..uvs.len()).collect::<Vec<_>>();
+    for tri in &triangles {
+        union(&mut parents, tri[0], tri[1]);
+        union(&mut parents, tri[0], tri[2]);
+    }
+    let mut chart_of_root = vec![usize::MAX; uvs.len()];
+    let mut charts: Vec<ChartGeometry> = vec![];
+    let mut used = vec![false; uvs.len()];
+    for tri in triangles {
+        let root = find(&mut parents, tri[0]);
+        if chart_of_root[root] == usize::MAX {
+            chart_of_root[root] = charts.len();
+            charts.push(ChartGeometry {
+                vertices: vec![],
+                triangles: vec![],
+            });
+        }
+        let chart = &mut charts[chart_of_root[root]];
+        for v in tri {
+            if !used[v] {
+                used[v] = true;
+                chart.vertices.push(v);
+            }
+        }
+        chart.triangles.push(tri);
+    }
+
+    Ok(pack(uvs, &charts, options))
+}
+
+/// pack `charts` into the unit square, UVs are modified in place
+pub(crate) fn pack(
+    uvs: &mut [[f32; 2]],
+    charts: &[ChartGeometry],
+    options: AtlasOptions,
+) -> AtlasStats {
+    if charts.is_empty() {
+        return AtlasStats::default();
+    }
+
+    // orient charts and move them to origin
+    let mut sizes = Vec::with_capacity(charts.len());
+    for chart in charts {
+        let mut points = chart
+            .vertices
+            .iter()
+            .map(|v| [uvs[*v][0] as f64, uvs[*v][1] as f64])
+            .collect::<Vec<_>>();
+        if options.allow_rotation {
+            let angle = best_rotation(&points);
+            rotate(&mut points, angle);
+        }
+        let (min, max) = bounds(&points);
+        let mut size = [max[0] - min[0], max[1] - min[1]];
+        if options.allow_rotation && size[1] > size[0] {
+            points.iter_mut().for_each(|p| *p = [-p[1], p[0]]);
+            size = [size[1], size[0]];
+        }
+        let (min, _) = bounds(&points);
+        for (v, p) in chart.vertices.iter().zip(points) {
+            uvs[*v] = [(p[0] - min[0]) as f32, (p[1] - min[1]) as f32];
+        }
+        sizes.push(size);
+    }
+
+    let resolution = options.resolution as f64;
+    let padding = options.padding as f64;
+    let bin = (resolution - padding).max(1.0);
+    let mut order = (0..charts.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| sizes[*b][1].total_cmp(&sizes[*a][1]));
+
+    // the largest scale that still fits
+    let total_area = sizes.iter().map(|s| s[0] * s[1]).sum::<f64>();
+    let max_extent = sizes.iter().map(|s| s[0].max(s[1])).fold(0.0, f64::max);
+    let mut high = if total_area > 0.0 {
+        (resolution * resolution / total_area).sqrt()
+    } else {
+        resolution
+    };
+    if max_extent > 0.0 {
+        high = high.min((resolution - 2.0 * padding).max(1.0) / max_extent);
+    }
+    let mut low = 0.0;
+    let mut best = skyline_pack(&sizes, &order, 0.0, padding, bin);
+    for _ in 0..32 {
+        let mid = 0.5 * (low + high);
+        let (positions, used) = skyline_pack(&sizes, &order, mid, padding, bin);
+        if used[0] <= bin && used[1] <= bin {
+            low = mid;
+            best = (positions, used);
+        } else {
+            high = mid;
+        }
+    }
+    let scale = low;
+    let (positions, used) = best;
+    // charts can't fit even at a tiny scale, shrink the whole atlas
+    let extent = resolution.max(used[0] + padding).max(used[1] + padding);
+
+    let mut covered = 0.0;
+    for (chart, position) in charts.iter().zip(positions) {
+        for v in &chart.vertices {
+            let uv = uvs[*v];
+            uvs[*v] = [
+                ((position[0] + padding + uv[0] as f64 * scale) / extent) as f32,
+                ((position[1] + padding + uv[1] as f64 * scale) / extent) as f32,
+            ];
+        }
+        for tri in &chart.triangles {
+            let a = uvs[tri[0]];
+            let b = uvs[tri[1]];
+            let c = uvs[tri[2]];
+            let area = 0.5
+                * ((b[0] - a[0]) as f64 * (c[1] - a[1]) as f64
+                    - (b[1] - a[1]) as f64 * (c[0] - a[0]) as f64);
+            covered += area.abs();
+        }
+    }
+
+    AtlasStats {
+        num_charts: charts.len(),
+        efficiency: covered as f32,
+        texels_per_unit: (scale * resolution / extent) as f32,
+    }
+}
+
+/// bottom-left skyline packing of chart rectangles, return positions and used width and height in
+/// texels
+///
+/// a chart wider than `width` is put on top of everything, so the used width can exceed `width`
+fn skyline_pack(
+    sizes: &[[f64; 2]],
+    order: &[usize],
+    scale: f64,
+    padding: f64,
+    width: f64,
+) -> (Vec<[f64; 2]>, [f64; 2]) {
+    // segments of `(x, y, width)`
+    let mut skyline = vec![(0.0, 0.0, width)];
+    let mut positions = vec![[0.0; 2]; sizes.len()];
+    let mut used: [f64; 2] = [0.0, 0.0];
+    for &chart in order {
+        let w = (sizes[chart][0] * scale).ceil() + padding;
+        let h = (sizes[chart][1] * scale).ceil() + padding;
+
+        let mut best: Option<(usize, f64)> = None;
+        for i in 0..skyline.len() {
+            let x = skyline[i].0;
+            if x + w > width {
+                break;
+            }
+            let mut y: f64 = 0.0;
+            let mut covered = 0.0;
+            let mut j = i;
+            while covered < w && j < skyline.len() {
+                y = y.max(skyline[j].1);
+                covered += skyline[j].2;
+                j += 1;
+            }
+            if best.is_none_or(|(_, best_y)| y < best_y) {
+                best = Some((i, y));
+            }
+        }
+        let (i, y) = match best {
+            Some(best) => best,
+            // wider than the bin, put it on top of everything
+            None => (0, used[1]),
+        };
+
+        let x = skyline[i].0;
+        positions[chart] = [x, y];
+        used = [used[0].max(x + w), used[1].max(y + h)];
+
+        // replace covered segments with the new one
+        let mut j = i;
+        let mut end = skyline[i].0;
+        while j < skyline.len() && end < x + w {
+            end = skyline[j].0 + skyline[j].2;
+            j += 1;
+        }
+        let mut new_segments = vec![(x, y + h, w)];
+        if end > x + w {
+            new_segments.push((x + w, skyline[j - 1].1, end - x - w));
+        }
+        skyline.splice(i..j, new_segments);
+    }
+    (positions, used)
+}
+
+/// rotation angle that minimizes the area of the bounding box, checked along edges of the convex hull
+fn best_rotation(points: &[[f64; 2]]) -> f64 {
+    let hull = convex_hull(points);
+    let mut best = (f64::MAX, 0.0);
+    for i in 0..hull.len() {
+        let a = hull[i];
+        let b = hull[(i + 1) % hull.len()];
+        let angle = -(b[1] - a[1]).atan2(b[0] - a[0]);
+        let mut rotated = hull.clone();
+        rotate(&mut rotated, angle);
+        let (min, max) = bounds(&rotated);
+        let area = (max[0] - min[0]) * (max[1] - min[1]);
+        if area < best.0 {
+            best = (area, angle);
+        }
+    }
+    best.1
+}
+
+fn convex_hull(points: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let cross = |o: [f64; 2], a: [f64; 2], b: [f64; 2]| {
+        (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+    };
+    let mut hull: Vec<[f64; 2]> = Vec::with_capacity(2 * points.len());
+    for pass in 0..2 {
+        let start = hull.len();
+        let iter: Box<dyn Iterator<Item = &[f64; 2]>> = if pass == 0 {
+            Box::new(points.iter())
+        } else {
+            Box::new(points.iter().rev())
+        };
+        for p in iter {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], *p) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(*p);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+fn rotate(points: &mut [[f64; 2]], angle: f64) {
+    let (sin, cos) = angle.sin_cos();
+    for p in points {
+        *p = [cos * p[0] - sin * p[1], sin * p[0] + cos * p[1]];
+    }
+}
+
+fn bounds(points: &[[f64; 2]]) -> ([f64; 2], [f64; 2]) {
+    let mut min = [f64::MAX; 2];
+    let mut max = [f64::MIN; 2];
+    for p in points {
+        for i in 0..2 {
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+        }
+    }
+    (min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trimesh::TriMeshBuilder;
+
+    #[test]
+    fn packed_charts_do_not_overlap() {
+        // separate triangles and quads of different sizes and orientations
+        let mut positions = vec![];
+        let mut uvs = vec![];
+        let mut indices = vec![];
+        for chart in 0..12u32 {
+            let size = 1.0 + (chart % 4) as f32;
+            let angle = chart as f32 * 0.4;
+            let corners: &[[f32; 2]] = if chart % 2 == 0 {
+                &[[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [0.0, 1.0]]
+            } else {
+                &[[0.0, 0.0], [1.0, 0.0], [0.3, 1.5]]
+            };
+            let start = uvs.len() as u32;
+            for c in corners {
+                let (sin, cos) = angle.sin_cos();
+                let uv = [
+                    size * (cos * c[0] - sin * c[1]) + chart as f32,
+                    size * (sin * c[0] + cos * c[1]),
+                ];
+                uvs.push(uv);
+                positions.push([uv[0], uv[1], 0.0]);
+            }
+            indices.extend([start, start + 1, start + 2]);
+            if corners.len() == 4 {
+                indices.extend([start, start + 2, start + 3]);
+            }
+        }
+        let mut mesh = TriMeshBuilder::new()
+            .attribute(TriMesh::POSITION, positions)
+            .attribute(TriMesh::TEXCOORD, uvs)
+            .indices(indices)
+            .build()
+            .unwrap();
+
+        let stats = pack_trimesh(&mut mesh, AtlasOptions::default()).unwrap();
+        assert_eq!(stats.num_charts, 12);
+        assert!(stats.efficiency > 0.0 && stats.efficiency <= 1.0);
+
+        let triangles = mesh.triangles();
+        let uvs = mesh.attribute_as::<[f32; 2]>(TriMesh::TEXCOORD).unwrap();
+        assert!(uvs.iter().flatten().all(|x| (0.0..=1.0).contains(x)));
+        let boxes = triangles
+            .iter()
+            .map(|tri| (tri[0], bounds(&tri.map(|v| uvs[v].map(f64::from)))))
+            .collect::<Vec<_>>();
+        // triangles of the same chart may overlap their bounding boxes
+        let mut parents = (0..uvs.len()).collect::<Vec<_>>();
+        for tri in &triangles {
+            union(&mut parents, tri[0], tri[1]);
+            union(&mut parents, tri[0], tri[2]);
+        }
+        for (a, (min_a, max_a)) in &boxes {
+            assert!(min_a.iter().chain(max_a).all(|x| (0.0..=1.0).contains(x)));
+            for (b, (min_b, max_b)) in &boxes {
+                if find(&mut parents, *a) == find(&mut parents, *b) {
+                    continue;
+                }
+                let disjoint = (0..2).any(|i| max_a[i] <= min_b[i] || max_b[i] <= min_a[i]);
+                assert!(disjoint);
+            }
+        }
+    }
+
+    #[test]
+    fn wide_charts_stay_in_unit_square() {
+        // a long strip and small squares in a tiny texture with wide padding
+        let rects = [[40.0, 0.5], [1.0, 1.0], [0.5, 0.5], [2.0, 0.3]];
+        let mut uvs = vec![];
+        let mut indices = vec![];
+        for (i, [w, h]) in rects.iter().enumerate() {
+            let start = uvs.len() as u32;
+            let x = 50.0 * i as f32;
+            uvs.extend([[x, 0.0], [x + w, 0.0], [x + w, *h], [x, *h]]);
+            indices.extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+        }
+        let positions = uvs.iter().map(|uv| [uv[0], uv[1], 0.0]).collect::<Vec<_>>();
+        let mut mesh = TriMeshBuilder::new()
+            .attribute(TriMesh::POSITION, positions)
+            .attribute(TriMesh::TEXCOORD, uvs)
+            .indices(indices)
+            .build()
+            .unwrap();
+
+        for resolution in [4, 6, 8, 16, 64] {
+            let options = AtlasOptions {
+                resolution,
+                padding: 3,
+                allow_rotation: false,
+            };
+            let stats = pack_trimesh(&mut mesh, options).unwrap();
+            assert_eq!(stats.num_charts, 4);
+            let uvs = mesh.attribute_as::<[f32; 2]>(TriMesh::TEXCOORD).unwrap();
+            assert!(uvs.iter().flatten().all(|x| (0.0..=1.0).contains(x)));
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::halfedge::{FaceRef, HalfEdgeMesh, HalfEdgeRef, VertexRef};
+use crate::util::{dot, normalize};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChartOptions {
+    /// maximal angle in radians between normal of a face and average normal of its chart
+    pub max_normal_deviation: f32,
+    /// maximal number of faces in a chart, `0` means no limit
+    pub max_faces: usize,
+}
+
+impl Default for ChartOptions {
+    fn default() -> Self {
+        Self {
+            max_normal_deviation: std::f32::consts::FRAC_PI_3,
+            max_faces: usize::MAX,
+        }
+    }
+}
+
+/// chart id of each face, every chart is a topological disk
+pub struct Charts {
+    chart_of_face: Vec<usize>,
+    num_charts: usize,
+}
+
+/// split faces of `mesh` into disk-like charts by region growing on normal deviation (D-charts style)
+///
+/// a face joins the growing chart when its normal is close enough to the average normal of the chart
+/// and it touches the chart along a single path of edges, so that the chart stays a disk
+pub fn segment_charts<VData, EData, FData, Position>(
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+    options: ChartOptions,
+    position: Position,
+) -> Charts
+where
+    Position: Fn(&VData) -> [f32; 3],
+{
+    let num_faces = mesh.num_faces_with_boundary();
+    let mut normals = vec![[0.0; 3]; num_faces];
+    let mut areas = vec![0.0; num_faces];
+    for face in mesh.faces() {
+        if face.is_boundary(mesh) {
+            continue;
+        }
+        let (normal, area) = face_normal(mesh, &face, &position);
+        normals[face.id] = normal;
+        areas[face.id] = area;
+    }
+
+    let max_cost = 1.0 - options.max_normal_deviation.cos();
+    let max_faces = match options.max_faces {
+        0 => usize::MAX,
+        max_faces => max_faces,
+    };
+    let mut chart_of_face = vec![usize::MAX; num_faces];
+    let mut vertex_stamp = vec![usize::MAX; mesh.num_vertices()];
+    let mut num_charts = 0;
+    for seed in mesh.faces() {
+        if seed.is_boundary(mesh) || chart_of_face[seed.id] != usize::MAX {
+            continue;
+        }
+
+        let chart = num_charts;
+        num_charts += 1;
+        let mut chart_normal = [0.0; 3];
+        let mut chart_faces = 0;
+        let mut queue = BinaryHeap::new();
+        queue.push((Reverse(0), seed.id));
+
+        while let Some((Reverse(cost_bits), face)) = queue.pop() {
+            if chart_faces >= max_faces {
+                break;
+            }
+            if chart_of_face[face] != usize::MAX {
+                continue;
+            }
+            let fref = mesh.face_ref(face);
+            let cost = if chart_faces == 0 {
+                0.0
+            } else {
+                (1.0 - dot(normals[face], normalize(chart_normal))).max(0.0)
+            };
+            if cost > max_cost {
+                continue;
+            }
+            if cost.to_bits() > cost_bits {
+                queue.push((Reverse(cost.to_bits()), face));
+                continue;
+            }
+            if chart_faces > 0 && !keeps_disk(mesh, &fref, chart, &chart_of_face, &vertex_stamp) {
+                continue;
+            }
+
+            chart_of_face[face] = chart;
+            chart_faces += 1;
+            for i in 0..3 {
+                chart_normal[i] += normals[face][i] * areas[face];
+            }
+            let mut he = fref.halfedge(mesh);
+            loop {
+                vertex_stamp[he.vertex(mesh).id] = chart;
+                let neighbor = he.twin(mesh).face(mesh);
+                if !neighbor.is_boundary(mesh) && chart_of_face[neighbor.id] == usize::MAX {
+                    let cost = 1.0 - dot(normals[neighbor.id], normalize(chart_normal));
+                    queue.push((Reverse(cost.max(0.0).to_bits()), neighbor.id));
+                }
+                he = he.next(mesh);
+                if he == fref.halfedge(mesh) {
+                    break;
+                }
+            }
+        }
+    }
+
+    Charts {
+        chart_of_face,
+        num_charts,
+    }
+}
+
+impl Charts {
+    pub fn num_charts(&self) -> usize {
+        self.num_charts
+    }
+
+    /// return `None` for boundary faces
+    pub fn chart(&self, face: &FaceRef) -> Option<usize> {
+        self.chart_of_face
+            .get(face.id)
+            .copied()
+            .filter(|chart| *chart != usize::MAX)
+    }
+
+    /// one half-edge of each edge between two different charts
+    pub fn seams<VData, EData, FData>(
+        &self,
+        mesh: &HalfEdgeMesh<VData, EData, FData>,
+    ) -> Vec<HalfEdgeRef> {
+        mesh.halfedges()
+            .filter(|he| {
+                let twin = he.twin(mesh);
+                let chart = self.chart(&he.face(mesh));
+                let twin_chart = self.chart(&twin.face(mesh));
+                he.id < twin.id && chart.is_some() && twin_chart.is_some() && chart != twin_chart
+            })
+            .collect()
+    }
+
+    /// cut `mesh` along seams so that each chart becomes a connected component
+    ///
+    /// see `HalfEdgeMesh::cut_edges` for details about the returned vertices
+    pub fn cut<VData, EData, FData>(
+        self,
+        mesh: &mut HalfEdgeMesh<VData, EData, FData>,
+    ) -> Vec<(VertexRef, VertexRef)>
+    where
+        VData: Clone,
+        EData: Clone,
+        FData: Default,
+    {
+        let seams = self.seams(mesh);
+        mesh.cut_edges(&seams)
+    }
+}
+
+/// whether adding `face` to `chart` keeps it a disk: the face must touch the chart along one
+/// connected path of edges, and its other vertices must not be in the chart
+fn keeps_disk<VData, EData, FData>(
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+    face: &FaceRef,
+    chart: usize,
+    chart_of_face: &[usize],
+    vertex_stamp: &[usize],
+) -> bool {
+    let mut shared = vec![];
+    let mut vertices = vec![];
+    let mut he = face.halfedge(mesh);
+    loop {
+        let neighbor = he.twin(mesh).face(mesh);
+        shared.push(!neighbor.is_boundary(mesh) && chart_of_face[neighbor.id] == chart);
+        vertices.push(he.vertex(mesh).id);
+        he = he.next(mesh);
+        if he == face.halfedge(mesh) {
+            break;
+        }
+    }
+
+    let n = shared.len();
+    let num_shared = shared.iter().filter(|s| **s).count();
+    if num_shared == 0 || num_shared == n {
+        return false;
+    }
+    let runs = (0..n)
+        .filter(|i| shared[*i] && !shared[(i + n - 1) % n])
+        .count();
+    if runs != 1 {
+        return false;
+    }
+    // edge `i` goes from vertex `i` to vertex `i + 1`
+    (0..n).all(|i| shared[i] || shared[(i + n - 1) % n] || vertex_stamp[vertices[i]] != chart)
+}
+
+fn face_normal<VData, EData, FData, Position>(
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+    face: &FaceRef,
+    position: &Position,
+) -> ([f32; 3], f32)
+where
+    Position: Fn(&VData) -> [f32; 3],
+{
+    let mut points = vec![];
+    let mut he = face.halfedge(mesh);
+    loop {
+        points.push(position(he.vertex(mesh).data(mesh)));
+        he = he.next(mesh);
+        if he == face.halfedge(mesh) {
+            break;
+        }
+    }
+
+    // Newell's method
+    let mut normal = [0.0; 3];
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        normal[0] += (a[1] - b[1]) * (a[2] + b[2]);
+        normal[1] += (a[2] - b[2]) * (a[0] + b[0]);
+        normal[2] += (a[0] - b[0]) * (a[1] + b[1]);
+    }
+    let area = 0.5 * dot(normal, normal).sqrt();
+    (normalize(normal), area)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// `n` by `n` grid of flat quads split into triangles
+    fn grid(n: usize) -> HalfEdgeMesh<[f32; 3], (), ()> {
+        let vertices = (0..(n + 1) * (n + 1))
+            .map(|v| (v, [(v % (n + 1)) as f32, (v / (n + 1)) as f32, 0.0]))
+            .collect();
+        let mut faces = vec![];
+        for j in 0..n {
+            for i in 0..n {
+                let v = j * (n + 1) + i;
+                faces.push(vec![v, v + 1, v + n + 2]);
+                faces.push(vec![v, v + n + 2, v + n + 1]);
+            }
+        }
+        HalfEdgeMesh::new(faces, vertices, HashMap::new(), HashMap::new())
+    }
+
+    fn chart_sizes(mesh: &HalfEdgeMesh<[f32; 3], (), ()>, max_faces: usize) -> Vec<usize> {
+        let options = ChartOptions {
+            max_faces,
+            ..Default::default()
+        };
+        let charts = segment_charts(mesh, options, |p| *p);
+        let mut sizes = vec![0; charts.num_charts()];
+        for face in mesh.faces().filter(|face| !face.is_boundary(mesh)) {
+            sizes[charts.chart(&face).unwrap()] += 1;
+        }
+        sizes
+    }
+
+    #[test]
+    fn max_faces() {
+        let mesh = grid(3);
+        assert_eq!(chart_sizes(&mesh, 0), [18]);
+        assert_eq!(chart_sizes(&mesh, usize::MAX), [18]);
+        let sizes = chart_sizes(&mesh, 4);
+        assert!(sizes.iter().all(|size| (1..=4).contains(size)));
+        assert_eq!(sizes.iter().sum::<usize>(), 18);
+    }
+}
@@ -0,0 +1,8 @@
+mod atlas;
+mod chart;
+mod param;
+mod sparse;
+
+pub use atlas::*;
+pub use chart::*;
+pub use param::*;
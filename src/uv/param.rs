@@ -0,0 +1,850 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::halfedge::{HalfEdgeMesh, HalfEdgeRef, VertexRef};
+use crate::util::{cross, dot, find, sub, union};
+
+use super::{
+    atlas::{self, AtlasOptions, AtlasStats, ChartGeometry},
+    sparse::SparseMatrix,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamMethod {
+    /// uniform weights, boundary fixed to a circle
+    Tutte,
+    /// cotangent weights, boundary fixed to a circle
+    Harmonic,
+    /// least squares conformal map with free boundary
+    Lscm,
+    /// as-rigid-as-possible map, initialized with LSCM and refined by `iterations` local-global steps
+    Arap { iterations: u32 },
+}
+
+#[derive(Debug)]
+pub struct ParamError {
+    info: String,
+}
+
+impl ParamError {
+    pub(crate) fn new(info: String) -> Self {
+        Self { info }
+    }
+}
+
+impl std::fmt::Display for ParamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "failed to parameterize mesh, inner error: '{}'",
+            self.info
+        )
+    }
+}
+
+impl std::error::Error for ParamError {}
+
+/// distortion of a parameterization, faces are fan-triangulated and statistics are area weighted
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Distortion {
+    /// mean of `|angle_uv - angle_3d|` over all corners, in radians
+    pub mean_angle_error: f32,
+    pub max_angle_error: f32,
+    /// mean of `sigma_max / sigma_min` of the jacobians, `1` means conformal
+    pub mean_conformal: f32,
+    pub max_conformal: f32,
+    /// mean of `max(r, 1 / r)` where `r` is the relative area ratio of a triangle, `1` means area preserving
+    pub mean_area: f32,
+    pub max_area: f32,
+    pub flipped_faces: usize,
+}
+
+/// per-vertex UVs of a halfedge mesh
+///
+/// each connected component of the mesh is a chart, UVs of a chart start at origin and are scaled
+/// so that its UV area equals its surface area
+pub struct Parameterization {
+    pub(crate) uvs: Vec<[f32; 2]>,
+    pub(crate) charts: Vec<ChartGeometry>,
+}
+
+/// parameterize every connected component of `mesh`, each of which must be a topological disk
+///
+/// vertices that don't belong to any face get UV `[0, 0]`
+pub fn parameterize<VData, EData, FData, Position>(
+    mesh: &HalfEdgeMesh<VData, EData, FData>,
+    method: ParamMethod,
+    position: Position,
+) -> Result<Parameterization, ParamError>
+where
+    Position: Fn(&VData) -> [f32; 3],
+{
+    let patches = Patch::collect(mesh, &position)?;
+
+    let mut uvs = vec![[0.0; 2]; mesh.num_vertices()];
+    let mut charts = Vec::with_capacity(patches.len());
+    for patch in patches {
+        let patch_uvs = match method {
+            ParamMethod::Tutte => patch.fixed_boundary(true)?,
+            ParamMethod::Harmonic => patch.fixed_boundary(false)?,
+            ParamMethod::Lscm => patch.lscm()?,
+            ParamMethod::Arap { iterations } => {
+                let init = patch.lscm()?;
+                patch.arap(init, iterations)?
+            }
+        };
+        let patch_uvs = patch.normalize(patch_uvs);
+        for (local, uv) in patch_uvs.into_iter().enumerate() {
+            uvs[patch.vertices[local]] = [uv[0] as f32, uv[1] as f32];
+        }
+        charts.push(ChartGeometry {
+            triangles: patch
+                .triangles
+                .iter()
+                .map(|tri| tri.map(|v| patch.vertices[v]))
+                .collect(),
+            vertices: patch.vertices,
+        });
+    }
+
+    Ok(Parameterization { uvs, charts })
+}
+
+impl Parameterization {
+    pub fn uv(&self, vertex: &VertexRef) -> [f32; 2] {
+        self.uvs[vertex.id]
+    }
+
+    /// UV of the corner at the source vertex of `halfedge`
+    pub fn corner_uv<VData, EData, FData>(
+        &self,
+        mesh: &HalfEdgeMesh<VData, EData, FData>,
+        halfedge: &HalfEdgeRef,
+    ) -> [f32; 2] {
+        self.uv(&halfedge.vertex(mesh))
+    }
+
+    pub fn num_charts(&self) -> usize {
+        self.charts.len()
+    }
+
+    /// vertices of each chart
+    pub fn chart_vertices(&self, chart: usize) -> impl Iterator<Item = usize> + '_ {
+        self.charts[chart].vertices.iter().copied()
+    }
+
+    /// uniformly scale and translate all UVs into `[0, 1]^2`
+    pub fn fit_unit_square(&mut self) {
+        let mut min = [f32::MAX; 2];
+        let mut max = [f32::MIN; 2];
+        for uv in self
+            .charts
+            .iter()
+            .flat_map(|c| &c.vertices)
+            .map(|v| self.uvs[*v])
+        {
+            for i in 0..2 {
+                min[i] = min[i].min(uv[i]);
+                max[i] = max[i].max(uv[i]);
+            }
+        }
+        let extent = (max[0] - min[0]).max(max[1] - min[1]);
+        if extent <= 0.0 {
+            return;
+        }
+        for v in self.charts.iter().flat_map(|c| &c.vertices) {
+            let uv = &mut self.uvs[*v];
+            uv[0] = (uv[0] - min[0]) / extent;
+            uv[1] = (uv[1] - min[1]) / extent;
+        }
+    }
+
+    /// pack all charts into the unit square
+    pub fn pack(&mut self, options: AtlasOptions) -> AtlasStats {
+        atlas::pack(&mut self.uvs, &self.charts, options)
+    }
+
+    pub fn write_to_vertices<VData, EData, FData, Setter>(
+        &self,
+        mesh: &mut HalfEdgeMesh<VData, EData, FData>,
+        mut setter: Setter,
+    ) where
+        Setter: FnMut(&mut VData, [f32; 2]),
+    {
+        for vref in mesh.vertices() {
+            if vref.id < self.uvs.len() {
+                setter(vref.data_mut(mesh), self.uvs[vref.id]);
+            }
+        }
+    }
+
+    pub fn distortion<VData, EData, FData, Position>(
+        &self,
+        mesh: &HalfEdgeMesh<VData, EData, FData>,
+        position: Position,
+    ) -> Distortion
+    where
+        Position: Fn(&VData) -> [f32; 3],
+    {
+        let mut triangles = vec![];
+        for face in face_loops(mesh) {
+            for i in 1..face.len() - 1 {
+                let tri = [face[0], face[i], face[i + 1]];
+                let p = tri.map(|v| to_f64(position(mesh.vertex_data(&mesh.vertex_ref(v)))));
+                let q = tri.map(|v| [self.uvs[v][0] as f64, self.uvs[v][1] as f64]);
+                triangles.push((p, q));
+            }
+        }
+
+        let area_3d = triangles.iter().map(|(p, _)| triangle_area(p)).sum::<f64>();
+        let area_uv = triangles
+            .iter()
+            .map(|(_, q)| signed_area_2d(q).abs())
+            .sum::<f64>();
+        if area_3d <= 0.0 || area_uv <= 0.0 {
+            return Distortion::default();
+        }
+        let global_ratio = area_uv / area_3d;
+
+        let mut result = Distortion::default();
+        let mut angle_sum = 0.0;
+        let mut conformal_sum = 0.0;
+        let mut area_sum = 0.0;
+        let mut weight_sum = 0.0;
+        for (p, q) in &triangles {
+            let weight = triangle_area(p);
+            if weight <= 0.0 {
+                continue;
+            }
+            let x = flatten(p);
+            let j = jacobian(&x, q);
+            let (sigma_max, sigma_min) = singular_values(j);
+            if j[0][0] * j[1][1] - j[0][1] * j[1][0] < 0.0 {
+                result.flipped_faces += 1;
+            }
+
+            let conformal = if sigma_min > 0.0 {
+                sigma_max / sigma_min
+            } else {
+                f64::MAX
+            };
+            let ratio = signed_area_2d(q).abs() / weight / global_ratio;
+            let area = if ratio > 0.0 {
+                ratio.max(1.0 / ratio)
+            } else {
+                f64::MAX
+            };
+            let mut angle = 0.0;
+            for k in 0..3 {
+                let error = (corner_angle_2d(&x, k) - corner_angle_2d(q, k)).abs();
+                result.max_angle_error = result.max_angle_error.max(error as f32);
+                angle += error / 3.0;
+            }
+
+            result.max_conformal = result.max_conformal.max(conformal as f32);
+            result.max_area = result.max_area.max(area as f32);
+            angle_sum += weight * angle;
+            conformal_sum += weight * conformal.min(f32::MAX as f64);
+            area_sum += weight * area.min(f32::MAX as f64);
+            weight_sum += weight;
+        }
+        if weight_sum > 0.0 {
+            result.mean_angle_error = (angle_sum / weight_sum) as f32;
+            result.mean_conformal = (conformal_sum / weight_sum) as f32;
+            result.mean_area = (area_sum / weight_sum) as f32;
+        }
+        result
+    }
+}
+
+/// a connected component of the mesh with local vertex indices
+struct Patch {
+    vertices: Vec<usize>,
+    positions: Vec<[f64; 3]>,
+    triangles: Vec<[usize; 3]>,
+    /// boundary loop, ordered along the orientation of the faces
+    boundary: Vec<usize>,
+}
+
+impl Patch {
+    fn collect<VData, EData, FData, Position>(
+        mesh: &HalfEdgeMesh<VData, EData, FData>,
+        position: &Position,
+    ) -> Result<Vec<Patch>, ParamError>
+    where
+        Position: Fn(&VData) -> [f32; 3],
+    {
+        let num_vertices = mesh.num_vertices();
+        let mut parents = (0..num_vertices).collect::<Vec<_>>();
+        let mut faces = vec![];
+        let mut boundaries = vec![];
+        for face in mesh.faces() {
+            let mut he = face.halfedge(mesh);
+            let mut vertices = vec![];
+            let mut edges = vec![];
+            loop {
+                vertices.push(he.vertex(mesh).id);
+                edges.push(mesh.halfedges[he.id].edge);
+                he = he.next(mesh);
+                if he == face.halfedge(mesh) {
+                    break;
+                }
+            }
+            if face.is_boundary(mesh) {
+                vertices.reverse();
+                boundaries.push(vertices);
+            } else {
+                for v in &vertices[1..] {
+                    union(&mut parents, vertices[0], *v);
+                }
+                faces.push((vertices, edges));
+            }
+        }
+
+        let mut patch_of_root = HashMap::new();
+        let mut patches: Vec<(Patch, HashSet<usize>, usize)> = vec![];
+        let mut local = vec![usize::MAX; num_vertices];
+        for (vertices, edges) in faces {
+            let root = find(&mut parents, vertices[0]);
+            let patch_id = *patch_of_root.entry(root).or_insert_with(|| {
+                patches.push((
+                    Patch {
+                        vertices: vec![],
+                        positions: vec![],
+                        triangles: vec![],
+                        boundary: vec![],
+                    },
+                    HashSet::new(),
+                    0,
+                ));
+                patches.len() - 1
+            });
+            let (patch, patch_edges, num_faces) = &mut patches[patch_id];
+            *num_faces += 1;
+            patch_edges.extend(edges);
+            for v in &vertices {
+                if local[*v] == usize::MAX {
+                    local[*v] = patch.vertices.len();
+                    patch.vertices.push(*v);
+                    let p = position(mesh.vertex_data(&mesh.vertex_ref(*v)));
+                    patch.positions.push(to_f64(p));
+                }
+            }
+            for i in 1..vertices.len() - 1 {
+                patch.triangles.push([
+                    local[vertices[0]],
+                    local[vertices[i]],
+                    local[vertices[i + 1]],
+                ]);
+            }
+        }
+
+        let mut num_boundaries = vec![0; patches.len()];
+        for boundary in boundaries {
+            if let Some(patch_id) = patch_of_root.get(&find(&mut parents, boundary[0])) {
+                num_boundaries[*patch_id] += 1;
+                patches[*patch_id].0.boundary = boundary.iter().map(|v| local[*v]).collect();
+            }
+        }
+
+        let mut result = Vec::with_capacity(patches.len());
+        for (id, (patch, edges, num_faces)) in patches.into_iter().enumerate() {
+            let euler = patch.vertices.len() as i64 - edges.len() as i64 + num_faces as i64;
+            if euler != 1 || num_boundaries[id] != 1 {
+                return Err(ParamError::new(format!(
+                    "component {} is not a topological disk (euler characteristic {}, {} boundary loops)",
+                    id, euler, num_boundaries[id]
+                )));
+            }
+            result.push(patch);
+        }
+        Ok(result)
+    }
+
+    fn cotangent_weights(&self) -> HashMap<(usize, usize), f64> {
+        let mut weights = HashMap::new();
+        for tri in &self.triangles {
+            let cot = cotangents(&self.positions, tri);
+            for k in 0..3 {
+                let (a, b) = (tri[(k + 1) % 3], tri[(k + 2) % 3]);
+                *weights.entry((a.min(b), a.max(b))).or_insert(0.0) += 0.5 * cot[k];
+            }
+        }
+        weights
+    }
+
+    fn uniform_weights(&self) -> HashMap<(usize, usize), f64> {
+        let mut weights = HashMap::new();
+        for tri in &self.triangles {
+            for k in 0..3 {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                weights.insert((a.min(b), a.max(b)), 1.0);
+            }
+        }
+        weights
+    }
+
+    fn area(&self) -> f64 {
+        self.triangles
+            .iter()
+            .map(|tri| triangle_area(&tri.map(|v| self.positions[v])))
+            .sum()
+    }
+
+    /// Tutte or harmonic map with boundary fixed to a circle of the same area as the patch
+    fn fixed_boundary(&self, uniform: bool) -> Result<Vec<[f64; 2]>, ParamError> {
+        let weights = if uniform {
+            self.uniform_weights()
+        } else {
+            self.cotangent_weights()
+        };
+
+        let n = self.positions.len();
+        let mut uvs = vec![[0.0; 2]; n];
+        let mut fixed = vec![false; n];
+        let lengths = (0..self.boundary.len())
+            .map(|i| {
+                let a = self.positions[self.boundary[i]];
+                let b = self.positions[self.boundary[(i + 1) % self.boundary.len()]];
+                distance(a, b)
+            })
+            .collect::<Vec<_>>();
+        let total_length = lengths.iter().sum::<f64>();
+        let radius = (self.area() / std::f64::consts::PI).sqrt();
+        let mut arc = 0.0;
+        for (i, v) in self.boundary.iter().enumerate() {
+            let theta = if total_length > 0.0 {
+                2.0 * std::f64::consts::PI * arc / total_length
+            } else {
+                2.0 * std::f64::consts::PI * i as f64 / self.boundary.len() as f64
+            };
+            uvs[*v] = [radius * theta.cos(), radius * theta.sin()];
+            fixed[*v] = true;
+            arc += lengths[i];
+        }
+
+        let mut index = vec![usize::MAX; n];
+        let mut num_free = 0;
+        for v in 0..n {
+            if !fixed[v] {
+                index[v] = num_free;
+                num_free += 1;
+            }
+        }
+
+        let mut triplets = vec![];
+        let mut rhs = vec![[0.0; 2]; num_free];
+        for ((a, b), w) in weights {
+            for (i, j) in [(a, b), (b, a)] {
+                if fixed[i] {
+                    continue;
+                }
+                triplets.push((index[i], index[i], w));
+                if fixed[j] {
+                    rhs[index[i]][0] += w * uvs[j][0];
+                    rhs[index[i]][1] += w * uvs[j][1];
+                } else {
+                    triplets.push((index[i], index[j], -w));
+                }
+            }
+        }
+        let matrix = SparseMatrix::from_triplets(num_free, triplets);
+        for c in 0..2 {
+            let b = rhs.iter().map(|r| r[c]).collect::<Vec<_>>();
+            let mut x = vec![0.0; num_free];
+            if !matrix.solve(&b, &mut x) {
+                return Err(ParamError::new(
+                    "linear solver didn't converge, mesh may contain degenerate faces".to_owned(),
+                ));
+            }
+            for v in 0..n {
+                if !fixed[v] {
+                    uvs[v][c] = x[index[v]];
+                }
+            }
+        }
+
+        Ok(uvs)
+    }
+
+    /// least squares conformal map, minimizing `E_dirichlet - A` with two boundary vertices pinned
+    fn lscm(&self) -> Result<Vec<[f64; 2]>, ParamError> {
+        let n = self.positions.len();
+
+        let farthest = |from: usize| {
+            *self
+                .boundary
+                .iter()
+                .max_by(|a, b| {
+                    let da = distance(self.positions[from], self.positions[**a]);
+                    let db = distance(self.positions[from], self.positions[**b]);
+                    da.total_cmp(&db)
+                })
+                .unwrap()
+        };
+        let pin1 = farthest(self.boundary[0]);
+        let pin0 = farthest(pin1);
+        let mut x = vec![0.0; 2 * n];
+        x[pin1] = distance(self.positions[pin0], self.positions[pin1]);
+
+        // unknowns are `[u_0, .., u_n-1, v_0, .., v_n-1]`
+        let mut triplets = vec![];
+        for ((a, b), w) in self.cotangent_weights() {
+            for offset in [0, n] {
+                triplets.push((a + offset, a + offset, w));
+                triplets.push((b + offset, b + offset, w));
+                triplets.push((a + offset, b + offset, -w));
+                triplets.push((b + offset, a + offset, -w));
+            }
+        }
+        for i in 0..self.boundary.len() {
+            let a = self.boundary[i];
+            let b = self.boundary[(i + 1) % self.boundary.len()];
+            // A = 0.5 * sum(u_a * v_b - u_b * v_a)
+            triplets.push((a, n + b, -0.5));
+            triplets.push((n + b, a, -0.5));
+            triplets.push((b, n + a, 0.5));
+            triplets.push((n + a, b, 0.5));
+        }
+
+        let pinned = |i: usize| i % n == pin0 || i % n == pin1;
+        let mut index = vec![usize::MAX; 2 * n];
+        let mut num_free = 0;
+        for (i, index) in index.iter_mut().enumerate() {
+            if !pinned(i) {
+                *index = num_free;
+                num_free += 1;
+            }
+        }
+        let mut free_triplets = Vec::with_capacity(triplets.len());
+        let mut rhs = vec![0.0; num_free];
+        for (i, j, w) in triplets {
+            if pinned(i) {
+                continue;
+            }
+            if pinned(j) {
+                rhs[index[i]] -= w * x[j];
+            } else {
+                free_triplets.push((index[i], index[j], w));
+            }
+        }
+        let matrix = SparseMatrix::from_triplets(num_free, free_triplets);
+        let mut free = vec![0.0; num_free];
+        if !matrix.solve(&rhs, &mut free) {
+            return Err(ParamError::new(
+                "linear solver didn't converge, mesh may contain degenerate faces".to_owned(),
+            ));
+        }
+        for i in 0..2 * n {
+            if !pinned(i) {
+                x[i] = free[index[i]];
+            }
+        }
+
+        Ok(self.orient((0..n).map(|v| [x[v], x[n + v]]).collect()))
+    }
+
+    /// local-global ARAP (Liu et al. 2008) starting from `uvs`
+    fn arap(&self, mut uvs: Vec<[f64; 2]>, iterations: u32) -> Result<Vec<[f64; 2]>, ParamError> {
+        let n = self.positions.len();
+        let flat = self
+            .triangles
+            .iter()
+            .map(|tri| flatten(&tri.map(|v| self.positions[v])))
+            .collect::<Vec<_>>();
+        // cotangent of the angle opposite to edge `(k, k + 1)`
+        let cot = self
+            .triangles
+            .iter()
+            .map(|tri| {
+                let c = cotangents(&self.positions, tri);
+                [c[2], c[0], c[1]]
+            })
+            .collect::<Vec<_>>();
+
+        let pin = 0;
+        let mut triplets = vec![(pin, pin, 1.0)];
+        let mut pin_coupling = vec![];
+        for (tri, cot) in self.triangles.iter().zip(&cot) {
+            for k in 0..3 {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                for (i, j) in [(a, b), (b, a)] {
+                    if i == pin {
+                        continue;
+                    }
+                    triplets.push((i, i, cot[k]));
+                    if j == pin {
+                        pin_coupling.push((i, cot[k]));
+                    } else {
+                        triplets.push((i, j, -cot[k]));
+                    }
+                }
+            }
+        }
+        let matrix = SparseMatrix::from_triplets(n, triplets);
+
+        for _ in 0..iterations {
+            let mut rhs = vec![[0.0; 2]; n];
+            for ((tri, x), cot) in self.triangles.iter().zip(&flat).zip(&cot) {
+                // local step: best rotation of the triangle
+                let mut s = [[0.0; 2]; 2];
+                for (k, w) in cot.iter().enumerate() {
+                    let (a, b) = (k, (k + 1) % 3);
+                    let du = sub2(uvs[tri[a]], uvs[tri[b]]);
+                    let dx = sub2(x[a], x[b]);
+                    for r in 0..2 {
+                        for c in 0..2 {
+                            s[r][c] += w * du[r] * dx[c];
+                        }
+                    }
+                }
+                let theta = (s[1][0] - s[0][1]).atan2(s[0][0] + s[1][1]);
+                let (sin, cos) = theta.sin_cos();
+
+                for (k, w) in cot.iter().enumerate() {
+                    let (a, b) = (k, (k + 1) % 3);
+                    let dx = sub2(x[a], x[b]);
+                    let rotated = [cos * dx[0] - sin * dx[1], sin * dx[0] + cos * dx[1]];
+                    for c in 0..2 {
+                        rhs[tri[a]][c] += w * rotated[c];
+                        rhs[tri[b]][c] -= w * rotated[c];
+                    }
+                }
+            }
+
+            // global step
+            for c in 0..2 {
+                let mut b = rhs.iter().map(|r| r[c]).collect::<Vec<_>>();
+                b[pin] = uvs[pin][c];
+                for (i, w) in &pin_coupling {
+                    b[*i] += w * uvs[pin][c];
+                }
+                let mut x = uvs.iter().map(|uv| uv[c]).collect::<Vec<_>>();
+                if !matrix.solve(&b, &mut x) {
+                    return Err(ParamError::new(
+                        "linear solver didn't converge, mesh may contain degenerate faces"
+                            .to_owned(),
+                    ));
+                }
+                for (uv, x) in uvs.iter_mut().zip(x) {
+                    uv[c] = x;
+                }
+            }
+        }
+
+        Ok(uvs)
+    }
+
+    fn signed_area_uv(&self, uvs: &[[f64; 2]]) -> f64 {
+        self.triangles
+            .iter()
+            .map(|tri| signed_area_2d(&tri.map(|v| uvs[v])))
+            .sum()
+    }
+
+    /// mirror the chart if most of it is flipped
+    fn orient(&self, mut uvs: Vec<[f64; 2]>) -> Vec<[f64; 2]> {
+        if self.signed_area_uv(&uvs) < 0.0 {
+            uvs.iter_mut().for_each(|uv| uv[0] = -uv[0]);
+        }
+        uvs
+    }
+
+    /// fix orientation, scale UV area to surface area and move the chart to origin
+    fn normalize(&self, uvs: Vec<[f64; 2]>) -> Vec<[f64; 2]> {
+        let mut uvs = self.orient(uvs);
+        let signed_area = self.signed_area_uv(&uvs);
+        let scale = if signed_area != 0.0 {
+            (self.area() / signed_area.abs()).sqrt()
+        } else {
+            1.0
+        };
+
+        let mut min = [f64::MAX; 2];
+        for uv in &uvs {
+            min[0] = min[0].min(uv[0]);
+            min[1] = min[1].min(uv[1]);
+        }
+        uvs.iter_mut().for_each(|uv| {
+            uv[0] = (uv[0] - min[0]) * scale;
+            uv[1] = (uv[1] - min[1]) * scale;
+        });
+        uvs
+    }
+}
+
+fn face_loops<VData, EData, FData>(mesh: &HalfEdgeMesh<VData, EData, FData>) -> Vec<Vec<usize>> {
+    mesh.faces()
+        .filter(|face| !face.is_boundary(mesh))
+        .map(|face| {
+            let mut he = face.halfedge(mesh);
+            let mut vertices = vec![];
+            loop {
+                vertices.push(he.vertex(mesh).id);
+                he = he.next(mesh);
+                if he == face.halfedge(mesh) {
+                    break;
+                }
+            }
+            vertices
+        })
+        .collect()
+}
+
+fn to_f64(p: [f32; 3]) -> [f64; 3] {
+    [p[0] as f64, p[1] as f64, p[2] as f64]
+}
+
+fn sub2(a: [f64; 2], b: [f64; 2]) -> [f64; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn distance(a: [f64; 3], b: [f64; 3]) -> f64 {
+    let d = sub(a, b);
+    dot(d, d).sqrt()
+}
+
+fn triangle_area(p: &[[f64; 3]; 3]) -> f64 {
+    let c = cross(sub(p[1], p[0]), sub(p[2], p[0]));
+    0.5 * dot(c, c).sqrt()
+}
+
+fn signed_area_2d(q: &[[f64; 2]; 3]) -> f64 {
+    let a = sub2(q[1], q[0]);
+    let b = sub2(q[2], q[0]);
+    0.5 * (a[0] * b[1] - a[1] * b[0])
+}
+
+/// cotangent of the angle at each corner of `tri`
+fn cotangents(positions: &[[f64; 3]], tri: &[usize; 3]) -> [f64; 3] {
+    let mut result = [0.0; 3];
+    for (k, result) in result.iter_mut().enumerate() {
+        let a = positions[tri[k]];
+        let e1 = sub(positions[tri[(k + 1) % 3]], a);
+        let e2 = sub(positions[tri[(k + 2) % 3]], a);
+        let sin = dot(cross(e1, e2), cross(e1, e2)).sqrt();
+        if sin > f64::EPSILON * dot(e1, e1).max(dot(e2, e2)) {
+            *result = dot(e1, e2) / sin;
+        }
+    }
+    result
+}
+
+/// isometric 2D coordinates of a 3D triangle
+fn flatten(p: &[[f64; 3]; 3]) -> [[f64; 2]; 3] {
+    let e1 = sub(p[1], p[0]);
+    let e2 = sub(p[2], p[0]);
+    let len = dot(e1, e1).sqrt();
+    if len <= 0.0 {
+        return [[0.0; 2]; 3];
+    }
+    let x = dot(e2, e1) / len;
+    let c = cross(e1, e2);
+    let y = dot(c, c).sqrt() / len;
+    [[0.0, 0.0], [len, 0.0], [x, y]]
+}
+
+fn jacobian(x: &[[f64; 2]; 3], q: &[[f64; 2]; 3]) -> [[f64; 2]; 2] {
+    let dx1 = sub2(x[1], x[0]);
+    let dx2 = sub2(x[2], x[0]);
+    let dq1 = sub2(q[1], q[0]);
+    let dq2 = sub2(q[2], q[0]);
+    let det = dx1[0] * dx2[1] - dx2[0] * dx1[1];
+    if det == 0.0 {
+        return [[0.0; 2]; 2];
+    }
+    // J = [dq1 dq2] * inverse([dx1 dx2])
+    let inv = [[dx2[1] / det, -dx2[0] / det], [-dx1[1] / det, dx1[0] / det]];
+    let mut j = [[0.0; 2]; 2];
+    for (r, row) in j.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            *value = dq1[r] * inv[0][c] + dq2[r] * inv[1][c];
+        }
+    }
+    j
+}
+
+fn singular_values(j: [[f64; 2]; 2]) -> (f64, f64) {
+    let e = (j[0][0] + j[1][1]) * 0.5;
+    let f = (j[0][0] - j[1][1]) * 0.5;
+    let g = (j[1][0] + j[0][1]) * 0.5;
+    let h = (j[1][0] - j[0][1]) * 0.5;
+    let q = (e * e + h * h).sqrt();
+    let r = (f * f + g * g).sqrt();
+    (q + r, (q - r).abs())
+}
+
+fn corner_angle_2d(q: &[[f64; 2]; 3], k: usize) -> f64 {
+    let e1 = sub2(q[(k + 1) % 3], q[k]);
+    let e2 = sub2(q[(k + 2) % 3], q[k]);
+    let cross = e1[0] * e2[1] - e1[1] * e2[0];
+    let dot = e1[0] * e2[0] + e1[1] * e2[1];
+    cross.abs().atan2(dot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// irregular triangulation of a flat patch in the plane `x + y + z = 0`
+    fn flat_patch() -> HalfEdgeMesh<[f32; 3], (), ()> {
+        let n = 4;
+        let u = [0.6f32, -0.6, 0.0];
+        let v = [-0.3f32, -0.3, 0.6];
+        let mut vertices = HashMap::new();
+        for j in 0..n {
+            for i in 0..n {
+                // interior vertices are jittered so that the triangles differ
+                let jitter = if (1..n - 1).contains(&i) && (1..n - 1).contains(&j) {
+                    0.15 * ((i * 7 + j * 3) % 5) as f32 / 5.0
+                } else {
+                    0.0
+                };
+                let (a, b) = (i as f32 + jitter, j as f32 - jitter);
+                vertices.insert(j * n + i, [0, 1, 2].map(|k| a * u[k] + b * v[k]));
+            }
+        }
+        let mut faces = vec![];
+        for j in 0..n - 1 {
+            for i in 0..n - 1 {
+                let v = j * n + i;
+                faces.push(vec![v, v + 1, v + n + 1]);
+                faces.push(vec![v, v + n + 1, v + n]);
+            }
+        }
+        HalfEdgeMesh::new(faces, vertices, HashMap::new(), HashMap::new())
+    }
+
+    fn assert_isometric(method: ParamMethod) {
+        let mesh = flat_patch();
+        let param = parameterize(&mesh, method, |p| *p).unwrap();
+        assert_eq!(param.num_charts(), 1);
+
+        let vertices = mesh.vertices().collect::<Vec<_>>();
+        for a in &vertices {
+            for b in &vertices {
+                let p = sub(to_f64(*mesh.vertex_data(a)), to_f64(*mesh.vertex_data(b)));
+                let (ua, ub) = (param.uv(a), param.uv(b));
+                let q = [(ua[0] - ub[0]) as f64, (ua[1] - ub[1]) as f64];
+                let expected = dot(p, p).sqrt();
+                let found = (q[0] * q[0] + q[1] * q[1]).sqrt();
+                assert!((expected - found).abs() < 1e-4, "{:?}", method);
+            }
+        }
+
+        let distortion = param.distortion(&mesh, |p| *p);
+        assert_eq!(distortion.flipped_faces, 0);
+        assert!(distortion.max_angle_error < 1e-4);
+        assert!((distortion.max_conformal - 1.0).abs() < 1e-4);
+        assert!((distortion.max_area - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn lscm_of_flat_patch_is_isometric() {
+        assert_isometric(ParamMethod::Lscm);
+    }
+
+    #[test]
+    fn arap_of_flat_patch_is_isometric() {
+        assert_isometric(ParamMethod::Arap { iterations: 5 });
+    }
+}
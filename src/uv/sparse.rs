@@ -0,0 +1,147 @@
+/// symmetric sparse matrix in CSR form, only used by the linear solvers of this module
+pub(crate) struct SparseMatrix {
+    size: usize,
+    row_start: Vec<usize>,
+    cols: Vec<usize>,
+    values: Vec<f64>,
+    diagonal: Vec<f64>,
+}
+
+impl SparseMatrix {
+    /// build from `(row, col, value)` triplets, duplicated entries are summed
+    pub(crate) fn from_triplets(size: usize, mut triplets: Vec<(usize, usize, f64)>) -> Self {
+        triplets.sort_unstable_by_key(|(row, col, _)| (*row, *col));
+
+        let mut row_start = vec![0; size + 1];
+        let mut cols = Vec::with_capacity(triplets.len());
+        let mut values: Vec<f64> = Vec::with_capacity(triplets.len());
+        let mut diagonal = vec![0.0; size];
+        let mut last = None;
+        for (row, col, value) in triplets {
+            if row == col {
+                diagonal[row] += value;
+            }
+            if last == Some((row, col)) {
+                *values.last_mut().unwrap() += value;
+            } else {
+                cols.push(col);
+                values.push(value);
+                row_start[row + 1] += 1;
+                last = Some((row, col));
+            }
+        }
+        for i in 0..size {
+            row_start[i + 1] += row_start[i];
+        }
+
+        Self {
+            size,
+            row_start,
+            cols,
+            values,
+            diagonal,
+        }
+    }
+
+    pub(crate) fn mul(&self, x: &[f64], y: &mut [f64]) {
+        for (row, y) in y.iter_mut().enumerate() {
+            *y = (self.row_start[row]..self.row_start[row + 1])
+                .map(|i| self.values[i] * x[self.cols[i]])
+                .sum();
+        }
+    }
+
+    /// solve `self * x = b` with Jacobi-preconditioned conjugate gradient, `x` is used as initial guess
+    ///
+    /// return `false` if the solver didn't converge
+    pub(crate) fn solve(&self, b: &[f64], x: &mut [f64]) -> bool {
+        let n = self.size;
+        if n == 0 {
+            return true;
+        }
+
+        let b_norm = dot(b, b).sqrt();
+        if b_norm == 0.0 {
+            x.iter_mut().for_each(|x| *x = 0.0);
+            return true;
+        }
+        let tolerance = 1e-10 * b_norm;
+
+        let inv_diagonal = self
+            .diagonal
+            .iter()
+            .map(|d| if *d != 0.0 { 1.0 / d } else { 1.0 })
+            .collect::<Vec<_>>();
+
+        let mut r = vec![0.0; n];
+        self.mul(x, &mut r);
+        r.iter_mut().zip(b).for_each(|(r, b)| *r = b - *r);
+        let mut z = r
+            .iter()
+            .zip(&inv_diagonal)
+            .map(|(r, d)| r * d)
+            .collect::<Vec<_>>();
+        let mut p = z.clone();
+        let mut ap = vec![0.0; n];
+        let mut rz = dot(&r, &z);
+
+        for _ in 0..(10 * n).max(1000) {
+            if dot(&r, &r).sqrt() <= tolerance {
+                return true;
+            }
+            self.mul(&p, &mut ap);
+            let pap = dot(&p, &ap);
+            if pap.abs() < f64::MIN_POSITIVE {
+                break;
+            }
+            let alpha = rz / pap;
+            for i in 0..n {
+                x[i] += alpha * p[i];
+                r[i] -= alpha * ap[i];
+                z[i] = r[i] * inv_diagonal[i];
+            }
+            let rz_new = dot(&r, &z);
+            let beta = rz_new / rz;
+            rz = rz_new;
+            for i in 0..n {
+                p[i] = z[i] + beta * p[i];
+            }
+        }
+
+        dot(&r, &r).sqrt() <= tolerance * 1e3
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conjugate_gradient_converges() {
+        // path graph laplacian with a grounded first vertex, symmetric positive definite
+        let n = 50;
+        let mut triplets = vec![(0, 0, 1.0)];
+        for i in 0..n - 1 {
+            triplets.extend([
+                (i, i, 1.0),
+                (i + 1, i + 1, 1.0),
+                (i, i + 1, -1.0),
+                (i + 1, i, -1.0),
+            ]);
+        }
+        let matrix = SparseMatrix::from_triplets(n, triplets);
+        let expected = (0..n).map(|i| (i as f64 * 0.3).sin()).collect::<Vec<_>>();
+        let mut b = vec![0.0; n];
+        matrix.mul(&expected, &mut b);
+
+        let mut x = vec![0.0; n];
+        assert!(matrix.solve(&b, &mut x));
+        for (x, expected) in x.iter().zip(&expected) {
+            assert!((x - expected).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+#![cfg(feature = "derive")]
+
+use std::collections::HashMap;
+
+use pep_mesh::{
+    halfedge::HalfEdgeMesh,
+    io::{
+        ply::{self, FromPropertyMap, Property, PropertyMap, SaveOptions, ToPropertyMap},
+        LoadError,
+    },
+};
+
+#[derive(Debug, Default, Clone, PartialEq, FromPropertyMap, ToPropertyMap)]
+struct VData {
+    #[ply(x, y, z)]
+    position: [f32; 3],
+    #[ply(rename = "quality", default = 1.0)]
+    weight: f64,
+    #[ply(red, green, blue)]
+    color: [u8; 3],
+    #[ply(skip)]
+    visited: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, FromPropertyMap, ToPropertyMap)]
+struct FData {
+    material: u16,
+}
+
+fn quad() -> HalfEdgeMesh<VData, (), FData> {
+    let vertices = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ]
+    .iter()
+    .enumerate()
+    .map(|(v, p)| {
+        let data = VData {
+            position: *p,
+            weight: 0.25 * v as f64,
+            color: [v as u8 * 60, 255, 0],
+            visited: true,
+        };
+        (v, data)
+    })
+    .collect();
+    let mut faces_data = HashMap::new();
+    faces_data.insert(0, FData { material: 7 });
+    HalfEdgeMesh::new(vec![vec![0, 1, 2, 3]], vertices, HashMap::new(), faces_data)
+}
+
+#[test]
+fn round_trip_through_ply() {
+    let mesh = quad();
+    let props = mesh
+        .vertex_data(&mesh.vertices().next().unwrap())
+        .to_proprety_map();
+    let names = props.map.keys().map(|key| key.as_ref()).collect::<Vec<_>>();
+    assert_eq!(names, ["x", "y", "z", "quality", "red", "green", "blue"]);
+    assert_eq!(props.map["red"], Property::U8(0));
+
+    for encoding in [ply::Encoding::Ascii, ply::Encoding::BinaryLittleEndian] {
+        let options = SaveOptions {
+            encoding,
+            ..Default::default()
+        };
+        let mut bytes = vec![];
+        ply::save_halfedge_to_writer(&mut bytes, &mesh, &options).unwrap();
+        let loaded: HalfEdgeMesh<VData, (), FData> =
+            ply::load_to_halfedge_from_reader(bytes.as_slice()).unwrap();
+
+        let data = |mesh: &HalfEdgeMesh<VData, (), FData>| {
+            let mut vertices = mesh
+                .vertices()
+                .map(|vref| {
+                    let mut data = mesh.vertex_data(&vref).clone();
+                    data.visited = false;
+                    data
+                })
+                .collect::<Vec<_>>();
+            vertices.sort_by(|a, b| a.weight.total_cmp(&b.weight));
+            let faces = mesh
+                .faces()
+                .filter(|face| !face.is_boundary(mesh))
+                .map(|face| mesh.face_data(&face).clone())
+                .collect::<Vec<_>>();
+            (vertices, faces)
+        };
+        assert_eq!(data(&loaded), data(&mesh));
+    }
+}
+
+#[test]
+fn missing_and_mismatched_properties() {
+    let mut props = PropertyMap::default();
+    props.map.insert("x".into(), Property::F32(2.0));
+    props.map.insert("red".into(), Property::I32(300));
+
+    // missing properties get defaults, mismatches are errors
+    assert!(VData::try_from_proprety_map(props.clone()).is_err());
+    let data = VData::from_proprety_map(props.clone());
+    assert_eq!(data.position, [2.0, 0.0, 0.0]);
+    assert_eq!(data.weight, 1.0);
+    assert_eq!(data.color, [0, 0, 0]);
+
+    props.map.insert("red".into(), Property::U8(200));
+    let data = VData::try_from_proprety_map(props).unwrap();
+    assert_eq!(data.color, [200, 0, 0]);
+
+    // a loader reports the record that can't be converted
+    let file = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+                property float z\nproperty float red\nelement face 1\n\
+                property list uchar int vertex_index\nend_header\n\
+                0 0 0 0\n1 0 0 0.5\n0 1 0 0\n3 0 1 2\n";
+    let result = ply::load_to_halfedge_from_reader::<_, VData, (), ()>(file.as_bytes());
+    assert!(matches!(
+        result,
+        Err(LoadError::Parse { element: Some(ref element), .. }) if element == "vertex"
+    ));
+}